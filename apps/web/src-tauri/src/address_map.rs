@@ -0,0 +1,330 @@
+// Per-channel Modbus address map of the loaded racks, the table PLC engineers
+// otherwise maintain by hand. Addresses are derived from the same process
+// image layout the server answers from, so the report matches what a master
+// reads and writes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::catalog::ModuleKind;
+use crate::state::{DataArea, ImageRef, Simulator};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+    #[serde(alias = "md")]
+    Markdown,
+}
+
+/// Where one channel lives in the Modbus address space
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressMapEntry {
+    pub rack: String,
+    pub unit_id: u8,
+    pub slot: u16,
+    pub module_id: String,
+    pub module_number: String,
+    pub channel: u16,
+    /// Signal name from the module's `channels` in the config
+    pub signal: Option<String>,
+    /// Table the channel is accessed through: bits for digital channels, else registers
+    pub area: DataArea,
+    /// Bit address for digital channels, else the first register
+    pub address: u16,
+    /// Width of the value: 1 for digital channels, 16 or 32 for registers
+    pub bits: u8,
+    /// Register image holding the channel, also for digital channels
+    pub register_area: DataArea,
+    pub register: u16,
+    /// Bit within `register` for digital channels
+    pub register_bit: Option<u8>,
+    /// Raw-to-engineering range, e.g. `0x0000..0x7FF8 = 4..20 mA`
+    pub scaling: Option<String>,
+}
+
+/// Address map of every rack, inputs before outputs, in process image order.
+/// Registers are given at the WAGO addresses with `mirror_areas`, else at the
+/// `modbus_map` bases; output bits at their read-back address.
+pub fn build(sim: &Simulator) -> Vec<AddressMapEntry> {
+    let mut entries = Vec::new();
+    for rack in 0..sim.racks.len() {
+        // Bit numbering follows `read_discrete_inputs` and `read_coils`:
+        // digital modules in slot order
+        let mut bit_base = HashMap::new();
+        let (mut input_bits, mut output_bits) = (0, 0);
+        for index in sim.rack_module_range(rack) {
+            let module = sim.modules[index].as_ref();
+            let number = module.get_config().module_number.as_str();
+            if sim.is_digital_input(number) {
+                bit_base.insert(index, input_bits);
+                input_bits += module.get_input_image_size() * 8;
+            }
+            if sim.is_digital_output(number) {
+                bit_base.insert(index, output_bits);
+                output_bits += module.get_state().channels.len();
+            }
+        }
+
+        let kind = |index: usize| sim.module_kind(&sim.modules[index].get_config().module_number);
+        for (index, offset, len) in sim.input_module_slices(rack) {
+            // Pulse outputs echo their data words in the input image
+            if matches!(kind(index), Some(ModuleKind::Pwm | ModuleKind::PulseTrain)) {
+                continue;
+            }
+            entries.extend(module_entries(sim, rack, index, offset, len, false, bit_base.get(&index).copied()));
+        }
+        for (index, offset, len) in sim.output_module_slices(rack).0 {
+            // Counters have control words in the output image, not channels
+            if kind(index) == Some(ModuleKind::Counter) {
+                continue;
+            }
+            entries.extend(module_entries(sim, rack, index, offset, len, true, bit_base.get(&index).copied()));
+        }
+    }
+    entries
+}
+
+/// Entries for the channels of one module whose data starts at image byte
+/// `offset`. Digital channels are bits; word channels take one register each
+/// (see `Module::word_offset`), or two when the module carries 32-bit values
+/// (see `Module::dword_offsets`). Channels without data of their own are left out.
+fn module_entries(
+    sim: &Simulator,
+    rack: usize,
+    index: usize,
+    offset: usize,
+    len: usize,
+    output: bool,
+    bit_base: Option<usize>,
+) -> Vec<AddressMapEntry> {
+    let module = sim.modules[index].as_ref();
+    let config = module.get_config();
+    let image = |item| if output { ImageRef::Output(item) } else { ImageRef::Input(item) };
+
+    (0..module.get_state().channels.len())
+        .filter_map(|ch| {
+            let (byte, bits) = match bit_base {
+                Some(_) => (offset + ch / 8, 1),
+                None => match module.dword_offsets().get(ch) {
+                    Some(&dword) => (offset + dword, 32),
+                    None => (offset + module.word_offset(ch)?, 16),
+                },
+            };
+            if byte >= offset + len {
+                return None;
+            }
+            let (register_area, register) = sim.register_address(image(byte / 2))?;
+            let (area, address, register_bit) = match bit_base {
+                Some(base) => (
+                    if output { DataArea::Coils } else { DataArea::DiscreteInputs },
+                    sim.bit_address(image(base + ch))?,
+                    Some(sim.register_bit(byte, (ch % 8) as u8)),
+                ),
+                None => (register_area, register, None),
+            };
+            Some(AddressMapEntry {
+                rack: sim.racks[rack].config.id.clone(),
+                unit_id: sim.racks[rack].config.coupler.unit_id,
+                slot: config.slot_position,
+                module_id: config.id.clone(),
+                module_number: config.module_number.clone(),
+                channel: ch as u16,
+                signal: config
+                    .channels
+                    .iter()
+                    .find(|definition| definition.ch as usize == ch)
+                    .map(|definition| definition.signal.name.clone()),
+                area,
+                address,
+                bits,
+                register_area,
+                register,
+                register_bit,
+                scaling: module.scaling(ch as u16).map(|scaling| scaling.to_string()),
+            })
+        })
+        .collect()
+}
+
+fn area_name(area: DataArea) -> &'static str {
+    match area {
+        DataArea::Coils => "coils",
+        DataArea::DiscreteInputs => "discrete_inputs",
+        DataArea::InputRegisters => "input_registers",
+        DataArea::HoldingRegisters => "holding_registers",
+    }
+}
+
+pub fn render(entries: &[AddressMapEntry], format: ReportFormat) -> Result<String, String> {
+    match format {
+        ReportFormat::Json => serde_json::to_string_pretty(entries).map_err(|e| e.to_string()),
+        ReportFormat::Csv => Ok(to_csv(entries)),
+        ReportFormat::Markdown => Ok(to_markdown(entries)),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn to_csv(entries: &[AddressMapEntry]) -> String {
+    let mut csv = String::from(
+        "rack,unit_id,slot,module_id,module_number,channel,signal,area,address,bits,register_area,register,register_bit,scaling\n",
+    );
+    for e in entries {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&e.rack),
+            e.unit_id,
+            e.slot,
+            csv_field(&e.module_id),
+            csv_field(&e.module_number),
+            e.channel,
+            csv_field(e.signal.as_deref().unwrap_or("")),
+            area_name(e.area),
+            e.address,
+            e.bits,
+            area_name(e.register_area),
+            e.register,
+            e.register_bit.map(|bit| bit.to_string()).unwrap_or_default(),
+            csv_field(e.scaling.as_deref().unwrap_or("")),
+        );
+    }
+    csv
+}
+
+fn to_markdown(entries: &[AddressMapEntry]) -> String {
+    let mut md = String::from("# Modbus Address Map\n");
+    let mut rack = None;
+    for e in entries {
+        if rack != Some(&e.rack) {
+            rack = Some(&e.rack);
+            let _ = write!(
+                md,
+                "\n## Rack `{}` (unit {})\n\n\
+                 | Slot | Module | Channel | Signal | Area | Address | Register | Scaling |\n\
+                 |------|--------|---------|--------|------|---------|----------|---------|\n",
+                e.rack, e.unit_id
+            );
+        }
+        let register = match e.register_bit {
+            Some(bit) => format!("{} 0x{:04X}.{}", area_name(e.register_area), e.register, bit),
+            None => format!("{} 0x{:04X}", area_name(e.register_area), e.register),
+        };
+        let _ = writeln!(
+            md,
+            "| {} | {} ({}) | {} | {} | {} | 0x{:04X} | {} | {} |",
+            e.slot,
+            e.module_number,
+            e.module_id,
+            e.channel,
+            e.signal.as_deref().unwrap_or(""),
+            area_name(e.area),
+            e.address,
+            register,
+            e.scaling.as_deref().unwrap_or(""),
+        );
+    }
+    md
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RACK: &str = r#"
+version: 2
+sim: { name: 'test', seed: 1, tick_ms: 10 }
+transport:
+  kind: 'modbus_tcp'
+  listen: { host: '127.0.0.1', port: 0 }
+  unit_id: 1
+process_image: { layout: 'wago_750_default', word_endian: 'little', align_modules_to: 2 }
+modbus_map:
+  inputs: { kind: 'input_registers', base: 0 }
+  outputs: { kind: 'holding_registers', base: 0 }
+racks:
+  - id: 'rack0'
+    name: 'Rack 0'
+    modules:
+      - id: 'di'
+        model: '750-1415'
+        name: 'DI'
+        channels: [{ ch: 2, signal: { name: 'door_closed', type: 'bool' } }]
+      - { id: 'ai', model: '750-455', name: 'AI', channels: [{ ch: 1, signal: { name: 'flow, line 1', type: 'analog' } }] }
+      - { id: 'do', model: '750-1515', name: 'DO', channels: [] }
+      - { id: 'cnt', model: '750-404', name: 'CNT', channels: [] }
+"#;
+
+    fn entries(yaml: &str) -> Vec<AddressMapEntry> {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(yaml).unwrap();
+        build(&sim)
+    }
+
+    #[test]
+    fn test_channels_follow_the_process_image() {
+        let entries = entries(RACK);
+        // 8 DI + 4 AI + 1 counter + 8 DO
+        assert_eq!(entries.len(), 21);
+
+        // AI words, then the counter (status word, then the count), then DI
+        let ai = &entries[1];
+        assert_eq!((ai.area, ai.address, ai.bits), (DataArea::InputRegisters, 1, 16));
+        assert_eq!(ai.signal.as_deref(), Some("flow, line 1"));
+        assert_eq!(ai.scaling.as_deref(), Some("0x0000..0x7FF0 = 4..20 mA"));
+        let count = &entries[4];
+        assert_eq!((count.module_id.as_str(), count.address, count.bits), ("cnt", 5, 32));
+
+        let door = &entries[7];
+        assert_eq!(door.signal.as_deref(), Some("door_closed"));
+        assert_eq!((door.area, door.address), (DataArea::DiscreteInputs, 2));
+        assert_eq!((door.register, door.register_bit), (7, Some(2)));
+
+        // DO read back at 0x0200; counters' control words are not channels
+        let do3 = &entries[16];
+        assert_eq!((do3.module_id.as_str(), do3.area, do3.address), ("do", DataArea::Coils, 0x0203));
+        assert_eq!((do3.register_area, do3.register), (DataArea::HoldingRegisters, 0x0203));
+        assert!(entries.iter().all(|e| e.module_id != "cnt" || e.area == DataArea::InputRegisters));
+    }
+
+    #[test]
+    fn test_configured_bases_and_byte_order() {
+        let yaml = RACK
+            .replace("align_modules_to: 2 }", "byte_order_in_word: 'little', align_modules_to: 2 }")
+            .replace("outputs: { kind: 'holding_registers', base: 0 }", "outputs: { kind: 'holding_registers', base: 0x0100 }\n  mirror_areas: false");
+        let entries = entries(&yaml);
+        assert_eq!(entries[7].register_bit, Some(10));
+        assert_eq!(entries[16].address, 3);
+        assert_eq!(entries[16].register, 0x0103);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let entries = entries(RACK);
+        let csv = render(&entries, ReportFormat::Csv).unwrap();
+        assert_eq!(csv.lines().count(), 22);
+        assert!(csv.contains("\"flow, line 1\""));
+
+        let md = render(&entries, ReportFormat::Markdown).unwrap();
+        assert!(md.contains("## Rack `rack0` (unit 1)"));
+        assert!(md.contains("| 0 | 750-1415 (di) | 2 | door_closed | discrete_inputs | 0x0002 | input_registers 0x0007.2 |  |"));
+
+        let json: serde_json::Value = serde_json::from_str(&render(&entries, ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[1]["area"], "input_registers");
+        assert_eq!(json[1]["moduleNumber"], "750-455");
+    }
+}
@@ -0,0 +1,241 @@
+// Module catalog: what the simulator knows about each I/O module part number.
+// The built-in descriptors are embedded from `module_catalog.yaml`; a user
+// catalog (`module_catalog` in the sim config) replaces or adds entries by
+// part number, so new variants need no code changes. `create_module` and the
+// process image classification are driven from here.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::modules::Scaling;
+
+const EMBEDDED: &str = include_str!("module_catalog.yaml");
+
+/// Behavior a module is simulated with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleKind {
+    DigitalInput,
+    DigitalOutput,
+    AnalogInput,
+    AnalogOutput,
+    Rtd,
+    Thermocouple,
+    Counter,
+    /// PWM output: duty cycle at a fixed period
+    Pwm,
+    /// Pulse train output: pulse frequency at 50 % duty
+    PulseTrain,
+}
+
+impl ModuleKind {
+    pub fn is_digital(self) -> bool {
+        matches!(self, ModuleKind::DigitalInput | ModuleKind::DigitalOutput)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleDescriptor {
+    pub part_number: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: ModuleKind,
+    pub channels: usize,
+    /// Input process image size in bytes; derived from kind and channels when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_bytes: Option<usize>,
+    /// Output process image size in bytes; derived from kind and channels when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_bytes: Option<usize>,
+    /// Raw process image value at the low and high end of `range`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_range: Option<[i32; 2]>,
+    /// Engineering range of analog and RTD channels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Module ID in the discovery registers (0x2031+); derived when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discovery_id: Option<u16>,
+}
+
+impl ModuleDescriptor {
+    pub fn input_bytes(&self) -> usize {
+        self.input_bytes.unwrap_or(match self.kind {
+            ModuleKind::DigitalInput => self.channels.div_ceil(8),
+            ModuleKind::AnalogInput | ModuleKind::Rtd | ModuleKind::Thermocouple => self.channels * 2,
+            // Status word and 32-bit count
+            ModuleKind::Counter => 6,
+            // Status word and data word echo per channel
+            ModuleKind::Pwm | ModuleKind::PulseTrain => self.channels * 4,
+            ModuleKind::DigitalOutput | ModuleKind::AnalogOutput => 0,
+        })
+    }
+
+    pub fn output_bytes(&self) -> usize {
+        self.output_bytes.unwrap_or(match self.kind {
+            ModuleKind::DigitalOutput => self.channels.div_ceil(8),
+            ModuleKind::AnalogOutput => self.channels * 2,
+            // Control word and 32-bit preset
+            ModuleKind::Counter => 6,
+            // Control word and data word per channel
+            ModuleKind::Pwm | ModuleKind::PulseTrain => self.channels * 4,
+            ModuleKind::DigitalInput | ModuleKind::AnalogInput | ModuleKind::Rtd | ModuleKind::Thermocouple => 0,
+        })
+    }
+
+    /// Raw and engineering ranges; analog modules default to 0x0000-0x7FFF =
+    /// 0-10 V, RTDs to Pt100 and thermocouples to type K, both in 0.1 °C,
+    /// PWM outputs to 0-100 % duty and pulse trains to 1 Hz steps
+    pub fn scaling(&self) -> Scaling {
+        let (raw, range, unit) = match self.kind {
+            ModuleKind::Rtd => ([-2000, 8500], [-200.0, 850.0], "°C"),
+            ModuleKind::Thermocouple => ([-1000, 13700], [-100.0, 1370.0], "°C"),
+            ModuleKind::Pwm => ([0x0000, 0x7FFF], [0.0, 100.0], "%"),
+            ModuleKind::PulseTrain => ([0x0000, 0x7FFF], [0.0, 32767.0], "Hz"),
+            _ => ([0x0000, 0x7FFF], [0.0, 10.0], "V"),
+        };
+        let [raw_min, raw_max] = self.raw_range.unwrap_or(raw);
+        let [min, max] = self.range.unwrap_or(range);
+        Scaling { raw_min, raw_max, min, max, unit: self.unit.clone().unwrap_or_else(|| unit.to_string()) }
+    }
+
+    /// Module ID per the WAGO discovery spec:
+    /// - Digital I/O modules: 0x8000 | (channel_count << 8) | (is_output << 1) | is_input
+    /// - Analog/Special modules: part number as decimal (e.g., 455 for 750-455)
+    pub fn discovery_id(&self) -> u16 {
+        if let Some(id) = self.discovery_id {
+            return id;
+        }
+        match self.kind {
+            ModuleKind::DigitalInput => 0x8000 | ((self.channels as u16) << 8) | 0x01,
+            ModuleKind::DigitalOutput => 0x8000 | ((self.channels as u16) << 8) | 0x02,
+            _ => part_number_id(&self.part_number),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.channels == 0 {
+            return Err(format!("{}: channels must be at least 1", self.part_number));
+        }
+        if self.kind.is_digital() && self.channels > 127 {
+            return Err(format!("{}: digital modules have at most 127 channels", self.part_number));
+        }
+        if self.kind == ModuleKind::Counter && (self.input_bytes() != 6 || self.output_bytes() != 6) {
+            return Err(format!("{}: counter modules use a 6-byte image", self.part_number));
+        }
+        let scaling = self.scaling();
+        if scaling.raw_min >= scaling.raw_max || scaling.min >= scaling.max {
+            return Err(format!("{}: raw_range and range must be [min, max]", self.part_number));
+        }
+        // Signed ranges are read as two's complement
+        let raw_limit = if scaling.raw_min < 0 { i16::MAX as i32 } else { u16::MAX as i32 };
+        if !self.kind.is_digital() && (scaling.raw_min < i16::MIN as i32 || scaling.raw_max > raw_limit) {
+            return Err(format!("{}: raw_range must fit in a register", self.part_number));
+        }
+        Ok(())
+    }
+}
+
+/// Numeric part of a part number ("750-455" -> 455), 0 if there is none
+pub fn part_number_id(part_number: &str) -> u16 {
+    part_number.split('-').nth(1).and_then(|s| s.parse::<u16>().ok()).unwrap_or(0)
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogFile {
+    modules: Vec<ModuleDescriptor>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModuleCatalog {
+    modules: BTreeMap<String, ModuleDescriptor>,
+    /// User catalog file merged over the built-in one, if any
+    pub source: Option<String>,
+}
+
+impl ModuleCatalog {
+    /// The built-in catalog
+    pub fn embedded() -> Self {
+        let mut catalog = Self::default();
+        catalog.merge_yaml(EMBEDDED).expect("embedded module catalog is valid");
+        catalog
+    }
+
+    /// The built-in catalog with the user catalog at `path` merged over it
+    pub fn with_user_file(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read module catalog '{}': {}", path, e))?;
+        let mut catalog = Self::embedded();
+        catalog.merge_yaml(&text).map_err(|e| format!("Module catalog '{}': {}", path, e))?;
+        catalog.source = Some(path.to_string());
+        Ok(catalog)
+    }
+
+    /// Add the descriptors in a catalog file, replacing entries with the same part number
+    pub fn merge_yaml(&mut self, text: &str) -> Result<(), String> {
+        let file: CatalogFile = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+        for descriptor in &file.modules {
+            descriptor.validate()?;
+        }
+        for descriptor in file.modules {
+            self.modules.insert(descriptor.part_number.clone(), descriptor);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, part_number: &str) -> Option<&ModuleDescriptor> {
+        self.modules.get(part_number)
+    }
+
+    /// All descriptors, by part number
+    pub fn descriptors(&self) -> impl Iterator<Item = &ModuleDescriptor> {
+        self.modules.values()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_catalog() {
+        let catalog = ModuleCatalog::embedded();
+        let di = catalog.get("750-1405").unwrap();
+        assert_eq!((di.input_bytes(), di.output_bytes(), di.discovery_id()), (2, 0, 0x9001));
+        assert_eq!(catalog.get("750-515").unwrap().discovery_id(), 0x8402);
+        let ai = catalog.get("750-454").unwrap();
+        assert_eq!(ai.scaling().to_string(), "0x0000..0x7FF8 = 4..20 mA");
+        assert_eq!(ai.discovery_id(), 454);
+        let counter = catalog.get("750-633").unwrap();
+        assert_eq!((counter.kind, counter.input_bytes(), counter.output_bytes()), (ModuleKind::Counter, 6, 6));
+        let pwm = catalog.get("750-511").unwrap();
+        assert_eq!((pwm.input_bytes(), pwm.output_bytes(), pwm.discovery_id()), (8, 8, 511));
+        assert_eq!(pwm.scaling().to_string(), "0x0000..0x7FFF = 0..100 %");
+    }
+
+    #[test]
+    fn test_user_entries_replace_and_extend() {
+        let mut catalog = ModuleCatalog::embedded();
+        catalog
+            .merge_yaml(
+                "modules:\n\
+                 - { part_number: '750-455', kind: analog_input, channels: 4, raw_range: [0, 32767], range: [0, 20], unit: 'mA' }\n\
+                 - { part_number: '750-1400', kind: digital_input, channels: 16, description: 'custom DI' }\n",
+            )
+            .unwrap();
+        assert_eq!(catalog.get("750-455").unwrap().scaling().to_string(), "0x0000..0x7FFF = 0..20 mA");
+        assert_eq!(catalog.get("750-1400").unwrap().discovery_id(), 0x9001);
+
+        let error = catalog
+            .merge_yaml("modules:\n- { part_number: '750-9', kind: analog_input, channels: 2, range: [10, 0] }\n")
+            .unwrap_err();
+        assert!(error.contains("[min, max]"));
+        assert!(catalog.get("750-9").is_none());
+    }
+}
@@ -0,0 +1,175 @@
+// Catalog of the WAGO fieldbus couplers a rack can simulate. The coupler sets
+// how many modules a rack may hold, how large its process images may grow,
+// which function codes are answered, the watchdog time after power-up and
+// which coupler registers are served.
+
+use crate::sim_config::TransportKind;
+
+/// Fieldbus side of a coupler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fieldbus {
+    /// Modbus TCP/UDP over ETHERNET; serves the network registers 0x1031-0x1034
+    Ethernet,
+    /// Modbus RTU over RS-485
+    Serial,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CouplerModel {
+    pub part_number: &'static str,
+    pub description: &'static str,
+    pub fieldbus: Fieldbus,
+    /// I/O modules behind the coupler, including bus extensions
+    pub max_modules: usize,
+    /// Largest input and output process image, in registers each
+    pub max_image_words: usize,
+    /// Function codes answered; anything else gets exception 0x01
+    pub function_codes: &'static [u8],
+    /// Watchdog time (0x1000) after power-up in 100 ms units. The watchdog
+    /// still only runs once started.
+    pub watchdog_time: u16,
+}
+
+const G3_FUNCTION_CODES: &[u8] = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x0F, 0x10, 0x16, 0x17];
+const G4_FUNCTION_CODES: &[u8] = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x0F, 0x10, 0x16, 0x17, 0x2B];
+const RTU_FUNCTION_CODES: &[u8] = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x0F, 0x10];
+
+pub const CATALOG: &[CouplerModel] = &[
+    CouplerModel {
+        part_number: "750-352",
+        description: "ETHERNET fieldbus coupler, 3rd generation",
+        fieldbus: Fieldbus::Ethernet,
+        max_modules: 250,
+        max_image_words: 1020,
+        function_codes: G3_FUNCTION_CODES,
+        watchdog_time: 0,
+    },
+    CouplerModel {
+        part_number: "750-362",
+        description: "Modbus TCP/UDP fieldbus coupler, 4th generation",
+        fieldbus: Fieldbus::Ethernet,
+        max_modules: 250,
+        max_image_words: 1020,
+        function_codes: G4_FUNCTION_CODES,
+        watchdog_time: 0,
+    },
+    CouplerModel {
+        part_number: "750-363",
+        description: "EtherNet/IP fieldbus coupler, 4th generation (Modbus TCP/UDP)",
+        fieldbus: Fieldbus::Ethernet,
+        max_modules: 250,
+        max_image_words: 1020,
+        function_codes: G4_FUNCTION_CODES,
+        watchdog_time: 0,
+    },
+    CouplerModel {
+        part_number: "750-315",
+        description: "Modbus RTU fieldbus coupler, RS-485",
+        fieldbus: Fieldbus::Serial,
+        max_modules: 64,
+        max_image_words: 256,
+        function_codes: RTU_FUNCTION_CODES,
+        watchdog_time: 100,
+    },
+];
+
+/// Coupler used when a rack names none
+pub fn default_for(transport: TransportKind) -> &'static CouplerModel {
+    let part_number = match transport {
+        TransportKind::ModbusRtu => "750-315",
+        _ => "750-362",
+    };
+    lookup(part_number).expect("default coupler is in the catalog")
+}
+
+pub fn lookup(part_number: &str) -> Option<&'static CouplerModel> {
+    CATALOG.iter().find(|model| model.part_number == part_number)
+}
+
+impl CouplerModel {
+    pub fn supports_function(&self, function: u8) -> bool {
+        self.function_codes.contains(&function)
+    }
+
+    /// Whether the coupler can be reached over `transport`. RTU over TCP is a
+    /// gateway mode offered for any ETHERNET coupler.
+    pub fn supports_transport(&self, transport: TransportKind) -> bool {
+        match self.fieldbus {
+            Fieldbus::Ethernet => transport != TransportKind::ModbusRtu,
+            Fieldbus::Serial => transport == TransportKind::ModbusRtu,
+        }
+    }
+
+    /// Check a rack's module count and process image sizes against the coupler
+    pub fn check_limits(&self, modules: usize, input_words: usize, output_words: usize) -> Result<(), String> {
+        if modules > self.max_modules {
+            return Err(format!(
+                "{} modules exceed the {} limit of {}",
+                modules, self.part_number, self.max_modules
+            ));
+        }
+        for (image, words) in [("input", input_words), ("output", output_words)] {
+            if words > self.max_image_words {
+                return Err(format!(
+                    "{} process image of {} words exceeds the {} limit of {}",
+                    image, words, self.part_number, self.max_image_words
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSPORTS: [TransportKind; 4] = [
+        TransportKind::ModbusTcp,
+        TransportKind::ModbusRtu,
+        TransportKind::ModbusUdp,
+        TransportKind::ModbusRtuOverTcp,
+    ];
+
+    #[test]
+    fn test_default_coupler_per_transport() {
+        assert_eq!(default_for(TransportKind::ModbusRtu).part_number, "750-315");
+        for transport in [TransportKind::ModbusTcp, TransportKind::ModbusUdp, TransportKind::ModbusRtuOverTcp] {
+            assert_eq!(default_for(transport).part_number, "750-362");
+        }
+        // Every default can be reached over the transport it is chosen for
+        for transport in TRANSPORTS {
+            assert!(default_for(transport).supports_transport(transport));
+        }
+    }
+
+    #[test]
+    fn test_supported_transports() {
+        let ethernet = lookup("750-362").unwrap();
+        assert!(!ethernet.supports_transport(TransportKind::ModbusRtu));
+        assert!(ethernet.supports_transport(TransportKind::ModbusRtuOverTcp));
+        assert!(lookup("750-352").unwrap().supports_transport(TransportKind::ModbusUdp));
+
+        let serial = lookup("750-315").unwrap();
+        let reachable: Vec<TransportKind> =
+            TRANSPORTS.into_iter().filter(|&transport| serial.supports_transport(transport)).collect();
+        assert_eq!(reachable, vec![TransportKind::ModbusRtu]);
+        assert!(lookup("750-999").is_none());
+    }
+
+    #[test]
+    fn test_function_codes_and_limits() {
+        assert!(lookup("750-362").unwrap().supports_function(0x2B));
+        assert!(!lookup("750-352").unwrap().supports_function(0x2B));
+        assert!(!lookup("750-315").unwrap().supports_function(0x17));
+
+        let serial = lookup("750-315").unwrap();
+        assert!(serial.check_limits(64, 256, 256).is_ok());
+        assert!(serial.check_limits(65, 0, 0).unwrap_err().contains("65 modules"));
+        assert!(serial.check_limits(1, 0, 257).unwrap_err().contains("output process image"));
+    }
+}
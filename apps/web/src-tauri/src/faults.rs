@@ -0,0 +1,240 @@
+// Wire-level fault injection for the Modbus server. Faults are configured
+// globally or per client IP and decided per request from a seeded RNG, so a
+// run with `sim.seed` set reproduces the same sequence of faults.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Faults applied to requests. Rates are probabilities from 0.0 to 1.0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultConfig {
+    /// Fixed delay before each response
+    #[serde(default, alias = "latency_ms")]
+    pub latency_ms: u64,
+    /// Random extra delay of up to this many milliseconds
+    #[serde(default, alias = "jitter_ms")]
+    pub jitter_ms: u64,
+    /// Request is processed but no response is sent
+    #[serde(default, alias = "drop_rate")]
+    pub drop_rate: f64,
+    /// Request is answered with `exception_code` instead of being processed
+    #[serde(default, alias = "exception_rate")]
+    pub exception_rate: f64,
+    #[serde(default = "default_exception_code", alias = "exception_code")]
+    pub exception_code: u8,
+    /// Connection is closed instead of answering (TCP transports)
+    #[serde(default, alias = "close_rate")]
+    pub close_rate: f64,
+    /// Response carries a wrong transaction ID (MBAP transports)
+    #[serde(default, alias = "corrupt_transaction_id_rate")]
+    pub corrupt_transaction_id_rate: f64,
+}
+
+fn default_exception_code() -> u8 {
+    0x04 // Server Device Failure
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            latency_ms: 0,
+            jitter_ms: 0,
+            drop_rate: 0.0,
+            exception_rate: 0.0,
+            exception_code: default_exception_code(),
+            close_rate: 0.0,
+            corrupt_transaction_id_rate: 0.0,
+        }
+    }
+}
+
+/// What happens to one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultAction {
+    Respond,
+    Drop,
+    Exception(u8),
+    Close,
+    CorruptTransactionId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultDecision {
+    pub delay: Duration,
+    pub action: FaultAction,
+}
+
+/// Global and per-client fault configuration, as reported to the frontend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultState {
+    pub global: FaultConfig,
+    /// Overrides for clients by IP address; replace the global faults entirely
+    pub clients: HashMap<String, FaultConfig>,
+}
+
+pub struct FaultInjector {
+    state: FaultState,
+    rng: SplitMix64,
+}
+
+impl FaultInjector {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            state: FaultState::default(),
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Restart the random sequence, e.g. after loading a config with `sim.seed`.
+    pub fn reseed(&mut self, seed: Option<u64>) {
+        self.rng = SplitMix64::new(seed);
+    }
+
+    pub fn state(&self) -> &FaultState {
+        &self.state
+    }
+
+    /// Set the faults for one client IP, or the global faults when `client` is `None`.
+    pub fn set(&mut self, client: Option<String>, config: FaultConfig) {
+        match client {
+            Some(client) => {
+                self.state.clients.insert(client, config);
+            }
+            None => self.state.global = config,
+        }
+    }
+
+    /// Remove one client's override, or every fault when `client` is `None`.
+    pub fn clear(&mut self, client: Option<&str>) {
+        match client {
+            Some(client) => {
+                self.state.clients.remove(client);
+            }
+            None => self.state = FaultState::default(),
+        }
+    }
+
+    /// Decide the fate of one request from `peer` (`None` on a serial bus).
+    /// Faults are rolled in order close, drop, exception, corrupt; the first hit wins.
+    pub fn decide(&mut self, peer: Option<IpAddr>) -> FaultDecision {
+        let config = peer
+            .and_then(|ip| self.state.clients.get(&ip.to_string()))
+            .unwrap_or(&self.state.global);
+
+        let mut delay = config.latency_ms;
+        if config.jitter_ms > 0 {
+            delay += self.rng.next() % (config.jitter_ms + 1);
+        }
+
+        let action = if self.rng.roll(config.close_rate) {
+            FaultAction::Close
+        } else if self.rng.roll(config.drop_rate) {
+            FaultAction::Drop
+        } else if self.rng.roll(config.exception_rate) {
+            FaultAction::Exception(config.exception_code)
+        } else if self.rng.roll(config.corrupt_transaction_id_rate) {
+            FaultAction::CorruptTransactionId
+        } else {
+            FaultAction::Respond
+        };
+
+        FaultDecision {
+            delay: Duration::from_millis(delay),
+            action,
+        }
+    }
+}
+
+/// Small deterministic PRNG; good enough for fault rolls and needs no extra crate.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: Option<u64>) -> Self {
+        Self(seed.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+        }))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// True with probability `rate`; a zero rate does not advance the sequence.
+    fn roll(&mut self, rate: f64) -> bool {
+        if rate <= 0.0 {
+            return false;
+        }
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < rate
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(injector: &mut FaultInjector, peer: Option<IpAddr>) -> Vec<FaultDecision> {
+        (0..50).map(|_| injector.decide(peer)).collect()
+    }
+
+    #[test]
+    fn test_no_faults_by_default() {
+        let mut injector = FaultInjector::new(Some(1));
+        let decision = injector.decide(None);
+        assert_eq!(decision, FaultDecision { delay: Duration::ZERO, action: FaultAction::Respond });
+    }
+
+    #[test]
+    fn test_seeded_sequence_repeats() {
+        let config = FaultConfig {
+            latency_ms: 10,
+            jitter_ms: 20,
+            drop_rate: 0.2,
+            exception_rate: 0.2,
+            exception_code: 0x06,
+            ..FaultConfig::default()
+        };
+        let mut a = FaultInjector::new(Some(42));
+        let mut b = FaultInjector::new(Some(42));
+        a.set(None, config.clone());
+        b.set(None, config);
+
+        let first = sequence(&mut a, None);
+        assert_eq!(first, sequence(&mut b, None));
+        assert!(first.iter().all(|d| (10..=30).contains(&d.delay.as_millis())));
+        assert!(first.iter().any(|d| d.action == FaultAction::Drop));
+        assert!(first.iter().any(|d| d.action == FaultAction::Exception(0x06)));
+
+        a.reseed(Some(42));
+        assert_eq!(first, sequence(&mut a, None));
+    }
+
+    #[test]
+    fn test_client_override() {
+        let mut injector = FaultInjector::new(Some(7));
+        injector.set(None, FaultConfig { drop_rate: 1.0, ..FaultConfig::default() });
+        injector.set(Some("10.0.0.5".into()), FaultConfig { close_rate: 1.0, ..FaultConfig::default() });
+
+        let plc: IpAddr = "10.0.0.5".parse().unwrap();
+        let other: IpAddr = "10.0.0.6".parse().unwrap();
+        assert_eq!(injector.decide(Some(plc)).action, FaultAction::Close);
+        assert_eq!(injector.decide(Some(other)).action, FaultAction::Drop);
+        assert_eq!(injector.decide(None).action, FaultAction::Drop);
+
+        injector.clear(Some("10.0.0.5"));
+        assert_eq!(injector.decide(Some(plc)).action, FaultAction::Drop);
+        injector.clear(None);
+        assert_eq!(injector.decide(Some(plc)).action, FaultAction::Respond);
+    }
+}
@@ -1,14 +1,24 @@
 pub mod models;
+pub mod couplers;
+pub mod catalog;
 pub mod modules;
+pub mod pdu;
 pub mod server;
 pub mod state;
 pub mod sim_config;
 pub mod scenario;
 pub mod reactive;
+pub mod faults;
+pub mod watchdog;
+pub mod registers;
+pub mod address_map;
 
-use models::{ConnectionState, ModuleInstance, RackConfig, SimulationState, ModuleState};
+use faults::{FaultConfig, FaultState};
+use models::{ChannelFault, ConnectionState, ModuleInstance, RackConfig, SimulationState, ModuleState};
 use reactive::{BehaviorDebug, ChannelRef, ForceInfo, ManualInfo, ValidationError};
-use state::{AppState, Simulator};
+use server::ServerManager;
+use state::{AppState, Simulator, TICK_MS};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tauri::{State, Manager};
 
@@ -23,9 +33,12 @@ fn get_rack_state(state: State<AppState>) -> Result<(Option<RackConfig>, Vec<Mod
 }
 
 #[tauri::command]
-fn create_rack(state: State<AppState>, name: String, description: Option<String>) -> Result<RackConfig, String> {
-    let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
-    
+async fn create_rack(
+    state: State<'_, AppState>,
+    server: State<'_, ServerManager>,
+    name: String,
+    description: Option<String>,
+) -> Result<RackConfig, String> {
     let now = chrono::Utc::now().to_rfc3339();
     let config = RackConfig {
         id: format!("rack-{}", chrono::Utc::now().timestamp_millis()),
@@ -36,13 +49,15 @@ fn create_rack(state: State<AppState>, name: String, description: Option<String>
             ip_address: "127.0.0.1".to_string(),
             modbus_port: 502,
             unit_id: 1,
+            metadata: models::CouplerMetadata::default(),
         },
         modules: vec![],
         created_at: now.clone(),
         updated_at: now,
     };
     
-    sim.load_rack(config.clone());
+    state.inner().0.lock().map_err(|e| e.to_string())?.load_rack(config.clone());
+    server.apply_config(state.inner().0.clone()).await?;
     Ok(config)
 }
 
@@ -67,33 +82,54 @@ fn list_configs() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-fn load_config(state: State<AppState>, config_path: String) -> Result<RackConfig, String> {
+async fn load_config(
+    state: State<'_, AppState>,
+    server: State<'_, ServerManager>,
+    config_path: String,
+) -> Result<RackConfig, String> {
     let content = std::fs::read_to_string(&config_path).map_err(|e| format!("Failed to read file: {}", e))?;
     
-    let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
-    sim.load_from_yaml_string(&content).map_err(|e| format!("Failed to parse YAML: {}", e))?;
-    
-    Ok(sim.config.clone().ok_or("Failed to load config".to_string())?)
+    let config = {
+        let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
+        sim.load_from_yaml_string(&content).map_err(|e| format!("Failed to parse YAML: {}", e))?;
+        sim.config.clone().ok_or("Failed to load config".to_string())?
+    };
+
+    // A new transport.listen takes effect immediately if the server is running
+    server.apply_config(state.inner().0.clone()).await?;
+    Ok(config)
 }
 
 #[tauri::command]
 fn add_module(state: State<AppState>, module_number: String, slot_position: u16) -> Result<ModuleInstance, String> {
     let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
     
+    if sim.module_descriptor(&module_number).is_none() {
+        return Err(format!("Module {} is not in the module catalog", module_number));
+    }
+    let max_modules = sim.racks.first().map_or(usize::MAX, |rack| rack.coupler.max_modules);
     if let Some(config) = &mut sim.config {
+        if config.modules.len() >= max_modules {
+            return Err(format!(
+                "Coupler {} takes at most {} modules",
+                config.coupler.module_number, max_modules
+            ));
+        }
         let id = format!("module-{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0));
         let instance = ModuleInstance {
             id: id.clone(),
             module_number,
             slot_position,
             label: None,
+            channels: Vec::new(),
+            module_config: HashMap::new(),
         };
         
         config.modules.push(instance.clone());
         config.modules.sort_by_key(|m| m.slot_position);
         
         let new_config = config.clone();
-        sim.load_rack(new_config);
+        sim.update_primary_rack(new_config);
         
         Ok(instance)
     } else {
@@ -108,7 +144,7 @@ fn remove_module(state: State<AppState>, module_id: String) -> Result<(), String
     if let Some(config) = &mut sim.config {
         config.modules.retain(|m| m.id != module_id);
         let new_config = config.clone();
-        sim.load_rack(new_config);
+        sim.update_primary_rack(new_config);
         Ok(())
     } else {
         Err("No rack configured".to_string())
@@ -116,7 +152,8 @@ fn remove_module(state: State<AppState>, module_id: String) -> Result<(), String
 }
 
 #[tauri::command]
-fn clear_rack(state: State<AppState>) -> Result<(), String> {
+async fn clear_rack(state: State<'_, AppState>, server: State<'_, ServerManager>) -> Result<(), String> {
+    server.stop(&state.inner().0).await;
     let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
     sim.clear_rack();
     Ok(())
@@ -129,92 +166,149 @@ fn set_channel_value(state: State<AppState>, module_id: String, channel: u16, va
     Ok(())
 }
 
-fn build_sim_config_from_rack(config: &RackConfig) -> sim_config::SimConfigRoot {
-    sim_config::SimConfigRoot {
+#[tauri::command]
+fn set_channel_fault(
+    state: State<AppState>,
+    module_id: String,
+    channel: u16,
+    fault: Option<ChannelFault>,
+) -> Result<(), String> {
+    let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
+    sim.set_channel_fault(&module_id, channel, fault);
+    Ok(())
+}
+
+/// Build a YAML config from the loaded racks. The first rack supplies the
+/// listen address; every rack keeps its own unit ID, and racks served on
+/// another endpoint get their own `listen` entry.
+fn build_sim_config(sim: &Simulator) -> Result<sim_config::SimConfigRoot, String> {
+    let racks: Vec<&RackConfig> = sim.racks.iter().map(|rack| &rack.config).collect();
+    let Some(config) = racks.first().copied() else {
+        return Err("No rack configured".to_string());
+    };
+    Ok(sim_config::SimConfigRoot {
         version: 2,
         sim: sim_config::SimSettings {
             name: config.name.clone(),
-            seed: None,
-            tick_ms: 10,
+            seed: sim.seed,
+            tick_ms: TICK_MS,
         },
         transport: sim_config::TransportConfig {
-            kind: "modbus_tcp".to_string(),
+            kind: sim.transport_kind,
             listen: sim_config::ListenConfig {
                 host: config.coupler.ip_address.clone(),
                 port: config.coupler.modbus_port,
             },
+            serial: sim.serial.clone(),
             unit_id: config.coupler.unit_id,
+            unknown_unit: sim.unknown_unit_policy,
         },
-        process_image: sim_config::ProcessImageConfig {
-            layout: "wago_750_default".to_string(),
-            word_endian: "big".to_string(),
-            align_modules_to: 2,
-        },
+        process_image: sim.process_image.clone(),
         modbus_map: sim_config::ModbusMapConfig {
-            inputs: sim_config::ModbusArea {
-                kind: "input_registers".to_string(),
-                base: 0,
-            },
-            outputs: sim_config::ModbusArea {
-                kind: "holding_registers".to_string(),
-                base: 0,
-            },
+            inputs: sim.input_area,
+            outputs: sim.output_area,
+            address_check: sim.address_check,
+            mirror_areas: sim.mirror_areas,
         },
-        racks: vec![sim_config::RackDefinition {
-            id: config.id.clone(),
-            name: config.name.clone(),
-            modules: config
-                .modules
-                .iter()
-                .map(|module| sim_config::ModuleDefinition {
-                    id: module.id.clone(),
-                    model: module.module_number.clone(),
-                    name: module.label.clone().unwrap_or_else(|| module.module_number.clone()),
-                    channels: Vec::new(),
-                    module_config: None,
-                })
-                .collect(),
-        }],
+        module_catalog: sim.module_catalog.source.clone(),
+        racks: sim
+            .racks
+            .iter()
+            .map(|rack| (&rack.config, &rack.watchdog))
+            .map(|(rack, watchdog)| sim_config::RackDefinition {
+                id: rack.id.clone(),
+                name: rack.name.clone(),
+                unit_id: Some(rack.coupler.unit_id),
+                listen: (rack.coupler.ip_address != config.coupler.ip_address
+                    || rack.coupler.modbus_port != config.coupler.modbus_port)
+                    .then(|| sim_config::ListenConfig {
+                        host: rack.coupler.ip_address.clone(),
+                        port: rack.coupler.modbus_port,
+                    }),
+                coupler: sim_config::CouplerDefinition {
+                    model: Some(rack.coupler.module_number.clone()),
+                    metadata: rack.coupler.metadata.clone(),
+                },
+                modules: rack
+                    .modules
+                    .iter()
+                    .map(|module| sim_config::ModuleDefinition {
+                        id: module.id.clone(),
+                        model: module.module_number.clone(),
+                        name: module.label.clone().unwrap_or_else(|| module.module_number.clone()),
+                        channels: module.channels.clone(),
+                        module_config: (!module.module_config.is_empty()).then(|| module.module_config.clone()),
+                        safe_values: watchdog.safe_values.get(&module.id).cloned().unwrap_or_default(),
+                    })
+                    .collect(),
+            })
+            .collect(),
         scenarios: None,
         reactive_scenarios: None,
-    }
+    })
 }
 
 #[tauri::command]
 fn save_config(state: State<AppState>, path: String) -> Result<(), String> {
     let sim = state.inner().0.lock().map_err(|e| e.to_string())?;
-    let config = sim.config.clone().ok_or("No rack configured".to_string())?;
-    let root = build_sim_config_from_rack(&config);
+    let root = build_sim_config(&sim)?;
     let yaml = serde_yaml::to_string(&root).map_err(|e| e.to_string())?;
     std::fs::write(&path, yaml).map_err(|e| e.to_string())?;
     Ok(())
 }
 
 #[tauri::command]
-async fn start_simulation(state: State<'_, AppState>) -> Result<(), String> {
-    let simulator = state.inner().0.clone();
-    
-    {
-        let mut sim = simulator.lock().map_err(|e| e.to_string())?;
-        if sim.simulation_state == SimulationState::Running {
-            return Ok(());
-        }
-        sim.simulation_state = SimulationState::Running;
+async fn start_simulation(state: State<'_, AppState>, server: State<'_, ServerManager>) -> Result<(), String> {
+    server.start(state.inner().0.clone()).await?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_simulation(state: State<'_, AppState>, server: State<'_, ServerManager>) -> Result<(), String> {
+    server.stop(&state.inner().0).await;
+    Ok(())
+}
+
+/// Per-channel Modbus address map of the loaded racks as `csv`, `json` or
+/// `markdown`. Also written to `path` when one is given.
+#[tauri::command]
+fn export_address_map(
+    state: State<AppState>,
+    format: address_map::ReportFormat,
+    path: Option<String>,
+) -> Result<String, String> {
+    let sim = state.inner().0.lock().map_err(|e| e.to_string())?;
+    if sim.racks.is_empty() {
+        return Err("No rack configured".to_string());
+    }
+    let report = address_map::render(&address_map::build(&sim), format)?;
+    if let Some(path) = path {
+        std::fs::write(&path, &report).map_err(|e| e.to_string())?;
     }
+    Ok(report)
+}
 
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = server::run_server(simulator, 502).await {
-            eprintln!("Modbus server error: {}", e);
-        }
-    });
-    
+// --- Fault Injection Commands ---
+
+#[tauri::command]
+fn get_faults(state: State<AppState>) -> Result<FaultState, String> {
+    let sim = state.inner().0.lock().map_err(|e| e.to_string())?;
+    Ok(sim.faults.state().clone())
+}
+
+/// Set the faults for one client IP, or the global faults when `client` is omitted.
+#[tauri::command]
+fn set_faults(state: State<AppState>, config: FaultConfig, client: Option<String>) -> Result<(), String> {
+    let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
+    sim.faults.set(client, config);
     Ok(())
 }
 
+/// Remove one client's faults, or all faults when `client` is omitted.
 #[tauri::command]
-fn stop_simulation(state: State<AppState>) -> Result<(), String> {
+fn clear_faults(state: State<AppState>, client: Option<String>) -> Result<(), String> {
     let mut sim = state.inner().0.lock().map_err(|e| e.to_string())?;
-    sim.simulation_state = SimulationState::Stopped;
+    sim.faults.clear(client.as_deref());
     Ok(())
 }
 
@@ -432,6 +526,7 @@ pub fn run() {
   tauri::Builder::default()
     .plugin(tauri_plugin_dialog::init())
     .manage(AppState(Arc::new(Mutex::new(Simulator::new()))))
+    .manage(ServerManager::new())
     .setup(|app| {
       if cfg!(debug_assertions) {
         app.handle().plugin(
@@ -445,7 +540,7 @@ pub fn run() {
       let sim = app_state.0.clone();
       tauri::async_runtime::spawn(async move {
           loop {
-              tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+              tokio::time::sleep(tokio::time::Duration::from_millis(TICK_MS)).await;
               if let Ok(mut s) = sim.lock() {
                   s.tick();
               }
@@ -463,9 +558,15 @@ pub fn run() {
         remove_module,
         clear_rack,
         set_channel_value,
+        set_channel_fault,
         save_config,
+        export_address_map,
         start_simulation,
         stop_simulation,
+        // Fault injection commands
+        get_faults,
+        set_faults,
+        clear_faults,
         // Scripted scenario commands
         list_scenarios,
         load_scenario,
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use crate::reactive::ValueSource;
+use crate::sim_config::ChannelDefinition;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -9,6 +11,39 @@ pub struct CouplerConfig {
     pub ip_address: String,
     pub modbus_port: u16,
     pub unit_id: u8,
+    /// Identity and network settings reported in the coupler registers
+    #[serde(default)]
+    pub metadata: CouplerMetadata,
+}
+
+/// Coupler information register contents; unset fields use the simulator defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CouplerMetadata {
+    /// "00:30:DE:xx:xx:xx"; defaults to the WAGO prefix plus the last three IP octets
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "mac_address")]
+    pub mac_address: Option<String>,
+    /// Defaults to 255.255.255.0
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "subnet_mask")]
+    pub subnet_mask: Option<String>,
+    /// Defaults to 0.0.0.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    /// "major.minor", e.g. "01.07"; defaults to 01.00
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "firmware_version")]
+    pub firmware_version: Option<String>,
+    /// Firmware compile date as "Mmm dd yyyy"
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "firmware_date")]
+    pub firmware_date: Option<String>,
+    /// Firmware compile time as "hh:mm:ss"
+    #[serde(default, skip_serializing_if = "Option::is_none", alias = "firmware_time")]
+    pub firmware_time: Option<String>,
+}
+
+impl CouplerMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +53,12 @@ pub struct ModuleInstance {
     pub module_number: String,
     pub slot_position: u16,
     pub label: Option<String>,
+    /// Signals wired to the channels (`channels` in YAML)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<ChannelDefinition>,
+    /// Module parameters (`module_config` in YAML)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub module_config: HashMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +86,21 @@ impl Default for ChannelValue {
     }
 }
 
+/// Fault on an I/O channel, injected or detected by the module
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelFault {
+    /// Wire break in the sensor circuit
+    OpenCircuit,
+    ShortCircuit,
+    OverRange,
+    UnderRange,
+    /// Sensor element broken (reads like an open circuit)
+    SensorBreak,
+    /// Internal module error, e.g. missing field supply
+    ModuleFault,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct ChannelState {
@@ -52,7 +108,7 @@ pub struct ChannelState {
     #[serde(default)]
     pub value: ChannelValue,
     pub raw_value: u16,
-    pub fault: Option<String>,
+    pub fault: Option<ChannelFault>,
     pub status: u8,
     /// Who/what set this value (Default, Scenario, Manual, Force)
     #[serde(default)]
@@ -107,6 +163,18 @@ pub enum SimulationState {
     Paused,
 }
 
+/// Lifecycle status of the Modbus server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum ServerStatus {
+    #[default]
+    Stopped,
+    /// `address` lists every endpoint, comma-separated, when racks use several listeners
+    Binding { address: String },
+    Listening { address: String },
+    Failed { reason: String },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ModbusClientInfo {
@@ -134,4 +202,11 @@ impl ModbusClientInfo {
 pub struct ConnectionState {
     pub modbus_clients: Vec<ModbusClientInfo>,
     pub last_activity: u64,
+    /// Address the Modbus server is actually bound to (None when not listening)
+    pub listen_address: Option<String>,
+    /// All bound listener addresses when racks are served on separate endpoints
+    pub listen_addresses: Vec<String>,
+    pub server_status: ServerStatus,
+    /// IDs of racks whose watchdog has timed out (outputs at safe values)
+    pub watchdog_tripped_racks: Vec<String>,
 }
@@ -1,4 +1,7 @@
-use crate::models::{ChannelState, ModuleInstance, ModuleState, ChannelValue};
+use crate::catalog::{ModuleCatalog, ModuleDescriptor, ModuleKind};
+use crate::models::{ChannelFault, ChannelState, ModuleInstance, ModuleState, ChannelValue};
+use crate::sim_config::ScalingOverride;
+use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub trait Module: Send + Sync {
@@ -13,6 +16,256 @@ pub trait Module: Send + Sync {
     fn get_output_image_size(&self) -> usize; // in bytes
     fn read_inputs(&self) -> Vec<u8>;
     fn write_outputs(&mut self, data: &[u8]);
+
+    /// Byte offsets of 32-bit values in the input and output data. Modules
+    /// report them low word first; `process_image.word_endian` may swap the words.
+    fn dword_offsets(&self) -> &'static [usize] {
+        &[]
+    }
+
+    /// Byte offset of word channel `channel` in the module's data, `None`
+    /// for channels without a word of their own
+    fn word_offset(&self, channel: usize) -> Option<usize> {
+        Some(channel * 2)
+    }
+
+    /// Inject a fault on a channel, or clear it with `None`. Modules without
+    /// diagnostics ignore faults.
+    fn set_channel_fault(&mut self, _channel: u16, _fault: Option<ChannelFault>) {}
+
+    /// Raw-to-engineering scaling of a channel, for address map reports.
+    /// `None` for modules without a scaled value (digital I/O, pulse counts).
+    fn scaling(&self, _channel: u16) -> Option<Scaling> {
+        None
+    }
+
+    /// Let `elapsed_ms` of simulated time pass, for modules whose data
+    /// changes over time on its own (counters accumulating pulses)
+    fn advance(&mut self, _elapsed_ms: u64) {}
+}
+
+/// Raw process image range and the engineering range it represents
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scaling {
+    pub raw_min: i32,
+    pub raw_max: i32,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+}
+
+impl std::fmt::Display for Scaling {
+    /// e.g. `0x0000..0x7FF8 = 4..20 mA`; signed raw ranges are shown in decimal
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.raw_min < 0 {
+            write!(f, "{}..{}", self.raw_min, self.raw_max)?;
+        } else {
+            write!(f, "0x{:04X}..0x{:04X}", self.raw_min, self.raw_max)?;
+        }
+        write!(f, " = {}..{} {}", self.min, self.max, self.unit)
+    }
+}
+
+/// Conversion between the raw value and the engineering value of one analog
+/// channel: EU = scale * raw + offset. Built from the catalog scaling with the
+/// module's and the channel's `ScalingOverride`s applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelScaling {
+    raw_min: i32,
+    raw_max: i32,
+    /// Engineering values at `raw_min` and `raw_max`
+    value_at_min: f64,
+    value_at_max: f64,
+    /// Engineering range values are clamped to before the raw range, if any
+    limits: Option<(f64, f64)>,
+    unit: String,
+}
+
+impl ChannelScaling {
+    pub fn new(base: &Scaling, overrides: &ScalingOverride) -> Self {
+        let min = overrides.min_value.unwrap_or(base.min);
+        let max = overrides.max_value.unwrap_or(base.max);
+        let explicit = overrides.scale.is_some() || overrides.offset.is_some();
+        let (value_at_min, value_at_max, limits) = if explicit {
+            let scale = overrides.scale.unwrap_or((max - min) / (base.raw_max - base.raw_min) as f64);
+            let offset = overrides.offset.unwrap_or(min - scale * base.raw_min as f64);
+            // An explicit scale or offset only clamps to an explicitly given range
+            let limits = (overrides.min_value.is_some() || overrides.max_value.is_some()).then(|| {
+                (overrides.min_value.unwrap_or(f64::MIN), overrides.max_value.unwrap_or(f64::MAX))
+            });
+            (scale * base.raw_min as f64 + offset, scale * base.raw_max as f64 + offset, limits)
+        } else {
+            // The raw range ends at min and max
+            (min, max, None)
+        };
+        Self {
+            raw_min: base.raw_min,
+            raw_max: base.raw_max,
+            value_at_min,
+            value_at_max,
+            limits,
+            unit: overrides.units.clone().unwrap_or_else(|| base.unit.clone()),
+        }
+    }
+
+    /// Scaling of each of a module's channels: catalog ranges, then
+    /// `module_config`, then the channel's signal definition
+    fn for_channels(config: &ModuleInstance, descriptor: &ModuleDescriptor) -> Vec<Self> {
+        let base = descriptor.scaling();
+        let module = ScalingOverride::from_module_config(&config.module_config).unwrap_or_default();
+        (0..descriptor.channels)
+            .map(|ch| {
+                let overrides = config
+                    .channels
+                    .iter()
+                    .find(|definition| definition.ch as usize == ch)
+                    .map_or_else(|| module.clone(), |definition| definition.signal.scaling.or(&module));
+                Self::new(&base, &overrides)
+            })
+            .collect()
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        match self.limits {
+            Some((min, max)) => value.clamp(min, max),
+            None => value,
+        }
+    }
+
+    fn raw_to_value_i32(&self, raw: i32) -> f64 {
+        let raw = raw.clamp(self.raw_min, self.raw_max);
+        let span = self.value_at_max - self.value_at_min;
+        self.clamp(self.value_at_min + (raw - self.raw_min) as f64 * span / (self.raw_max - self.raw_min) as f64)
+    }
+
+    /// Register value for an engineering value, clamped to the raw range.
+    /// Signed ranges are encoded in two's complement.
+    pub fn value_to_raw(&self, value: f64) -> u16 {
+        let span = self.value_at_max - self.value_at_min;
+        let norm = (self.clamp(value) - self.value_at_min) / span;
+        let raw = (self.raw_min as f64 + norm * (self.raw_max - self.raw_min) as f64).round();
+        raw.clamp(self.raw_min as f64, self.raw_max as f64) as i32 as u16
+    }
+
+    /// Over- or under-range fault of an engineering value that lies beyond
+    /// the raw range (after clamping to the limits, if any)
+    pub fn range_fault(&self, value: f64) -> Option<ChannelFault> {
+        let span = self.value_at_max - self.value_at_min;
+        let norm = (self.clamp(value) - self.value_at_min) / span;
+        let raw = (self.raw_min as f64 + norm * (self.raw_max - self.raw_min) as f64).round();
+        if raw > self.raw_max as f64 {
+            Some(ChannelFault::OverRange)
+        } else if raw < self.raw_min as f64 {
+            Some(ChannelFault::UnderRange)
+        } else {
+            None
+        }
+    }
+
+    /// Engineering value of a register value
+    pub fn raw_to_value(&self, raw: u16) -> f64 {
+        let raw = if self.raw_min < 0 { raw as i16 as i32 } else { raw as i32 };
+        self.raw_to_value_i32(raw)
+    }
+
+    /// The raw range and the engineering values at its ends
+    pub fn scaling(&self) -> Scaling {
+        Scaling {
+            raw_min: self.raw_min,
+            raw_max: self.raw_max,
+            min: self.raw_to_value_i32(self.raw_min),
+            max: self.raw_to_value_i32(self.raw_max),
+            unit: self.unit.clone(),
+        }
+    }
+}
+
+/// Check the parameters of a module instance before it is created
+pub fn check_module_config(config: &ModuleInstance, descriptor: &ModuleDescriptor) -> Result<(), String> {
+    ScalingOverride::from_module_config(&config.module_config)?;
+    for definition in &config.channels {
+        definition
+            .signal
+            .scaling
+            .validate()
+            .map_err(|e| format!("channel {}: {}", definition.ch, e))?;
+    }
+    match descriptor.kind {
+        ModuleKind::AnalogInput => {
+            config_value(&config.module_config, "status_byte", false)?;
+        }
+        ModuleKind::Rtd => {
+            RtdSettings::from_module_config(&config.module_config, descriptor.channels)?;
+        }
+        ModuleKind::Thermocouple => {
+            ThermocoupleSettings::from_module_config(&config.module_config, descriptor.channels)?;
+        }
+        ModuleKind::Counter => {
+            CounterSettings::from_module_config(&config.module_config)?;
+        }
+        ModuleKind::Pwm => {
+            pwm_period(&config.module_config)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `module_config` value parsed as `T`, `default` when absent
+fn config_value<T: std::str::FromStr>(config: &HashMap<String, String>, key: &str, default: T) -> Result<T, String> {
+    match config.get(key) {
+        Some(value) => value.trim().parse().map_err(|_| format!("{} '{}' is invalid", key, value)),
+        None => Ok(default),
+    }
+}
+
+// --- Analog input diagnostics (analog, RTD and thermocouple inputs) ---
+
+/// Status byte bits of an analog input channel
+pub const STATUS_UNDERRANGE: u8 = 0x01;
+pub const STATUS_OVERRANGE: u8 = 0x02;
+pub const STATUS_ERROR: u8 = 0x40;
+
+/// Raw value reported on a wire break (upscale burnout) or module error
+const RAW_ERROR: u16 = 0x7FFF;
+
+/// `module_config` value holding one `what` for all channels or a
+/// comma-separated one per channel, `default` when absent
+fn per_channel<T: Copy>(
+    config: &HashMap<String, String>,
+    key: &str,
+    what: &str,
+    channels: usize,
+    default: T,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Vec<T>, String> {
+    let Some(value) = config.get(key) else {
+        return Ok(vec![default; channels]);
+    };
+    let values = value
+        .split(',')
+        .map(|name| parse(name.trim()).ok_or_else(|| format!("unknown {} '{}'", what, name.trim())))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values.len() {
+        1 => Ok(vec![values[0]; channels]),
+        n if n == channels => Ok(values),
+        n => Err(format!("{} {}s given for {} channels", n, what, channels)),
+    }
+}
+
+/// Input image of channels measured as (raw value, status byte). With
+/// `status_byte` every channel takes two words: the status byte (high byte 0),
+/// then the value.
+fn measured_image(measurements: impl Iterator<Item = (u16, u8)>, status_byte: bool, image_size: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(image_size);
+    for (raw, status) in measurements {
+        if status_byte {
+            bytes.extend_from_slice(&[status, 0]);
+        }
+        bytes.extend_from_slice(&raw.to_le_bytes());
+    }
+    bytes.resize(image_size, 0);
+    bytes
 }
 
 fn current_time_ms() -> u64 {
@@ -26,15 +279,15 @@ fn current_time_ms() -> u64 {
 pub struct DigitalInputModule {
     config: ModuleInstance,
     channels: Vec<bool>,
-    channel_count: usize,
+    image_size: usize,
 }
 
 impl DigitalInputModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
         Self {
             config,
-            channels: vec![false; channel_count],
-            channel_count,
+            channels: vec![false; descriptor.channels],
+            image_size: descriptor.input_bytes(),
         }
     }
 }
@@ -73,7 +326,7 @@ impl Module for DigitalInputModule {
     }
 
     fn get_input_image_size(&self) -> usize {
-        (self.channel_count + 7) / 8 // Ceil div
+        self.image_size
     }
 
     fn get_output_image_size(&self) -> usize {
@@ -84,7 +337,7 @@ impl Module for DigitalInputModule {
         let size = self.get_input_image_size();
         let mut bytes = vec![0u8; size];
         for (i, &val) in self.channels.iter().enumerate() {
-            if val {
+            if val && i / 8 < size {
                 let byte_idx = i / 8;
                 let bit_idx = i % 8;
                 bytes[byte_idx] |= 1 << bit_idx;
@@ -101,15 +354,15 @@ impl Module for DigitalInputModule {
 pub struct DigitalOutputModule {
     config: ModuleInstance,
     channels: Vec<bool>,
-    channel_count: usize,
+    image_size: usize,
 }
 
 impl DigitalOutputModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
         Self {
             config,
-            channels: vec![false; channel_count],
-            channel_count,
+            channels: vec![false; descriptor.channels],
+            image_size: descriptor.output_bytes(),
         }
     }
 }
@@ -152,7 +405,7 @@ impl Module for DigitalOutputModule {
     }
 
     fn get_output_image_size(&self) -> usize {
-        (self.channel_count + 7) / 8
+        self.image_size
     }
 
     fn read_inputs(&self) -> Vec<u8> {
@@ -161,7 +414,7 @@ impl Module for DigitalOutputModule {
 
     fn write_outputs(&mut self, data: &[u8]) {
         if data.is_empty() { return; }
-        for i in 0..self.channel_count {
+        for i in 0..self.channels.len() {
             let byte_idx = i / 8;
             let bit_idx = i % 8;
             if byte_idx < data.len() {
@@ -172,51 +425,49 @@ impl Module for DigitalOutputModule {
 }
 
 // --- Analog Input Module (Generic) ---
+// Values beyond the raw range and injected faults read 0x7FFF (under range:
+// the low end of the raw range), with the matching bits in the optional
+// status byte (`status_byte: 'true'` in module_config).
 pub struct AnalogInputModule {
     config: ModuleInstance,
-    values: Vec<f64>, // mA or Volts
-    channel_count: usize,
-    raw_min: u16,
-    raw_max: u16,
-    default_min: f64,
-    default_max: f64,
+    values: Vec<f64>, // Engineering units (mA or Volts unless overridden)
+    scaling: Vec<ChannelScaling>,
+    faults: Vec<Option<ChannelFault>>,
+    status_byte: bool,
+    image_size: usize,
 }
 
 impl AnalogInputModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
-        // Set module-specific raw ranges and defaults per rioservice implementation
-        let (raw_min, raw_max, default_min, default_max) = match config.module_number.as_str() {
-            "750-454" => (0x0000, 0x7FF8, 4.0, 20.0),  // 2-ch, 4-20mA, differential
-            "750-455" => (0x0000, 0x7FF0, 4.0, 20.0),  // 4-ch, 4-20mA, single-ended
-            _ => (0x0000, 0x7FFF, 0.0, 10.0),          // Default: 0-10V
-        };
-        
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
+        // Raw ranges and defaults come from the module catalog and config overrides
+        let scaling = ChannelScaling::for_channels(&config, descriptor);
+        let status_byte = config_value(&config.module_config, "status_byte", false).unwrap_or(false);
+        let words_per_channel = if status_byte { 2 } else { 1 };
+
         Self {
+            // Start at the value of raw 0 (e.g. 4 mA, 0 V)
+            values: scaling.iter().map(|channel| channel.raw_to_value(0)).collect(),
+            scaling,
+            faults: vec![None; descriptor.channels],
+            status_byte,
+            image_size: descriptor.input_bytes() * words_per_channel,
             config,
-            values: vec![default_min; channel_count],
-            channel_count,
-            raw_min,
-            raw_max,
-            default_min,
-            default_max,
         }
     }
 
-    fn value_to_raw(&self, val: f64) -> u16 {
-        // Clamp to valid range
-        let clamped = val.clamp(self.default_min, self.default_max);
-        
-        // Scale: raw = (val - minValue) / (maxValue - minValue) * (rawMax - rawMin) + rawMin
-        let range = self.default_max - self.default_min;
-        if range.abs() < f64::EPSILON {
-            return self.raw_min;
+    /// Raw value, status byte and fault of a channel
+    fn measure(&self, channel: usize) -> (u16, u8, Option<ChannelFault>) {
+        let scaling = &self.scaling[channel];
+        let value = self.values[channel];
+        match self.faults[channel].or_else(|| scaling.range_fault(value)) {
+            None => (scaling.value_to_raw(value), 0, None),
+            Some(ChannelFault::UnderRange) => {
+                (scaling.raw_min as u16, STATUS_ERROR | STATUS_UNDERRANGE, Some(ChannelFault::UnderRange))
+            }
+            Some(ChannelFault::ModuleFault) => (RAW_ERROR, STATUS_ERROR, Some(ChannelFault::ModuleFault)),
+            // Over range, wire break and short circuit (overcurrent)
+            Some(fault) => (RAW_ERROR, STATUS_ERROR | STATUS_OVERRANGE, Some(fault)),
         }
-        
-        let norm = (clamped - self.default_min) / range;
-        let raw_range = (self.raw_max - self.raw_min) as f64;
-        let raw = self.raw_min as f64 + (norm * raw_range);
-        
-        raw.round().clamp(self.raw_min as f64, self.raw_max as f64) as u16
     }
 }
 
@@ -231,11 +482,11 @@ impl Module for AnalogInputModule {
 
     fn get_state(&self) -> ModuleState {
         let channels = self.values.iter().enumerate().map(|(i, &val)| {
-            ChannelState::new(
-                i as u16,
-                ChannelValue::Number(val),
-                self.value_to_raw(val),
-            )
+            let (raw, status, fault) = self.measure(i);
+            let mut channel = ChannelState::new(i as u16, ChannelValue::Number(val), raw);
+            channel.status = status;
+            channel.fault = fault;
+            channel
         }).collect();
 
         ModuleState {
@@ -253,22 +504,34 @@ impl Module for AnalogInputModule {
         }
     }
 
+    fn set_channel_fault(&mut self, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(slot) = self.faults.get_mut(channel as usize) {
+            *slot = fault;
+        }
+    }
+
     fn get_input_image_size(&self) -> usize {
-        self.channel_count * 2
+        self.image_size
     }
 
     fn get_output_image_size(&self) -> usize {
         0
     }
 
+    fn word_offset(&self, channel: usize) -> Option<usize> {
+        Some(if self.status_byte { channel * 4 + 2 } else { channel * 2 })
+    }
+
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        self.scaling.get(channel as usize).map(ChannelScaling::scaling)
+    }
+
     fn read_inputs(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(self.channel_count * 2);
-        for &val in &self.values {
-            let raw = self.value_to_raw(val);
-            bytes.push((raw & 0xFF) as u8);
-            bytes.push((raw >> 8) as u8);
-        }
-        bytes
+        let measurements = (0..self.values.len()).map(|channel| {
+            let (raw, status, _) = self.measure(channel);
+            (raw, status)
+        });
+        measured_image(measurements, self.status_byte, self.image_size)
     }
 
     fn write_outputs(&mut self, _data: &[u8]) {
@@ -276,66 +539,29 @@ impl Module for AnalogInputModule {
 }
 
 // --- Analog Output Module (Generic) ---
+// Output faults (open load, short circuit, module fault) are diagnostics
+// only: the channel keeps the value the PLC wrote and shows the fault with
+// the error status bit.
 pub struct AnalogOutputModule {
     config: ModuleInstance,
     values: Vec<f64>,
-    channel_count: usize,
-    raw_min: u16,
-    raw_max: u16,
-    default_min: f64,
-    default_max: f64,
+    scaling: Vec<ChannelScaling>,
+    faults: Vec<Option<ChannelFault>>,
+    image_size: usize,
 }
 
 impl AnalogOutputModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
-        // Set module-specific raw ranges per rioservice implementation
-        let (raw_min, raw_max, default_min, default_max) = match config.module_number.as_str() {
-            "750-563" => (0x0000, 0x7FF8, 4.0, 20.0),  // 2-ch, 4-20mA
-            "750-555" => (0x0000, 0x7FF0, 4.0, 20.0),  // 4-ch, 4-20mA
-            _ => (0x0000, 0x7FFF, 0.0, 10.0),          // Default: 0-10V
-        };
-        
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
+        // Raw ranges and defaults come from the module catalog and config overrides
+        let scaling = ChannelScaling::for_channels(&config, descriptor);
+
         Self {
+            values: scaling.iter().map(|channel| channel.raw_to_value(0)).collect(),
+            scaling,
+            faults: vec![None; descriptor.channels],
+            image_size: descriptor.output_bytes(),
             config,
-            values: vec![default_min; channel_count],
-            channel_count,
-            raw_min,
-            raw_max,
-            default_min,
-            default_max,
-        }
-    }
-    
-    fn raw_to_value(&self, raw: u16) -> f64 {
-        // Inverse scaling: EU = scale * raw + offset
-        // where scale = (maxValue - minValue) / (rawMax - rawMin)
-        let raw_clamped = raw.clamp(self.raw_min, self.raw_max);
-        let raw_range = (self.raw_max - self.raw_min) as f64;
-        
-        if raw_range < f64::EPSILON {
-            return self.default_min;
-        }
-        
-        let norm = (raw_clamped - self.raw_min) as f64 / raw_range;
-        let value = self.default_min + (norm * (self.default_max - self.default_min));
-        
-        value.clamp(self.default_min, self.default_max)
-    }
-    
-    fn value_to_raw(&self, val: f64) -> u16 {
-        // EU -> raw conversion for display
-        let clamped = val.clamp(self.default_min, self.default_max);
-        let range = self.default_max - self.default_min;
-        
-        if range.abs() < f64::EPSILON {
-            return self.raw_min;
-        }
-        
-        let norm = (clamped - self.default_min) / range;
-        let raw_range = (self.raw_max - self.raw_min) as f64;
-        let raw = self.raw_min as f64 + (norm * raw_range);
-        
-        raw.round().clamp(self.raw_min as f64, self.raw_max as f64) as u16
+        }
     }
 }
 
@@ -349,12 +575,17 @@ impl Module for AnalogOutputModule {
     }
 
     fn get_state(&self) -> ModuleState {
-        let channels = self.values.iter().enumerate().map(|(i, &val)| {
-            ChannelState::new(
+        let channels = self.values.iter().zip(&self.scaling).enumerate().map(|(i, (&val, scaling))| {
+            let mut channel = ChannelState::new(
                 i as u16,
                 ChannelValue::Number(val),
-                self.value_to_raw(val),
-            )
+                scaling.value_to_raw(val),
+            );
+            channel.fault = self.faults[i];
+            if channel.fault.is_some() {
+                channel.status = STATUS_ERROR;
+            }
+            channel
         }).collect();
 
         ModuleState {
@@ -372,23 +603,33 @@ impl Module for AnalogOutputModule {
         }
     }
 
+    fn set_channel_fault(&mut self, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(slot) = self.faults.get_mut(channel as usize) {
+            *slot = fault;
+        }
+    }
+
     fn get_input_image_size(&self) -> usize {
         0
     }
 
     fn get_output_image_size(&self) -> usize {
-        self.channel_count * 2
+        self.image_size
     }
 
     fn read_inputs(&self) -> Vec<u8> {
         vec![]
     }
 
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        self.scaling.get(channel as usize).map(ChannelScaling::scaling)
+    }
+
     fn write_outputs(&mut self, data: &[u8]) {
-        for (i, chunk) in data.chunks(2).enumerate() {
-            if i < self.channel_count && chunk.len() == 2 {
-                let raw = (chunk[0] as u16) | ((chunk[1] as u16) << 8);
-                self.values[i] = self.raw_to_value(raw);
+        // Inverse scaling with clamping at the raw and EU level
+        for ((value, scaling), chunk) in self.values.iter_mut().zip(&self.scaling).zip(data.chunks(2)) {
+            if chunk.len() == 2 {
+                *value = scaling.raw_to_value(u16::from_le_bytes([chunk[0], chunk[1]]));
             }
         }
     }
@@ -397,38 +638,163 @@ impl Module for AnalogOutputModule {
 // --- RTD Input Module (750-461, 464) ---
 // Per rioservice: RTD modules interpret registers as SIGNED int16_t
 // 750-464: rawMin=0xF830 (-2000), rawMax=0x2134 (+8500), scale=0.1, range=-200.0 to 850.0°C (Pt100)
+// Other sensors use the same 0.1 °C encoding over their own range; resistance
+// measurement reports 0.1 Ω. Status bytes are optional, as on the thermocouple modules.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtdSensor {
+    Pt100,
+    Pt1000,
+    Ni100,
+    Ni1000,
+    Resistance,
+}
+
+impl RtdSensor {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "pt100" => Self::Pt100,
+            "pt1000" => Self::Pt1000,
+            "ni100" => Self::Ni100,
+            "ni1000" => Self::Ni1000,
+            "resistance" | "ohm" => Self::Resistance,
+            _ => return None,
+        })
+    }
+
+    /// Raw and engineering range; Pt100 channels use the catalog range of the module
+    fn scaling(self, descriptor: &ModuleDescriptor) -> Scaling {
+        let (raw_min, raw_max, min, max, unit) = match self {
+            Self::Pt100 => return descriptor.scaling(),
+            Self::Pt1000 => (-2000, 8500, -200.0, 850.0, "°C"),
+            Self::Ni100 | Self::Ni1000 => (-600, 2500, -60.0, 250.0, "°C"),
+            Self::Resistance => (100, 12000, 10.0, 1200.0, "Ω"),
+        };
+        Scaling { raw_min, raw_max, min, max, unit: unit.to_string() }
+    }
+
+    /// Resistance change per unit of the measured value (Ω/°C near 0 °C), to
+    /// turn 2-wire lead resistance into a measurement error
+    fn sensitivity(self) -> f64 {
+        match self {
+            Self::Pt100 => 0.385,
+            Self::Pt1000 => 3.85,
+            Self::Ni100 => 0.618,
+            Self::Ni1000 => 6.18,
+            Self::Resistance => 1.0,
+        }
+    }
+}
+
+/// RTD parameters from `module_config`:
+/// - `sensor`: pt100, pt1000, ni100, ni1000 or resistance, for all channels or
+///   comma-separated per channel (default pt100)
+/// - `wiring`: 2- or 3-wire connection (default 3); 2-wire channels read high
+///   by the lead resistance
+/// - `lead_resistance`: resistance of both leads together in Ω (default 0)
+/// - `status_byte`: `true` to put a status byte ahead of every value (default false)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtdSettings {
+    pub sensors: Vec<RtdSensor>,
+    pub wiring: u8,
+    pub lead_resistance: f64,
+    pub status_byte: bool,
+}
+
+impl RtdSettings {
+    pub fn from_module_config(config: &HashMap<String, String>, channels: usize) -> Result<Self, String> {
+        let wiring = config_value(config, "wiring", 3u8)?;
+        if !matches!(wiring, 2 | 3) {
+            return Err(format!("wiring '{}' must be 2 or 3", wiring));
+        }
+        let lead_resistance = config_value(config, "lead_resistance", 0.0)?;
+        if lead_resistance < 0.0 {
+            return Err(format!("lead_resistance '{}' must not be negative", lead_resistance));
+        }
+        Ok(Self {
+            sensors: per_channel(config, "sensor", "RTD sensor", channels, RtdSensor::Pt100, RtdSensor::parse)?,
+            wiring,
+            lead_resistance,
+            status_byte: config_value(config, "status_byte", false)?,
+        })
+    }
+}
+
 pub struct RTDModule {
     config: ModuleInstance,
     temperatures: Vec<f64>,
-    channel_count: usize,
+    faults: Vec<Option<ChannelFault>>,
+    scalings: Vec<Scaling>,
+    settings: RtdSettings,
+    image_size: usize,
 }
 
 impl RTDModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
+        let settings = RtdSettings::from_module_config(&config.module_config, descriptor.channels)
+            .unwrap_or_else(|_| {
+                RtdSettings::from_module_config(&HashMap::new(), descriptor.channels).expect("RTD defaults are valid")
+            });
+        let words_per_channel = if settings.status_byte { 2 } else { 1 };
         Self {
+            // Room temperature, or a Pt100 at 0 °C in resistance mode
+            temperatures: settings
+                .sensors
+                .iter()
+                .map(|&sensor| if sensor == RtdSensor::Resistance { 100.0 } else { 20.0 })
+                .collect(),
+            faults: vec![None; descriptor.channels],
+            scalings: settings.sensors.iter().map(|sensor| sensor.scaling(descriptor)).collect(),
+            image_size: descriptor.input_bytes() * words_per_channel,
+            settings,
             config,
-            temperatures: vec![20.0; channel_count],
-            channel_count,
-        }
-    }
-
-    fn temp_to_raw(&self, temp: f64) -> u16 {
-        // RTD modules use signed interpretation with fixed 0.1 scale
-        // temp_celsius * 10 = raw_value (as int16_t)
-        // Raw range: 0xF830 (-2000) to 0x2134 (+8500) for Pt100 (-200°C to 850°C)
-        const RAW_MIN: i16 = -2000; // 0xF830
-        const RAW_MAX: i16 = 8500;  // 0x2134
-        
-        // Clamp temperature
-        let clamped = temp.clamp(-200.0, 850.0);
-        
-        // Convert: raw = temp * 10
-        let raw_signed = (clamped * 10.0).round() as i16;
-        let clamped_raw = raw_signed.clamp(RAW_MIN, RAW_MAX);
-        
+        }
+    }
+
+    fn value_to_raw(scaling: &Scaling, value: f64) -> u16 {
+        // RTD modules use signed interpretation; with the default catalog range
+        // temp_celsius * 10 = raw_value (as int16_t), 0xF830 (-2000) to 0x2134
+        // (+8500) for Pt100 (-200°C to 850°C)
+        let Scaling { raw_min, raw_max, min, max, .. } = *scaling;
+
+        let norm = (value - min) / (max - min);
+        let raw_signed = (raw_min as f64 + norm * (raw_max - raw_min) as f64).round() as i32;
+        let clamped_raw = raw_signed.clamp(raw_min, raw_max) as i16;
+
         // Return as u16 (reinterpret signed as unsigned for wire format)
         clamped_raw as u16
     }
+
+    /// Raw value, status byte and fault of a channel. A wire break or module
+    /// fault reads 0x7FFF and a short circuit the lowest register value, all
+    /// flagged as errors; values outside the sensor range clamp to it.
+    fn measure(&self, channel: usize) -> (u16, u8, Option<ChannelFault>) {
+        let scaling = &self.scalings[channel];
+        let fault = self.faults[channel];
+        let mut value = match fault {
+            Some(ChannelFault::OpenCircuit | ChannelFault::SensorBreak) => {
+                return (RAW_ERROR, STATUS_ERROR | STATUS_OVERRANGE, fault);
+            }
+            Some(ChannelFault::ModuleFault) => return (RAW_ERROR, STATUS_ERROR, fault),
+            Some(ChannelFault::ShortCircuit) => {
+                let raw = if scaling.raw_min < 0 { i16::MIN as u16 } else { 0 };
+                return (raw, STATUS_ERROR | STATUS_UNDERRANGE, fault);
+            }
+            Some(ChannelFault::OverRange) => f64::INFINITY,
+            Some(ChannelFault::UnderRange) => f64::NEG_INFINITY,
+            None => self.temperatures[channel],
+        };
+        if self.settings.wiring == 2 {
+            value += self.settings.lead_resistance / self.settings.sensors[channel].sensitivity();
+        }
+        if value > scaling.max {
+            (Self::value_to_raw(scaling, scaling.max), STATUS_ERROR | STATUS_OVERRANGE, Some(ChannelFault::OverRange))
+        } else if value < scaling.min {
+            (Self::value_to_raw(scaling, scaling.min), STATUS_ERROR | STATUS_UNDERRANGE, Some(ChannelFault::UnderRange))
+        } else {
+            (Self::value_to_raw(scaling, value), 0, fault)
+        }
+    }
 }
 
 impl Module for RTDModule {
@@ -442,11 +808,11 @@ impl Module for RTDModule {
 
     fn get_state(&self) -> ModuleState {
         let channels = self.temperatures.iter().enumerate().map(|(i, &val)| {
-            ChannelState::new(
-                i as u16,
-                ChannelValue::Number(val),
-                self.temp_to_raw(val),
-            )
+            let (raw, status, fault) = self.measure(i);
+            let mut channel = ChannelState::new(i as u16, ChannelValue::Number(val), raw);
+            channel.status = status;
+            channel.fault = fault;
+            channel
         }).collect();
 
         ModuleState {
@@ -464,22 +830,238 @@ impl Module for RTDModule {
         }
     }
 
+    fn set_channel_fault(&mut self, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(slot) = self.faults.get_mut(channel as usize) {
+            *slot = fault;
+        }
+    }
+
     fn get_input_image_size(&self) -> usize {
-        self.channel_count * 2
+        self.image_size
     }
 
     fn get_output_image_size(&self) -> usize {
         0
     }
 
+    fn word_offset(&self, channel: usize) -> Option<usize> {
+        Some(if self.settings.status_byte { channel * 4 + 2 } else { channel * 2 })
+    }
+
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        self.scalings.get(channel as usize).cloned()
+    }
+
     fn read_inputs(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(self.channel_count * 2);
-        for &val in &self.temperatures {
-            let raw = self.temp_to_raw(val);
-            bytes.push((raw & 0xFF) as u8);
-            bytes.push((raw >> 8) as u8);
+        let measurements = (0..self.temperatures.len()).map(|channel| {
+            let (raw, status, _) = self.measure(channel);
+            (raw, status)
+        });
+        measured_image(measurements, self.settings.status_byte, self.image_size)
+    }
+
+    fn write_outputs(&mut self, _data: &[u8]) {
+    }
+}
+
+// --- Thermocouple Input Module (750-469, 458) ---
+// Signed 0.1 °C like the RTD modules, with an optional status byte per channel.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermocoupleType {
+    J,
+    K,
+    T,
+    E,
+    N,
+    S,
+    B,
+    R,
+}
+
+impl ThermocoupleType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.trim().to_ascii_uppercase().as_str() {
+            "J" => Self::J,
+            "K" => Self::K,
+            "T" => Self::T,
+            "E" => Self::E,
+            "N" => Self::N,
+            "S" => Self::S,
+            "B" => Self::B,
+            "R" => Self::R,
+            _ => return None,
+        })
+    }
+
+    /// Measuring range in °C
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            Self::J => (-100.0, 1200.0),
+            Self::K => (-100.0, 1370.0),
+            Self::T => (-100.0, 400.0),
+            Self::E => (-100.0, 1000.0),
+            Self::N => (-100.0, 1300.0),
+            Self::S => (0.0, 1700.0),
+            Self::B => (600.0, 1800.0),
+            Self::R => (0.0, 1700.0),
         }
-        bytes
+    }
+}
+
+/// Thermocouple parameters from `module_config`:
+/// - `thermocouple`: type for all channels, or a comma-separated type per channel (default K)
+/// - `cold_junction`: terminal (cold junction) temperature in °C (default 25)
+/// - `cold_junction_compensation`: `false` to report the hot junction
+///   temperature relative to the cold junction (default true)
+/// - `status_byte`: `true` to put a status byte ahead of every value (default false)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermocoupleSettings {
+    pub types: Vec<ThermocoupleType>,
+    pub cold_junction: f64,
+    pub compensation: bool,
+    pub status_byte: bool,
+}
+
+impl ThermocoupleSettings {
+    pub fn from_module_config(config: &HashMap<String, String>, channels: usize) -> Result<Self, String> {
+        Ok(Self {
+            types: per_channel(config, "thermocouple", "thermocouple type", channels, ThermocoupleType::K, ThermocoupleType::parse)?,
+            cold_junction: config_value(config, "cold_junction", 25.0)?,
+            compensation: config_value(config, "cold_junction_compensation", true)?,
+            status_byte: config_value(config, "status_byte", false)?,
+        })
+    }
+}
+
+pub struct ThermocoupleModule {
+    config: ModuleInstance,
+    temperatures: Vec<f64>,
+    faults: Vec<Option<ChannelFault>>,
+    settings: ThermocoupleSettings,
+    image_size: usize,
+}
+
+impl ThermocoupleModule {
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
+        let settings = ThermocoupleSettings::from_module_config(&config.module_config, descriptor.channels)
+            .unwrap_or_else(|_| ThermocoupleSettings {
+                types: vec![ThermocoupleType::K; descriptor.channels],
+                cold_junction: 25.0,
+                compensation: true,
+                status_byte: false,
+            });
+        let words_per_channel = if settings.status_byte { 2 } else { 1 };
+        Self {
+            temperatures: vec![settings.cold_junction; descriptor.channels],
+            faults: vec![None; descriptor.channels],
+            image_size: descriptor.input_bytes() * words_per_channel,
+            settings,
+            config,
+        }
+    }
+
+    fn temp_to_raw(temp: f64) -> u16 {
+        (temp * 10.0).round() as i16 as u16
+    }
+
+    /// Raw value, status byte and fault of a channel. The module compensates
+    /// the cold junction itself, so a measured temperature is independent of
+    /// it unless compensation is off. A shorted thermocouple measures the
+    /// cold junction.
+    fn measure(&self, channel: usize) -> (u16, u8, Option<ChannelFault>) {
+        let (min, max) = self.settings.types[channel].range();
+        let mut temp = match self.faults[channel] {
+            Some(ChannelFault::OpenCircuit | ChannelFault::SensorBreak) => {
+                return (RAW_ERROR, STATUS_ERROR | STATUS_OVERRANGE, self.faults[channel]);
+            }
+            Some(ChannelFault::ModuleFault) => return (RAW_ERROR, STATUS_ERROR, self.faults[channel]),
+            Some(ChannelFault::ShortCircuit) => self.settings.cold_junction,
+            Some(ChannelFault::OverRange) => f64::INFINITY,
+            Some(ChannelFault::UnderRange) => f64::NEG_INFINITY,
+            None => self.temperatures[channel],
+        };
+        if !self.settings.compensation {
+            temp -= self.settings.cold_junction;
+        }
+        if temp > max {
+            (Self::temp_to_raw(max), STATUS_ERROR | STATUS_OVERRANGE, Some(ChannelFault::OverRange))
+        } else if temp < min {
+            (Self::temp_to_raw(min), STATUS_ERROR | STATUS_UNDERRANGE, Some(ChannelFault::UnderRange))
+        } else {
+            (Self::temp_to_raw(temp), 0, self.faults[channel])
+        }
+    }
+}
+
+impl Module for ThermocoupleModule {
+    fn get_id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn get_config(&self) -> &ModuleInstance {
+        &self.config
+    }
+
+    fn get_state(&self) -> ModuleState {
+        let channels = self.temperatures.iter().enumerate().map(|(i, &val)| {
+            let (raw, status, fault) = self.measure(i);
+            let mut channel = ChannelState::new(i as u16, ChannelValue::Number(val), raw);
+            channel.status = status;
+            channel.fault = fault;
+            channel
+        }).collect();
+
+        ModuleState {
+            id: self.config.id.clone(),
+            module_number: self.config.module_number.clone(),
+            slot_position: self.config.slot_position,
+            channels,
+            last_update: current_time_ms(),
+        }
+    }
+
+    fn set_channel_value(&mut self, channel: u16, value: f64) {
+        if (channel as usize) < self.temperatures.len() {
+            self.temperatures[channel as usize] = value;
+        }
+    }
+
+    fn set_channel_fault(&mut self, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(slot) = self.faults.get_mut(channel as usize) {
+            *slot = fault;
+        }
+    }
+
+    fn get_input_image_size(&self) -> usize {
+        self.image_size
+    }
+
+    fn get_output_image_size(&self) -> usize {
+        0
+    }
+
+    fn word_offset(&self, channel: usize) -> Option<usize> {
+        Some(if self.settings.status_byte { channel * 4 + 2 } else { channel * 2 })
+    }
+
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        let (min, max) = self.settings.types.get(channel as usize)?.range();
+        Some(Scaling {
+            raw_min: Self::temp_to_raw(min) as i16 as i32,
+            raw_max: Self::temp_to_raw(max) as i16 as i32,
+            min,
+            max,
+            unit: "°C".to_string(),
+        })
+    }
+
+    fn read_inputs(&self) -> Vec<u8> {
+        let measurements = (0..self.temperatures.len()).map(|channel| {
+            let (raw, status, _) = self.measure(channel);
+            (raw, status)
+        });
+        measured_image(measurements, self.settings.status_byte, self.image_size)
     }
 
     fn write_outputs(&mut self, _data: &[u8]) {
@@ -487,24 +1069,204 @@ impl Module for RTDModule {
 }
 
 // --- Counter Module (750-404, 633) ---
+// 3-word interface: control/status byte plus a 32-bit preset/count. The
+// channels are the count (0), the CLOCK (1) and U/D (2) inputs, the
+// outputs O1 (3) and O2 (4), and the pulse signal (5) in engineering units.
+// A rising CLOCK edge counts one pulse, down while U/D is on; the signal
+// adds its pulses as simulated time passes. In frequency mode the count
+// words carry the pulse frequency instead.
+
+/// Control byte bits (output word 0)
+pub const COUNTER_CTRL_O1: u8 = 0x04;
+pub const COUNTER_CTRL_O2: u8 = 0x08;
+/// Gate closed: pulses are not counted
+pub const COUNTER_CTRL_LOCK: u8 = 0x10;
+/// Load the preset; the count stays at the preset while set
+pub const COUNTER_CTRL_SET: u8 = 0x20;
+
+/// Status byte bits (input word 0); bits 2-5 acknowledge the control bits
+pub const COUNTER_STATUS_CLOCK: u8 = 0x01;
+pub const COUNTER_STATUS_DOWN: u8 = 0x02;
+/// Count wrapped past 0xFFFFFFFF; cleared by setting the counter
+pub const COUNTER_STATUS_OVERFLOW: u8 = 0x40;
+/// Count wrapped below 0; cleared by setting the counter
+pub const COUNTER_STATUS_UNDERFLOW: u8 = 0x80;
+
+const COUNTER_CTRL_MASK: u8 = COUNTER_CTRL_O1 | COUNTER_CTRL_O2 | COUNTER_CTRL_LOCK | COUNTER_CTRL_SET;
+
+/// What the count words of a counter carry (`mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterMode {
+    /// Pulses counted up or down
+    UpDown,
+    /// Pulse frequency, in units of `prescale` Hz
+    Frequency,
+}
+
+impl CounterMode {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "updown" => Self::UpDown,
+            "freq" | "frequency" => Self::Frequency,
+            _ => return None,
+        })
+    }
+}
+
+/// Counter parameters from `module_config`:
+/// - `mode`: `updown` or `freq` (default updown)
+/// - `prescale`: Hz per raw frequency value in freq mode (default 1)
+/// - `model`: what produces the pulses, setting the unit of the signal:
+///   `flowmeter` (`K` pulses per gallon, default 1) and `OM050S514-242SS`
+///   (99 pulses per gallon) in GPM, `fan` and `encoder` (`PPR` pulses per
+///   revolution, default 2) and `AFB0624EH-AF00` (2 PPR) in RPM. Without a
+///   model the signal is the pulse frequency in Hz
+/// - `scale`, `offset`, `units`: override the model's signal = scale * Hz + offset
+/// - `GPM`, `RPM` or `frequency` (in Hz): initial signal (default 0 Hz)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterSettings {
+    pub mode: CounterMode,
+    pub prescale: f64,
+    pub scale: f64,
+    pub offset: f64,
+    pub unit: String,
+    /// Initial signal in engineering units
+    pub signal: f64,
+}
+
+impl CounterSettings {
+    pub fn from_module_config(config: &HashMap<String, String>) -> Result<Self, String> {
+        let mode = match config.get("mode") {
+            Some(name) => CounterMode::parse(name.trim()).ok_or_else(|| format!("unknown counter mode '{}'", name.trim()))?,
+            None => CounterMode::UpDown,
+        };
+        let prescale = config_value(config, "prescale", 1.0)?;
+        if prescale <= 0.0 {
+            return Err(format!("prescale '{}' must be positive", prescale));
+        }
+        let pulses_per_unit = |key: &str, default: f64| {
+            let value = config_value(config, key, default)?;
+            if value < 1.0 {
+                return Err(format!("{} '{}' must be at least 1", key, value));
+            }
+            Ok(value)
+        };
+        let (scale, unit) = match config.get("model").map(|model| model.trim()) {
+            None => (1.0, "Hz"),
+            Some(model) => match model.to_ascii_lowercase().as_str() {
+                "flowmeter" => (60.0 / pulses_per_unit("K", 1.0)?, "GPM"),
+                "om050s514-242ss" => (60.0 / 99.0, "GPM"),
+                "fan" | "encoder" => (60.0 / pulses_per_unit("PPR", 2.0)?, "RPM"),
+                "afb0624eh-af00" => (60.0 / 2.0, "RPM"),
+                _ => return Err(format!("unknown counter model '{}'", model)),
+            },
+        };
+        let overrides = ScalingOverride::from_module_config(config)?;
+        let scale = overrides.scale.unwrap_or(scale);
+        let offset = overrides.offset.unwrap_or(0.0);
+        let signal = match ["GPM", "RPM"].into_iter().find(|key| config.contains_key(*key)) {
+            Some(key) => config_value(config, key, 0.0)?,
+            None => scale * config_value(config, "frequency", 0.0)? + offset,
+        };
+        Ok(Self {
+            mode,
+            prescale,
+            scale,
+            offset,
+            unit: overrides.units.unwrap_or_else(|| unit.to_string()),
+            signal,
+        })
+    }
+
+    /// Pulse frequency in Hz of a signal value, 0 below the offset
+    fn frequency(&self, signal: f64) -> f64 {
+        ((signal - self.offset) / self.scale).max(0.0)
+    }
+}
+
 pub struct CounterModule {
     config: ModuleInstance,
+    settings: CounterSettings,
+    /// Pulse signal in engineering units
+    signal: f64,
+    /// Pulses not counted yet, below one
+    pending_pulses: f64,
     count: u32,
-    status: u8,
     control: u8,
     preset: u32,
-    channel_count: usize, // Typically 1 or 2 logical counters for simplicity, though physical has more
+    clock: bool,
+    down: bool,
+    overflow: bool,
+    underflow: bool,
 }
 
 impl CounterModule {
-    pub fn new(config: ModuleInstance, channel_count: usize) -> Self {
+    pub fn new(config: ModuleInstance, _descriptor: &ModuleDescriptor) -> Self {
+        // `check_module_config` rejects invalid settings when a config is loaded
+        let settings = CounterSettings::from_module_config(&config.module_config)
+            .unwrap_or_else(|_| CounterSettings::from_module_config(&HashMap::new()).expect("counter defaults are valid"));
         Self {
             config,
+            signal: settings.signal,
+            settings,
+            pending_pulses: 0.0,
             count: 0,
-            status: 0,
             control: 0,
             preset: 0,
-            channel_count,
+            clock: false,
+            down: false,
+            overflow: false,
+            underflow: false,
+        }
+    }
+
+    fn status(&self) -> u8 {
+        let mut status = self.control & COUNTER_CTRL_MASK;
+        if self.clock {
+            status |= COUNTER_STATUS_CLOCK;
+        }
+        if self.down {
+            status |= COUNTER_STATUS_DOWN;
+        }
+        if self.overflow {
+            status |= COUNTER_STATUS_OVERFLOW;
+        }
+        if self.underflow {
+            status |= COUNTER_STATUS_UNDERFLOW;
+        }
+        status
+    }
+
+    /// Count `pulses` in the U/D direction unless the gate is locked or the
+    /// counter is held at its preset
+    pub fn count_pulses(&mut self, pulses: u32) {
+        if self.control & (COUNTER_CTRL_LOCK | COUNTER_CTRL_SET) != 0 {
+            return;
+        }
+        if self.down {
+            let (count, wrapped) = self.count.overflowing_sub(pulses);
+            self.count = count;
+            self.underflow |= wrapped;
+        } else {
+            let (count, wrapped) = self.count.overflowing_add(pulses);
+            self.count = count;
+            self.overflow |= wrapped;
+        }
+    }
+
+    /// Raw frequency value of the signal: pulse frequency / prescale, saturating
+    /// at 0xFFFF; a higher frequency needs a larger prescale
+    fn frequency_value(&self) -> u32 {
+        (self.settings.frequency(self.signal) / self.settings.prescale)
+            .round()
+            .min(u16::MAX as f64) as u32
+    }
+
+    /// Value of the count words
+    fn value(&self) -> u32 {
+        match self.settings.mode {
+            CounterMode::UpDown => self.count,
+            CounterMode::Frequency => self.frequency_value(),
         }
     }
 }
@@ -519,32 +1281,55 @@ impl Module for CounterModule {
     }
 
     fn get_state(&self) -> ModuleState {
-        // Expose count as channel 0
-        let mut ch = ChannelState::new(
+        let value = self.value();
+        let mut count = ChannelState::new(
             0,
-            ChannelValue::Number(self.count as f64),
-            (self.count & 0xFFFF) as u16, // Only show lower 16 bits in raw
+            ChannelValue::Number(value as f64),
+            (value & 0xFFFF) as u16, // Only show lower 16 bits in raw
         );
-        ch.status = self.status;
+        count.status = self.status();
+        let signal = |channel: u16, on: bool| ChannelState::new(channel, ChannelValue::Bool(on), on as u16);
 
         ModuleState {
             id: self.config.id.clone(),
             module_number: self.config.module_number.clone(),
             slot_position: self.config.slot_position,
-            channels: vec![ch],
+            channels: vec![
+                count,
+                signal(1, self.clock),
+                signal(2, self.down),
+                signal(3, self.control & COUNTER_CTRL_O1 != 0),
+                signal(4, self.control & COUNTER_CTRL_O2 != 0),
+                ChannelState::new(5, ChannelValue::Number(self.signal), self.frequency_value() as u16),
+            ],
             last_update: current_time_ms(),
         }
     }
 
     fn set_channel_value(&mut self, channel: u16, value: f64) {
-        if channel == 0 {
-            self.count = value as u32;
+        match channel {
+            0 => match self.settings.mode {
+                CounterMode::UpDown => self.count = value as u32,
+                CounterMode::Frequency => {
+                    self.signal = self.settings.scale * self.settings.prescale * value + self.settings.offset
+                }
+            },
+            1 => {
+                let clock = value > 0.5;
+                if clock && !self.clock {
+                    self.count_pulses(1);
+                }
+                self.clock = clock;
+            }
+            2 => self.down = value > 0.5,
+            5 => self.signal = value,
+            // O1 and O2 follow the control byte
+            _ => {}
         }
     }
 
     fn get_input_image_size(&self) -> usize {
         // 1 Word (Status) + 2 Words (Count) = 6 Bytes
-        // Per channel? Assume 1 logical counter per module instance for MVP mapping
         6
     }
 
@@ -556,68 +1341,231 @@ impl Module for CounterModule {
     fn read_inputs(&self) -> Vec<u8> {
         let mut bytes = Vec::with_capacity(6);
         // Word 0: [Status, Padding] -> Little Endian [Status, 0]
-        bytes.push(self.status);
+        bytes.push(self.status());
         bytes.push(0);
-        
-        // Word 1: Count LSW (Low, High)
-        let count_lsw = (self.count & 0xFFFF) as u16;
-        bytes.push((count_lsw & 0xFF) as u8);
-        bytes.push((count_lsw >> 8) as u8);
-        
-        // Word 2: Count MSW (Low, High)
-        let count_msw = ((self.count >> 16) & 0xFFFF) as u16;
-        bytes.push((count_msw & 0xFF) as u8);
-        bytes.push((count_msw >> 8) as u8);
-        
+
+        // Words 1-2: Count or frequency, LSW first
+        bytes.extend_from_slice(&self.value().to_le_bytes());
         bytes
     }
 
     fn write_outputs(&mut self, data: &[u8]) {
         if data.len() < 6 { return; }
-        // Word 0: Control
-        self.control = data[0]; // Byte 0 is control
-        // Byte 1 is padding
-        
-        // Word 1: Preset LSW
-        let preset_lsw = (data[2] as u32) | ((data[3] as u32) << 8);
-        // Word 2: Preset MSW
-        let preset_msw = (data[4] as u32) | ((data[5] as u32) << 8);
-        
-        self.preset = preset_lsw | (preset_msw << 16);
-        
-        // Simple logic: if control bit set, load preset?
-        // TODO: Implement actual counter control logic if needed
-    }
-}
-
-pub fn create_module(config: ModuleInstance) -> Option<Box<dyn Module>> {
-    match config.module_number.as_str() {
-        "750-1405" => Some(Box::new(DigitalInputModule::new(config, 16))),
-        "750-1415" => Some(Box::new(DigitalInputModule::new(config, 8))),
-        "750-430"  => Some(Box::new(DigitalInputModule::new(config, 8))),
-        "753-440"  => Some(Box::new(DigitalInputModule::new(config, 4))),
-        
-        "750-1504" => Some(Box::new(DigitalOutputModule::new(config, 16))),
-        "750-1515" => Some(Box::new(DigitalOutputModule::new(config, 8))),
-        "750-530"  => Some(Box::new(DigitalOutputModule::new(config, 8))),
-        "750-515"  => Some(Box::new(DigitalOutputModule::new(config, 4))), // Relay
-        
-        "750-455" => Some(Box::new(AnalogInputModule::new(config, 4))),
-        "750-454" => Some(Box::new(AnalogInputModule::new(config, 2))),
-        
-        "750-461" => Some(Box::new(RTDModule::new(config, 2))),
-        "750-464" => {
-            // Per rioservice: 750-464 is configurable as 2 or 4 channel
-            // Default to 2 channels (can be extended with config parsing)
-            Some(Box::new(RTDModule::new(config, 2)))
-        },
-        
-        "750-563" => Some(Box::new(AnalogOutputModule::new(config, 2))),
-        "750-555" => Some(Box::new(AnalogOutputModule::new(config, 4))),
-        
-        "750-404" => Some(Box::new(CounterModule::new(config, 1))),
-        "750-633" => Some(Box::new(CounterModule::new(config, 1))),
-        
-        _ => None,
-    }
-}
\ No newline at end of file
+        // Word 0: Control byte, high byte is padding
+        self.control = data[0];
+        // Words 1-2: Preset, LSW first
+        self.preset = u32::from_le_bytes([data[2], data[3], data[4], data[5]]);
+
+        if self.control & COUNTER_CTRL_SET != 0 {
+            self.count = self.preset;
+            self.overflow = false;
+            self.underflow = false;
+        }
+    }
+
+    fn dword_offsets(&self) -> &'static [usize] {
+        &[2] // Count (input) and preset (output) after the status/control word
+    }
+
+    fn word_offset(&self, _channel: usize) -> Option<usize> {
+        // Only the count has data of its own; the signals are status bits
+        None
+    }
+
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        // Only a frequency converts to the signal; counts are pulses
+        if channel != 0 || self.settings.mode != CounterMode::Frequency {
+            return None;
+        }
+        let per_raw = self.settings.scale * self.settings.prescale;
+        Some(Scaling {
+            raw_min: 0,
+            raw_max: u16::MAX as i32,
+            min: self.settings.offset,
+            max: per_raw * u16::MAX as f64 + self.settings.offset,
+            unit: self.settings.unit.clone(),
+        })
+    }
+
+    fn advance(&mut self, elapsed_ms: u64) {
+        if self.settings.mode != CounterMode::UpDown {
+            return;
+        }
+        let pulses = self.pending_pulses + self.settings.frequency(self.signal) * elapsed_ms as f64 / 1000.0;
+        self.pending_pulses = pulses.fract();
+        self.count_pulses(pulses.trunc() as u32);
+    }
+}
+
+// --- PWM and pulse train outputs (750-511, 673) ---
+// Per channel a control/status word and a data word, in and out; the input
+// data word echoes the one the output runs with. A PWM data word is the duty
+// cycle at a period set in `module_config`, a pulse train data word the pulse
+// frequency at 50 % duty. The channels are the duty cycles in % (0..n)
+// followed by the periods in ms (n..2n).
+
+/// `period_ms` of a PWM module's outputs: a positive period in ms, default 4 (250 Hz)
+fn pwm_period(config: &HashMap<String, String>) -> Result<f64, String> {
+    let period = config_value(config, "period_ms", 4.0)?;
+    if period <= 0.0 {
+        return Err(format!("period_ms '{}' must be positive", period));
+    }
+    Ok(period)
+}
+
+pub struct PwmModule {
+    config: ModuleInstance,
+    pulse_train: bool,
+    /// Engineering value of each data word: duty cycle in % or frequency in Hz
+    values: Vec<f64>,
+    scaling: Vec<ChannelScaling>,
+    controls: Vec<u8>,
+    /// PWM periods in ms
+    periods: Vec<f64>,
+    faults: Vec<Option<ChannelFault>>,
+}
+
+impl PwmModule {
+    pub fn new(config: ModuleInstance, descriptor: &ModuleDescriptor) -> Self {
+        let scaling = ChannelScaling::for_channels(&config, descriptor);
+        // `check_module_config` rejects an invalid period when a config is loaded
+        let period = pwm_period(&config.module_config).unwrap_or(4.0);
+        Self {
+            pulse_train: descriptor.kind == ModuleKind::PulseTrain,
+            values: scaling.iter().map(|channel| channel.raw_to_value(0)).collect(),
+            scaling,
+            controls: vec![0; descriptor.channels],
+            periods: vec![period; descriptor.channels],
+            faults: vec![None; descriptor.channels],
+            config,
+        }
+    }
+
+    /// Duty cycle in % and period in ms of an output; a stopped pulse train has neither
+    fn duty_and_period(&self, output: usize) -> (f64, f64) {
+        if !self.pulse_train {
+            return (self.values[output], self.periods[output]);
+        }
+        match self.values[output] {
+            frequency if frequency > 0.0 => (50.0, 1000.0 / frequency),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// Control byte acknowledged, with the error bit while the output is faulted
+    fn status(&self, output: usize) -> u8 {
+        let error = if self.faults[output].is_some() { STATUS_ERROR } else { 0 };
+        (self.controls[output] & !STATUS_ERROR) | error
+    }
+}
+
+impl Module for PwmModule {
+    fn get_id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn get_config(&self) -> &ModuleInstance {
+        &self.config
+    }
+
+    fn get_state(&self) -> ModuleState {
+        let outputs = self.values.len();
+        let duties = (0..outputs).map(|i| {
+            let mut channel = ChannelState::new(
+                i as u16,
+                ChannelValue::Number(self.duty_and_period(i).0),
+                self.scaling[i].value_to_raw(self.values[i]),
+            );
+            channel.status = self.status(i);
+            channel.fault = self.faults[i];
+            channel
+        });
+        let periods = (0..outputs)
+            .map(|i| ChannelState::new((outputs + i) as u16, ChannelValue::Number(self.duty_and_period(i).1), 0));
+
+        ModuleState {
+            id: self.config.id.clone(),
+            module_number: self.config.module_number.clone(),
+            slot_position: self.config.slot_position,
+            channels: duties.chain(periods).collect(),
+            last_update: current_time_ms(),
+        }
+    }
+
+    fn set_channel_value(&mut self, channel: u16, value: f64) {
+        let outputs = self.values.len();
+        let channel = channel as usize;
+        if channel < outputs {
+            // A pulse train runs at 50 % duty
+            if !self.pulse_train {
+                self.values[channel] = value;
+            }
+        } else if channel < 2 * outputs && value >= 0.0 {
+            let output = channel - outputs;
+            if self.pulse_train {
+                self.values[output] = if value > 0.0 { 1000.0 / value } else { 0.0 };
+            } else if value > 0.0 {
+                self.periods[output] = value;
+            }
+        }
+    }
+
+    fn set_channel_fault(&mut self, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(slot) = self.faults.get_mut(channel as usize) {
+            *slot = fault;
+        }
+    }
+
+    fn get_input_image_size(&self) -> usize {
+        self.values.len() * 4
+    }
+
+    fn get_output_image_size(&self) -> usize {
+        self.values.len() * 4
+    }
+
+    fn read_inputs(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.values.len() * 4);
+        for (i, (&value, scaling)) in self.values.iter().zip(&self.scaling).enumerate() {
+            // [Status, padding], then the data word
+            bytes.push(self.status(i));
+            bytes.push(0);
+            bytes.extend_from_slice(&scaling.value_to_raw(value).to_le_bytes());
+        }
+        bytes
+    }
+
+    fn write_outputs(&mut self, data: &[u8]) {
+        for (i, chunk) in data.chunks(4).enumerate().take(self.values.len()) {
+            if chunk.len() == 4 {
+                self.controls[i] = chunk[0];
+                self.values[i] = self.scaling[i].raw_to_value(u16::from_le_bytes([chunk[2], chunk[3]]));
+            }
+        }
+    }
+
+    fn word_offset(&self, channel: usize) -> Option<usize> {
+        // The data word after each control/status word; periods have none
+        (channel < self.values.len()).then_some(channel * 4 + 2)
+    }
+
+    fn scaling(&self, channel: u16) -> Option<Scaling> {
+        self.scaling.get(channel as usize).map(ChannelScaling::scaling)
+    }
+}
+
+/// Instantiate the module for `config.module_number` from its catalog entry;
+/// `None` for part numbers the catalog does not know
+pub fn create_module(config: ModuleInstance, catalog: &ModuleCatalog) -> Option<Box<dyn Module>> {
+    let descriptor = catalog.get(&config.module_number)?;
+    Some(match descriptor.kind {
+        ModuleKind::DigitalInput => Box::new(DigitalInputModule::new(config, descriptor)),
+        ModuleKind::DigitalOutput => Box::new(DigitalOutputModule::new(config, descriptor)),
+        ModuleKind::AnalogInput => Box::new(AnalogInputModule::new(config, descriptor)),
+        ModuleKind::AnalogOutput => Box::new(AnalogOutputModule::new(config, descriptor)),
+        ModuleKind::Rtd => Box::new(RTDModule::new(config, descriptor)),
+        ModuleKind::Thermocouple => Box::new(ThermocoupleModule::new(config, descriptor)),
+        ModuleKind::Counter => Box::new(CounterModule::new(config, descriptor)),
+        ModuleKind::Pwm | ModuleKind::PulseTrain => Box::new(PwmModule::new(config, descriptor)),
+    })
+}
@@ -0,0 +1,320 @@
+// Modbus PDU, MBAP and RTU framing for the network transports (Modbus TCP,
+// UDP, RTU over TCP). Framing the responses here rather than in tokio-modbus
+// lets injected faults alter the encoded frame. Decoded requests go through
+// the same `SimulatorService` routing, so register semantics are identical.
+
+use std::borrow::Cow;
+use tokio_modbus::prelude::*;
+use tokio_modbus::ExceptionCode;
+
+/// MBAP header length (transaction ID, protocol ID, length, unit ID)
+pub const MBAP_HEADER_LEN: usize = 7;
+
+/// Largest PDU allowed by the Modbus spec
+pub const MAX_PDU_LEN: usize = 253;
+
+/// MBAP header of a Modbus TCP/UDP frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MbapHeader {
+    pub transaction_id: u16,
+    pub unit_id: u8,
+}
+
+/// Split an MBAP frame into header and PDU. Frames with a foreign protocol ID
+/// or a length field that does not match are rejected, like a coupler that
+/// drops them without answering.
+pub fn split_mbap(frame: &[u8]) -> Option<(MbapHeader, &[u8])> {
+    if frame.len() <= MBAP_HEADER_LEN {
+        return None;
+    }
+    let protocol_id = u16::from_be_bytes([frame[2], frame[3]]);
+    let length = u16::from_be_bytes([frame[4], frame[5]]) as usize;
+    if protocol_id != 0 || length != frame.len() - 6 || length - 1 > MAX_PDU_LEN {
+        return None;
+    }
+    let header = MbapHeader {
+        transaction_id: u16::from_be_bytes([frame[0], frame[1]]),
+        unit_id: frame[6],
+    };
+    Some((header, &frame[MBAP_HEADER_LEN..]))
+}
+
+/// Length of the MBAP frame at the start of `buf` according to its length
+/// field, or `None` until the header has arrived.
+pub fn mbap_frame_len(buf: &[u8]) -> Option<usize> {
+    let length = u16::from_be_bytes([*buf.get(4)?, *buf.get(5)?]);
+    Some(6 + length as usize)
+}
+
+/// Prefix a response PDU with an MBAP header echoing the request.
+pub fn encode_mbap(header: MbapHeader, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(MBAP_HEADER_LEN + pdu.len());
+    frame.extend_from_slice(&header.transaction_id.to_be_bytes());
+    frame.extend_from_slice(&0u16.to_be_bytes());
+    frame.extend_from_slice(&((pdu.len() + 1) as u16).to_be_bytes());
+    frame.push(header.unit_id);
+    frame.extend_from_slice(pdu);
+    frame
+}
+
+/// Modbus CRC-16 (polynomial 0xA001, initial value 0xFFFF) of an RTU frame
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u16, |crc, _| if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 })
+    })
+}
+
+/// Length of the RTU request frame at the start of `buf`, or `None` until
+/// enough of it has arrived. RTU frames carry no length, so it follows from
+/// the function code; a frame with an unknown function code is taken to be
+/// everything received so far.
+pub fn rtu_request_len(buf: &[u8]) -> Option<usize> {
+    let len = match *buf.get(1)? {
+        0x01..=0x06 => 8,
+        0x0F | 0x10 => 9 + *buf.get(6)? as usize,
+        0x16 => 10,
+        0x17 => 13 + *buf.get(10)? as usize,
+        0x2B => 7,
+        _ => buf.len(),
+    };
+    (buf.len() >= len).then_some(len)
+}
+
+/// Split an RTU frame into unit ID and PDU; `None` if the CRC does not match.
+pub fn split_rtu(frame: &[u8]) -> Option<(u8, &[u8])> {
+    if frame.len() < 4 {
+        return None;
+    }
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    if crc16(body) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return None;
+    }
+    Some((body[0], &body[1..]))
+}
+
+/// Frame a response PDU for RTU: unit ID, PDU and CRC, low byte first.
+pub fn encode_rtu(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pdu.len() + 3);
+    frame.push(unit_id);
+    frame.extend_from_slice(pdu);
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    frame
+}
+
+/// Decode a request PDU. Unknown function codes decode as `Request::Custom`
+/// so the handler can answer them with IllegalFunction.
+pub fn decode_request(pdu: &[u8]) -> Result<Request<'static>, ExceptionCode> {
+    let (&function, data) = pdu.split_first().ok_or(ExceptionCode::IllegalFunction)?;
+    let word = |idx: usize| -> Result<u16, ExceptionCode> {
+        match data.get(idx * 2..idx * 2 + 2) {
+            Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+            None => Err(ExceptionCode::IllegalDataValue),
+        }
+    };
+    let quantity = |idx: usize, max: u16| -> Result<u16, ExceptionCode> {
+        let qty = word(idx)?;
+        if qty == 0 || qty > max {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        Ok(qty)
+    };
+
+    let request = match function {
+        0x01 => Request::ReadCoils(word(0)?, quantity(1, 2000)?),
+        0x02 => Request::ReadDiscreteInputs(word(0)?, quantity(1, 2000)?),
+        0x03 => Request::ReadHoldingRegisters(word(0)?, quantity(1, 125)?),
+        0x04 => Request::ReadInputRegisters(word(0)?, quantity(1, 125)?),
+        0x05 => match word(1)? {
+            0xFF00 => Request::WriteSingleCoil(word(0)?, true),
+            0x0000 => Request::WriteSingleCoil(word(0)?, false),
+            _ => return Err(ExceptionCode::IllegalDataValue),
+        },
+        0x06 => Request::WriteSingleRegister(word(0)?, word(1)?),
+        0x0F => {
+            let qty = quantity(1, 1968)? as usize;
+            let bytes = data.get(5..).ok_or(ExceptionCode::IllegalDataValue)?;
+            if data[4] as usize != qty.div_ceil(8) || bytes.len() != qty.div_ceil(8) {
+                return Err(ExceptionCode::IllegalDataValue);
+            }
+            let coils = (0..qty).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect();
+            Request::WriteMultipleCoils(word(0)?, Cow::Owned(coils))
+        }
+        0x10 => {
+            let qty = quantity(1, 123)? as usize;
+            let words = decode_words(data, 4, qty)?;
+            Request::WriteMultipleRegisters(word(0)?, Cow::Owned(words))
+        }
+        0x16 => Request::MaskWriteRegister(word(0)?, word(1)?, word(2)?),
+        0x17 => {
+            let qty = quantity(3, 121)? as usize;
+            let words = decode_words(data, 8, qty)?;
+            Request::ReadWriteMultipleRegisters(word(0)?, quantity(1, 125)?, word(2)?, Cow::Owned(words))
+        }
+        // Read Device Identification; other MEI types stay custom requests
+        0x2B if data.first() == Some(&0x0E) => match *data {
+            [_, read_code, object_id] => match ReadCode::try_from_value(read_code) {
+                Some(read_code) => Request::ReadDeviceIdentification(read_code, object_id),
+                None => return Err(ExceptionCode::IllegalDataValue),
+            },
+            _ => return Err(ExceptionCode::IllegalDataValue),
+        },
+        _ => Request::Custom(function, Cow::Owned(data.to_vec())),
+    };
+    Ok(request)
+}
+
+/// Function code of a request, e.g. for the watchdog function mask.
+pub fn function_code(request: &Request<'_>) -> u8 {
+    match request {
+        Request::ReadCoils(..) => 0x01,
+        Request::ReadDiscreteInputs(..) => 0x02,
+        Request::ReadHoldingRegisters(..) => 0x03,
+        Request::ReadInputRegisters(..) => 0x04,
+        Request::WriteSingleCoil(..) => 0x05,
+        Request::WriteSingleRegister(..) => 0x06,
+        Request::WriteMultipleCoils(..) => 0x0F,
+        Request::WriteMultipleRegisters(..) => 0x10,
+        Request::MaskWriteRegister(..) => 0x16,
+        Request::ReadWriteMultipleRegisters(..) => 0x17,
+        Request::ReadDeviceIdentification(..) => 0x2B,
+        Request::Custom(function, _) => *function,
+        _ => 0,
+    }
+}
+
+/// Register values after a byte count at `offset`, which must match `qty`.
+fn decode_words(data: &[u8], offset: usize, qty: usize) -> Result<Vec<u16>, ExceptionCode> {
+    let byte_count = *data.get(offset).ok_or(ExceptionCode::IllegalDataValue)? as usize;
+    let bytes = &data[offset + 1..];
+    if byte_count != qty * 2 || bytes.len() != byte_count {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    Ok(bytes.chunks(2).map(|w| u16::from_be_bytes([w[0], w[1]])).collect())
+}
+
+/// Encode a response PDU, or an exception PDU for `function`.
+pub fn encode_response(function: u8, result: &Result<Response, ExceptionCode>) -> Vec<u8> {
+    let response = match result {
+        Ok(response) => response,
+        Err(code) => return vec![function | 0x80, u8::from(*code)],
+    };
+    let mut pdu = vec![function];
+    match response {
+        Response::ReadCoils(bits) | Response::ReadDiscreteInputs(bits) => {
+            let mut packed = vec![0u8; bits.len().div_ceil(8)];
+            for (i, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+                packed[i / 8] |= 1 << (i % 8);
+            }
+            pdu.push(packed.len() as u8);
+            pdu.extend_from_slice(&packed);
+        }
+        Response::ReadHoldingRegisters(words)
+        | Response::ReadInputRegisters(words)
+        | Response::ReadWriteMultipleRegisters(words) => {
+            pdu.push((words.len() * 2) as u8);
+            for word in words {
+                pdu.extend_from_slice(&word.to_be_bytes());
+            }
+        }
+        Response::WriteSingleCoil(addr, value) => {
+            pdu.extend_from_slice(&addr.to_be_bytes());
+            pdu.extend_from_slice(&(if *value { 0xFF00u16 } else { 0x0000 }).to_be_bytes());
+        }
+        Response::WriteMultipleCoils(addr, value)
+        | Response::WriteSingleRegister(addr, value)
+        | Response::WriteMultipleRegisters(addr, value) => {
+            pdu.extend_from_slice(&addr.to_be_bytes());
+            pdu.extend_from_slice(&value.to_be_bytes());
+        }
+        Response::MaskWriteRegister(addr, and_mask, or_mask) => {
+            pdu.extend_from_slice(&addr.to_be_bytes());
+            pdu.extend_from_slice(&and_mask.to_be_bytes());
+            pdu.extend_from_slice(&or_mask.to_be_bytes());
+        }
+        Response::Custom(_, data) => pdu.extend_from_slice(data),
+        _ => return vec![function | 0x80, u8::from(ExceptionCode::IllegalFunction)],
+    }
+    pdu
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbap_round_trip() {
+        let frame = [0x12, 0x34, 0x00, 0x00, 0x00, 0x06, 0x07, 0x03, 0x00, 0x10, 0x00, 0x02];
+        let (header, pdu) = split_mbap(&frame).unwrap();
+        assert_eq!(header, MbapHeader { transaction_id: 0x1234, unit_id: 7 });
+        assert_eq!(decode_request(pdu), Ok(Request::ReadHoldingRegisters(0x10, 2)));
+
+        let reply = encode_mbap(header, &encode_response(0x03, &Ok(Response::ReadHoldingRegisters(vec![1, 0x0203]))));
+        assert_eq!(reply, vec![0x12, 0x34, 0x00, 0x00, 0x00, 0x07, 0x07, 0x03, 0x04, 0x00, 0x01, 0x02, 0x03]);
+        assert_eq!(mbap_frame_len(&frame[..5]), None);
+        assert_eq!(mbap_frame_len(&frame), Some(frame.len()));
+
+        // Wrong length field or protocol ID
+        assert!(split_mbap(&frame[..11]).is_none());
+        let mut foreign = frame;
+        foreign[3] = 1;
+        assert!(split_mbap(&foreign).is_none());
+    }
+
+    #[test]
+    fn test_rtu_framing() {
+        // Read 1 holding register at 0 from unit 1, the usual CRC example
+        let frame = [0x01, 0x03, 0x00, 0x00, 0x00, 0x01, 0x84, 0x0A];
+        assert_eq!(crc16(&frame[..6]), 0x0A84);
+        assert_eq!(rtu_request_len(&frame[..5]), None);
+        assert_eq!(rtu_request_len(&frame), Some(8));
+        assert_eq!(split_rtu(&frame), Some((1, &frame[1..6])));
+        let mut corrupted = frame;
+        corrupted[3] = 0x11;
+        assert_eq!(split_rtu(&corrupted), None);
+
+        // FC16 length follows from the byte count
+        let fc16 = [0x01, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04];
+        assert_eq!(rtu_request_len(&fc16), None);
+        assert_eq!(rtu_request_len(&[fc16.as_slice(), &[0; 6]].concat()), Some(13));
+
+        let reply = encode_rtu(7, &[0x03, 0x02, 0x00, 0x01]);
+        assert_eq!(split_rtu(&reply), Some((7, [0x03, 0x02, 0x00, 0x01].as_slice())));
+    }
+
+    #[test]
+    fn test_decode_write_requests() {
+        // 10 coils: 0b1100_1101, 0b01
+        let fc15 = [0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01];
+        let coils = vec![true, false, true, true, false, false, true, true, true, false];
+        assert_eq!(decode_request(&fc15), Ok(Request::WriteMultipleCoils(0x13, Cow::Owned(coils))));
+
+        let fc16 = [0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02];
+        assert_eq!(
+            decode_request(&fc16),
+            Ok(Request::WriteMultipleRegisters(1, Cow::Owned(vec![0x000A, 0x0102])))
+        );
+
+        // Byte count disagrees with quantity; quantity out of range
+        assert_eq!(decode_request(&fc16[..9]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(decode_request(&[0x03, 0x00, 0x00, 0x00, 0x7E]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(decode_request(&[0x05, 0x00, 0x00, 0x12, 0x34]), Err(ExceptionCode::IllegalDataValue));
+    }
+
+    #[test]
+    fn test_encode_coil_response() {
+        let bits = vec![true, false, true, true, false, false, true, true, true];
+        assert_eq!(encode_response(0x01, &Ok(Response::ReadCoils(bits))), vec![0x01, 0x02, 0xCD, 0x01]);
+    }
+
+    #[test]
+    fn test_decode_device_identification() {
+        assert_eq!(decode_request(&[0x2B, 0x0E, 0x01, 0x00]), Ok(Request::ReadDeviceIdentification(ReadCode::Basic, 0)));
+        assert_eq!(decode_request(&[0x2B, 0x0E, 0x05, 0x00]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(decode_request(&[0x2B, 0x0D, 0x00]), Ok(Request::Custom(0x2B, Cow::Owned(vec![0x0D, 0x00]))));
+    }
+}
@@ -9,16 +9,19 @@
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 
+use crate::models::ChannelFault;
+
 // ============================================================================
 // Ownership Model
 // ============================================================================
 
 /// Identifies who/what set a channel's current value.
 /// Precedence (highest to lowest): Force > Manual > Scenario > Default
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum ValueSource {
     /// Initial/default value (lowest priority)
+    #[default]
     Default,
     /// Set by a reactive scenario behavior
     Scenario {
@@ -31,12 +34,6 @@ pub enum ValueSource {
     Force,
 }
 
-impl Default for ValueSource {
-    fn default() -> Self {
-        ValueSource::Default
-    }
-}
-
 impl ValueSource {
     /// Returns the priority level (higher = takes precedence)
     pub fn priority(&self) -> u8 {
@@ -78,11 +75,15 @@ impl Default for OwnedValue {
 pub struct ChannelRef {
     pub module_position: usize,
     pub channel: u16,
+    /// Rack the module position is relative to; resolved against the current
+    /// rack layout whenever the channel is accessed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rack: Option<String>,
 }
 
 impl ChannelRef {
     pub fn new(module_position: usize, channel: u16) -> Self {
-        Self { module_position, channel }
+        Self { module_position, channel, rack: None }
     }
 }
 
@@ -91,10 +92,11 @@ impl ChannelRef {
 // ============================================================================
 
 /// How source values are transformed to target values
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase", tag = "type")]
 pub enum BehaviorMapping {
     /// target = source
+    #[default]
     Direct,
     /// target = 1.0 - source (for boolean inversion)
     Inverted,
@@ -104,12 +106,6 @@ pub enum BehaviorMapping {
     Constant { value: f64 },
 }
 
-impl Default for BehaviorMapping {
-    fn default() -> Self {
-        BehaviorMapping::Direct
-    }
-}
-
 // ============================================================================
 // Reactive Behavior Definition (YAML Schema)
 // ============================================================================
@@ -143,6 +139,11 @@ pub struct ReactiveBehavior {
     /// Constant value (for backward compatibility with older schema)
     #[serde(default)]
     pub value: Option<f64>,
+
+    /// Inject this fault on the target while the mapped value is on (> 0.5)
+    /// and clear it otherwise, instead of setting the target's value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault: Option<ChannelFault>,
 }
 
 fn default_true() -> bool {
@@ -642,6 +643,16 @@ impl ReactiveScenarioManager {
         scenarios: Vec<ReactiveScenario>,
         module_count: usize,
         channel_counts: &[usize],
+    ) {
+        self.load_scenarios_with(scenarios, |scenarios| validate_scenarios(scenarios, module_count, channel_counts));
+    }
+
+    /// Load scenarios, validating them with `validate` (e.g. against copies
+    /// with their rack-relative channel references resolved)
+    pub fn load_scenarios_with(
+        &mut self,
+        scenarios: Vec<ReactiveScenario>,
+        validate: impl FnOnce(&[ReactiveScenario]) -> ValidationResult,
     ) {
         // Clear existing runtime and scenarios
         self.active_runtime = None;
@@ -655,7 +666,7 @@ impl ReactiveScenarioManager {
             .collect();
 
         // Validate
-        self.validation_result = validate_scenarios(&reactive_scenarios, module_count, channel_counts);
+        self.validation_result = validate(&reactive_scenarios);
 
         // Store valid scenarios
         for scenario in reactive_scenarios {
@@ -980,10 +991,9 @@ impl DependencyGraph {
         n: usize,
     ) -> Vec<usize> {
         // Calculate in-degrees (number of dependencies each node has)
-        let mut in_degree = vec![0usize; n];
-        for i in 0..n {
-            in_degree[i] = reverse_edges.get(&i).map(|v| v.len()).unwrap_or(0);
-        }
+        let mut in_degree: Vec<usize> = (0..n)
+            .map(|i| reverse_edges.get(&i).map(|v| v.len()).unwrap_or(0))
+            .collect();
 
         // Start with nodes that have no dependencies
         let mut queue: VecDeque<usize> = (0..n).filter(|&i| in_degree[i] == 0).collect();
@@ -1210,7 +1220,7 @@ impl ReactiveScenarioRuntime {
             // Handle delays
             if behavior.delay_ms > 0 {
                 // Check if source value changed
-                let source_value = behavior.source.as_ref().map(|s| get_channel_value(s));
+                let source_value = behavior.source.as_ref().map(&get_channel_value);
                 let source_changed = source_value != runtime.last_source_value;
 
                 if source_changed {
@@ -1236,7 +1246,7 @@ impl ReactiveScenarioRuntime {
                 // No delay - apply immediately
                 results.push((behavior.target.clone(), output_value, behavior.id.clone()));
                 runtime.last_applied_tick = Some(current_tick);
-                runtime.last_source_value = behavior.source.as_ref().map(|s| get_channel_value(s));
+                runtime.last_source_value = behavior.source.as_ref().map(&get_channel_value);
             }
         }
 
@@ -1386,8 +1396,7 @@ mod tests {
 
     #[test]
     fn test_channel_ownership_resolve() {
-        let mut ownership = ChannelOwnership::default();
-        ownership.default_value = 0.5;
+        let mut ownership = ChannelOwnership { default_value: 0.5, ..Default::default() };
 
         // Default only
         let (value, source) = ownership.resolve();
@@ -1440,6 +1449,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "dup".to_string(),
@@ -1449,6 +1459,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1474,6 +1485,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None, // Missing value!
+                    fault: None,
                 },
             ],
         };
@@ -1499,6 +1511,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1524,6 +1537,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1576,6 +1590,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "b".to_string(),
@@ -1585,6 +1600,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1611,6 +1627,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "b".to_string(),
@@ -1620,6 +1637,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1651,6 +1669,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "b".to_string(),
@@ -1660,6 +1679,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1684,6 +1704,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "b".to_string(),
@@ -1693,6 +1714,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1718,6 +1740,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1759,6 +1782,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1792,6 +1816,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: Some(42.5),
+                    fault: None,
                 },
             ],
         };
@@ -1819,6 +1844,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1893,6 +1919,7 @@ mod tests {
                     delay_ms: 50, // 50ms delay
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -1996,6 +2023,7 @@ mod tests {
                         delay_ms: 0,
                         enabled: true,
                         value: Some(1.0),
+                        fault: None,
                     },
                 ],
             },
@@ -2013,6 +2041,7 @@ mod tests {
                         delay_ms: 0,
                         enabled: true,
                         value: Some(2.0),
+                        fault: None,
                     },
                 ],
             },
@@ -2085,6 +2114,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 // C depends on B
                 ReactiveBehavior {
@@ -2095,6 +2125,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2126,6 +2157,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None, // Not used by current scaled implementation
+                    fault: None,
                 },
             ],
         };
@@ -2154,6 +2186,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2184,6 +2217,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "b_to_c".to_string(),
@@ -2193,6 +2227,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
                 ReactiveBehavior {
                     id: "c_to_d".to_string(),
@@ -2202,6 +2237,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2234,6 +2270,7 @@ mod tests {
                     delay_ms: 100,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2273,6 +2310,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2298,6 +2336,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: false, // Disabled!
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2327,6 +2366,7 @@ mod tests {
                         delay_ms: 50,
                         enabled: true,
                         value: None,
+                        fault: None,
                     },
                 ],
             },
@@ -2376,6 +2416,7 @@ mod tests {
                     delay_ms: 0,
                     enabled: true,
                     value: None,
+                    fault: None,
                 },
             ],
         };
@@ -2396,11 +2437,13 @@ mod tests {
     #[test]
     fn test_ownership_priority_chain() {
         // Test that clearing higher-priority ownership falls through correctly
-        let mut ownership = ChannelOwnership::default();
-        ownership.default_value = 0.0;
-        ownership.scenario_value = Some(1.0);
-        ownership.manual_value = Some(2.0);
-        ownership.force = Some(ChannelForce::new(3.0));
+        let mut ownership = ChannelOwnership {
+            default_value: 0.0,
+            scenario_value: Some(1.0),
+            manual_value: Some(2.0),
+            force: Some(ChannelForce::new(3.0)),
+            ..Default::default()
+        };
 
         // Force takes precedence
         let (value, source) = ownership.resolve();
@@ -0,0 +1,431 @@
+// Coupler register space for FC03/FC04 reads and FC06/16/22/23 writes. The
+// 64K register addresses are served by blocks registered in a `RegisterMap`;
+// the first block that claims an address answers it, so a coupler register is
+// added once and reads the same through both function codes.
+
+use std::cell::OnceCell;
+use std::net::Ipv4Addr;
+
+use crate::couplers::Fieldbus;
+use crate::models::CouplerMetadata;
+use crate::sim_config::AddressCheck;
+use crate::state::{DataArea, ImageRef, Simulator};
+use crate::watchdog;
+
+/// Rack a request is answered for, with the input image built at most once
+pub struct RegisterContext<'a> {
+    pub sim: &'a Simulator,
+    pub rack: usize,
+    input_image: OnceCell<Vec<u16>>,
+}
+
+impl<'a> RegisterContext<'a> {
+    pub fn new(sim: &'a Simulator, rack: usize) -> Self {
+        Self { sim, rack, input_image: OnceCell::new() }
+    }
+
+    /// Input process image registers
+    pub fn input_image(&self) -> &[u16] {
+        self.input_image.get_or_init(|| self.sim.read_input_registers(self.rack))
+    }
+
+    fn metadata(&self) -> Option<&CouplerMetadata> {
+        self.sim.racks.get(self.rack).map(|rack| &rack.config.coupler.metadata)
+    }
+}
+
+/// A set of registers served by one handler
+pub trait RegisterBlock: Send + Sync {
+    /// Value of `addr` for a read in `area` (FC03 or FC04); `None` if this block does not serve it
+    fn read(&self, ctx: &RegisterContext, area: DataArea, addr: u16) -> Option<u16>;
+
+    /// Multi-word value (MAC address, string) that a read starting at `addr`
+    /// returns whole, as on the coupler. `None` for ordinary registers.
+    fn read_record(&self, _ctx: &RegisterContext, _addr: u16) -> Option<Vec<u16>> {
+        None
+    }
+
+    /// Whether this block accepts a write to `addr`
+    fn writable(&self, _ctx: &RegisterContext, _addr: u16) -> bool {
+        false
+    }
+
+    /// Store a written value; only called for addresses `writable` accepted
+    fn write(&self, _sim: &mut Simulator, _rack: usize, _addr: u16, _value: u16, _now: u64) {}
+}
+
+#[derive(Default)]
+pub struct RegisterMap {
+    blocks: Vec<Box<dyn RegisterBlock>>,
+}
+
+impl RegisterMap {
+    /// Register space of a WAGO 750 coupler
+    pub fn wago_coupler() -> Self {
+        let mut map = Self::default();
+        map.register(WatchdogRegisters);
+        map.register(NetworkConfig);
+        map.register(Constants);
+        map.register(CouplerInfo);
+        map.register(ModuleConfig);
+        map.register(ProcessImage);
+        map.register(UserMemory);
+        map
+    }
+
+    /// Add a block; blocks registered earlier take precedence
+    pub fn register(&mut self, block: impl RegisterBlock + 'static) {
+        self.blocks.push(Box::new(block));
+    }
+
+    pub fn read(&self, ctx: &RegisterContext, area: DataArea, addr: u16) -> Option<u16> {
+        self.blocks.iter().find_map(|block| {
+            block.read(ctx, area, addr).or_else(|| block.read_record(ctx, addr)?.first().copied())
+        })
+    }
+
+    pub fn read_record(&self, ctx: &RegisterContext, addr: u16) -> Option<Vec<u16>> {
+        self.blocks.iter().find_map(|block| block.read_record(ctx, addr))
+    }
+
+    pub fn writable(&self, ctx: &RegisterContext, addr: u16) -> bool {
+        self.blocks.iter().any(|block| block.writable(ctx, addr))
+    }
+
+    /// Apply a write to the first block that accepts it. Returns false if none does.
+    pub fn write(&self, sim: &mut Simulator, rack: usize, addr: u16, value: u16, now: u64) -> bool {
+        let ctx = RegisterContext::new(sim, rack);
+        let Some(block) = self.blocks.iter().find(|block| block.writable(&ctx, addr)) else {
+            return false;
+        };
+        block.write(sim, rack, addr, value, now);
+        true
+    }
+}
+
+/// Watchdog (0x1000-0x100A)
+pub struct WatchdogRegisters;
+
+impl RegisterBlock for WatchdogRegisters {
+    fn read(&self, ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+        self.writable(ctx, addr)
+            .then(|| ctx.sim.racks.get(ctx.rack).map_or(0, |rack| rack.watchdog.read(addr)))
+    }
+
+    fn writable(&self, _ctx: &RegisterContext, addr: u16) -> bool {
+        (watchdog::FIRST_REGISTER..=watchdog::LAST_REGISTER).contains(&addr)
+    }
+
+    fn write(&self, sim: &mut Simulator, rack: usize, addr: u16, value: u16, now: u64) {
+        if let Some(rack) = sim.racks.get_mut(rack) {
+            rack.watchdog.write(addr, value, now);
+        }
+    }
+}
+
+/// MAC address (0x1031, 3 words), IP address, subnet mask and gateway
+/// (0x1032-0x1034, 2 words each) from the coupler configuration; ETHERNET
+/// couplers only
+pub struct NetworkConfig;
+
+impl RegisterBlock for NetworkConfig {
+    fn read(&self, _ctx: &RegisterContext, _area: DataArea, _addr: u16) -> Option<u16> {
+        None
+    }
+
+    fn read_record(&self, ctx: &RegisterContext, addr: u16) -> Option<Vec<u16>> {
+        let rack = ctx.sim.racks.get(ctx.rack)?;
+        if rack.coupler.fieldbus != Fieldbus::Ethernet {
+            return None;
+        }
+        let metadata = &rack.config.coupler.metadata;
+        let ip = parse_ipv4(Some(&rack.config.coupler.ip_address)).unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let bytes = match addr {
+            0x1031 => mac_address(metadata, ip).to_vec(),
+            0x1032 => ip.octets().to_vec(),
+            0x1033 => parse_ipv4(metadata.subnet_mask.as_deref())
+                .unwrap_or(Ipv4Addr::new(255, 255, 255, 0))
+                .octets()
+                .to_vec(),
+            0x1034 => parse_ipv4(metadata.gateway.as_deref()).unwrap_or(Ipv4Addr::UNSPECIFIED).octets().to_vec(),
+            _ => return None,
+        };
+        Some(bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect())
+    }
+}
+
+fn parse_ipv4(text: Option<&str>) -> Option<Ipv4Addr> {
+    text?.trim().parse().ok()
+}
+
+/// Configured MAC address, or the WAGO prefix 00:30:DE plus the last three IP octets
+fn mac_address(metadata: &CouplerMetadata, ip: Ipv4Addr) -> [u8; 6] {
+    let configured = metadata.mac_address.as_deref().and_then(|text| {
+        let bytes: Vec<u8> = text.split([':', '-']).filter_map(|part| u8::from_str_radix(part, 16).ok()).collect();
+        <[u8; 6]>::try_from(bytes).ok()
+    });
+    configured.unwrap_or_else(|| {
+        let [_, b, c, d] = ip.octets();
+        [0x00, 0x30, 0xDE, b, c, d]
+    })
+}
+
+/// Constant registers (0x2000-0x2008) for checking a master's word and byte handling
+pub struct Constants;
+
+const CONSTANTS: [u16; 9] = [0x0000, 0xFFFF, 0x1234, 0xAAAA, 0x5555, 0x7FFF, 0x8000, 0x3FFF, 0x4000];
+
+impl RegisterBlock for Constants {
+    fn read(&self, _ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+        CONSTANTS.get(addr.checked_sub(0x2000)? as usize).copied()
+    }
+}
+
+/// Process image bit counts (0x1022-0x1025), firmware version, series and
+/// coupler part number (0x2010-0x2014), and the coupler description and
+/// firmware compile time and date strings (0x2020-0x2022)
+pub struct CouplerInfo;
+
+const DEFAULT_FIRMWARE_VERSION: (u8, u8) = (1, 0);
+const DEFAULT_FIRMWARE_DATE: &str = "Jan  1 2024";
+const DEFAULT_FIRMWARE_TIME: &str = "12:00:00";
+
+impl CouplerInfo {
+    fn firmware_version(ctx: &RegisterContext) -> (u8, u8) {
+        ctx.metadata()
+            .and_then(|metadata| {
+                let (major, minor) = metadata.firmware_version.as_deref()?.split_once('.')?;
+                Some((major.trim().parse().ok()?, minor.trim().parse().ok()?))
+            })
+            .unwrap_or(DEFAULT_FIRMWARE_VERSION)
+    }
+}
+
+impl RegisterBlock for CouplerInfo {
+    fn read(&self, ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+        match addr {
+            0x1022..=0x1025 => {
+                let (output_analog, input_analog, output_digital, input_digital) =
+                    ctx.sim.calculate_io_bit_counts(ctx.rack);
+                Some([output_analog, input_analog, output_digital, input_digital][(addr - 0x1022) as usize])
+            }
+            0x2010 => {
+                let (major, minor) = Self::firmware_version(ctx);
+                Some(u16::from_be_bytes([major, minor]))
+            }
+            0x2011 => Some(0x0750), // Series 750
+            0x2012 => Some(ctx.sim.rack_coupler_part_number(ctx.rack)),
+            0x2013 => Some(Self::firmware_version(ctx).0 as u16),
+            0x2014 => Some(Self::firmware_version(ctx).1 as u16),
+            _ => None,
+        }
+    }
+
+    fn read_record(&self, ctx: &RegisterContext, addr: u16) -> Option<Vec<u16>> {
+        let metadata = ctx.metadata()?;
+        match addr {
+            0x2020 => Some(string_registers(&format!("WAGO 750-{}", ctx.sim.rack_coupler_part_number(ctx.rack)), 16)),
+            0x2021 => Some(string_registers(metadata.firmware_time.as_deref().unwrap_or(DEFAULT_FIRMWARE_TIME), 8)),
+            0x2022 => Some(string_registers(metadata.firmware_date.as_deref().unwrap_or(DEFAULT_FIRMWARE_DATE), 8)),
+            _ => None,
+        }
+    }
+}
+
+/// ASCII string in `words` registers, two characters per register (first in the
+/// high byte), padded with NUL
+fn string_registers(text: &str, words: usize) -> Vec<u16> {
+    let mut bytes: Vec<u8> = text.bytes().take(words * 2).collect();
+    bytes.resize(words * 2, 0);
+    bytes.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect()
+}
+
+/// Module discovery (0x2030-0x2129): the coupler, then one register per module
+pub struct ModuleConfig;
+
+impl RegisterBlock for ModuleConfig {
+    fn read(&self, ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+        let index = match addr {
+            0x2030 => return Some(ctx.sim.rack_coupler_part_number(ctx.rack)),
+            0x2031..=0x2070 => (addr - 0x2031) as usize,
+            0x2071..=0x20AE => 64 + (addr - 0x2071) as usize,
+            0x20AF..=0x20EC => 128 + (addr - 0x20AF) as usize,
+            0x20ED..=0x2129 => 192 + (addr - 0x20ED) as usize,
+            _ => return None,
+        };
+        let modules = &ctx.sim.modules[ctx.sim.rack_module_range(ctx.rack)];
+        // 0 marks the end of the rack
+        Some(modules.get(index).map_or(0, |module| {
+            ctx.sim.encode_module_id(&module.get_config().module_number)
+        }))
+    }
+}
+
+/// Input and output process images at the WAGO and configured addresses
+pub struct ProcessImage;
+
+impl RegisterBlock for ProcessImage {
+    fn read(&self, ctx: &RegisterContext, area: DataArea, addr: u16) -> Option<u16> {
+        match ctx.sim.resolve_register(area, addr, false, ctx.input_image().len())? {
+            ImageRef::Input(index) => ctx.input_image().get(index).copied(),
+            ImageRef::Output(index) => ctx.sim.racks.get(ctx.rack)?.output_registers.get(index).copied(),
+        }
+    }
+
+    fn writable(&self, ctx: &RegisterContext, addr: u16) -> bool {
+        let output_words = ctx.sim.racks.get(ctx.rack).map_or(0, |rack| rack.output_registers.len());
+        matches!(
+            ctx.sim.resolve_register(DataArea::HoldingRegisters, addr, true, 0),
+            Some(ImageRef::Output(index)) if index < output_words
+        )
+    }
+
+    fn write(&self, sim: &mut Simulator, rack: usize, addr: u16, value: u16, _now: u64) {
+        let Some(ImageRef::Output(index)) = sim.resolve_register(DataArea::HoldingRegisters, addr, true, 0) else {
+            return;
+        };
+        let Some(rack) = sim.racks.get_mut(rack) else { return };
+        // A tripped watchdog holds the outputs at their safe values
+        if !rack.watchdog.is_tripped() {
+            rack.output_registers[index] = value;
+        }
+    }
+}
+
+/// Plain read/write registers anywhere else in the 64K space. Only served with
+/// `address_check: lenient`, where they read back what was last written.
+pub struct UserMemory;
+
+impl RegisterBlock for UserMemory {
+    fn read(&self, ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+        self.writable(ctx, addr)
+            .then(|| ctx.sim.racks.get(ctx.rack).and_then(|rack| rack.user_memory.get(&addr).copied()).unwrap_or(0))
+    }
+
+    fn writable(&self, ctx: &RegisterContext, _addr: u16) -> bool {
+        ctx.sim.address_check == AddressCheck::Lenient
+    }
+
+    fn write(&self, sim: &mut Simulator, rack: usize, addr: u16, value: u16, _now: u64) {
+        if let Some(rack) = sim.racks.get_mut(rack) {
+            rack.user_memory.insert(addr, value);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RACK: &str = r#"
+version: 2
+sim: { name: 'test', seed: 1, tick_ms: 10 }
+transport:
+  kind: 'modbus_tcp'
+  listen: { host: '127.0.0.1', port: 0 }
+  unit_id: 1
+process_image: { layout: 'wago_750_default', word_endian: 'little', align_modules_to: 2 }
+modbus_map:
+  inputs: { kind: 'input_registers', base: 0 }
+  outputs: { kind: 'holding_registers', base: 0 }
+racks:
+  - id: 'rack0'
+    name: 'Rack 0'
+    modules:
+      - { id: 'di', model: '750-1415', name: 'DI', channels: [] }
+      - { id: 'ai', model: '750-455', name: 'AI', channels: [] }
+      - { id: 'do', model: '750-1515', name: 'DO', channels: [] }
+"#;
+
+    fn rack() -> Simulator {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(RACK).unwrap();
+        sim
+    }
+
+    /// Serves 0x3000 as a constant
+    struct Constant(u16);
+
+    impl RegisterBlock for Constant {
+        fn read(&self, _ctx: &RegisterContext, _area: DataArea, addr: u16) -> Option<u16> {
+            (addr == 0x3000).then_some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_first_block_wins() {
+        let sim = rack();
+        let mut map = RegisterMap::default();
+        map.register(Constant(1));
+        map.register(Constant(2));
+        let ctx = RegisterContext::new(&sim, 0);
+        assert_eq!(map.read(&ctx, DataArea::HoldingRegisters, 0x3000), Some(1));
+        assert_eq!(map.read(&ctx, DataArea::InputRegisters, 0x3001), None);
+        assert!(!map.writable(&ctx, 0x3000));
+    }
+
+    #[test]
+    fn test_coupler_blocks() {
+        let sim = rack();
+        let ctx = RegisterContext::new(&sim, 0);
+        let read = |block: &dyn RegisterBlock, addr| block.read(&ctx, DataArea::InputRegisters, addr);
+
+        assert_eq!(read(&CouplerInfo, 0x1023), Some(64)); // 4 AI words
+        assert_eq!(read(&CouplerInfo, 0x1025), Some(8));
+        assert_eq!(read(&CouplerInfo, 0x2012), Some(362));
+        assert_eq!(read(&CouplerInfo, 0x2015), None);
+        assert_eq!(read(&ModuleConfig, 0x2030), Some(362));
+        assert_eq!(read(&ModuleConfig, 0x2032), Some(455));
+        assert_eq!(read(&ModuleConfig, 0x2034), Some(0));
+        assert_eq!(read(&WatchdogRegisters, 0x1001), Some(0xFFFF));
+        assert_eq!(read(&UserMemory, 0x3000), None);
+    }
+
+    #[test]
+    fn test_network_and_firmware_registers() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&RACK.replace(
+            "    name: 'Rack 0'\n",
+            "    name: 'Rack 0'\n    coupler: { gateway: '127.0.0.254', firmware_version: '02.13', firmware_time: '08:15:00' }\n",
+        ))
+        .unwrap();
+
+        assert_eq!(sim.read_holding_registers(0, 0x1031, 3), vec![0x0030, 0xDE00, 0x0001]);
+        assert_eq!(sim.read_input_register_range(0, 0x1032, 2), vec![0x7F00, 0x0001]);
+        assert_eq!(sim.read_input_register_range(0, 0x1033, 2), vec![0xFFFF, 0xFF00]);
+        assert_eq!(sim.read_input_register_range(0, 0x1034, 2), vec![0x7F00, 0x00FE]);
+        assert_eq!(sim.read_input_register_range(0, 0x2000, 9)[2..5], [0x1234, 0xAAAA, 0x5555]);
+        assert_eq!(sim.read_input_register_range(0, 0x2010, 5), vec![0x020D, 0x0750, 362, 2, 13]);
+        assert_eq!(&sim.read_input_register_range(0, 0x2021, 8)[..5], &[0x3038, 0x3A31, 0x353A, 0x3030, 0]);
+        assert_eq!(sim.read_input_register_range(0, 0x2020, 16)[..6], string_registers("WAGO 750-362", 6));
+
+        // Multi-word registers are read whole from their first address
+        assert!(sim.is_mapped(0, DataArea::HoldingRegisters, 0x1031, 3, false));
+        assert!(!sim.is_mapped(0, DataArea::HoldingRegisters, 0x1031, 4, false));
+        assert!(!sim.is_mapped(0, DataArea::HoldingRegisters, 0x1031, 1, true));
+        assert!(!sim.is_mapped(0, DataArea::InputRegisters, 0x2009, 1, false));
+    }
+
+    #[test]
+    fn test_process_image_and_user_memory_writes() {
+        let mut sim = rack();
+        let map = RegisterMap::wago_coupler();
+
+        // AI words come first, so the DO register is the only output word
+        assert!(map.write(&mut sim, 0, 0x0200, 0x0005, 0));
+        assert!(!map.write(&mut sim, 0, 0x0201, 1, 0));
+        let ctx = RegisterContext::new(&sim, 0);
+        assert_eq!(map.read(&ctx, DataArea::InputRegisters, 0x0200), Some(0x0005));
+        assert_eq!(map.read(&ctx, DataArea::HoldingRegisters, 0x0005), None);
+
+        sim.address_check = AddressCheck::Lenient;
+        assert!(map.write(&mut sim, 0, 0x4000, 0x1234, 0));
+        let ctx = RegisterContext::new(&sim, 0);
+        assert_eq!(map.read(&ctx, DataArea::HoldingRegisters, 0x4000), Some(0x1234));
+        assert_eq!(map.read(&ctx, DataArea::InputRegisters, 0x4001), Some(0));
+    }
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
+use crate::faults::FaultConfig;
+use crate::models::ChannelFault;
+use crate::reactive::ChannelRef;
 use crate::state::Simulator;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -8,6 +11,13 @@ pub enum ScenarioAction {
     Set,
     Ramp,
     Pulse,
+    /// Inject Modbus faults (`fault`, for `client` or globally); with
+    /// `duration_ms` the previous faults are restored afterwards
+    Fault,
+    /// Inject `channel_fault` on the target channel (none clears it); with
+    /// `duration_ms` the fault clears afterwards
+    #[serde(rename = "channel_fault")]
+    ChannelFault,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +30,9 @@ pub struct ScenarioStep {
     // Reactive Trigger (Wait for channel value)
     #[serde(alias = "trigger_module")]
     pub trigger_module: Option<usize>,
+    /// Rack `trigger_module` is relative to (defaults to the first rack)
+    #[serde(default, alias = "trigger_rack", skip_serializing_if = "Option::is_none")]
+    pub trigger_rack: Option<String>,
     #[serde(alias = "trigger_channel")]
     pub trigger_channel: Option<u16>,
     #[serde(alias = "trigger_value")]
@@ -30,17 +43,33 @@ pub struct ScenarioStep {
     pub delay_ms: Option<u64>,
 
     // Target for action
-    #[serde(alias = "module_position")]
+    #[serde(default, alias = "module_position")]
     pub module_position: usize,
+    /// Rack `module_position` is relative to (defaults to the first rack)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rack: Option<String>,
+    #[serde(default)]
     pub channel: u16,
     
     // Action
     pub action: ScenarioAction,
+    #[serde(default)]
     pub value: f64,
     #[serde(alias = "duration_ms")]
     pub duration_ms: Option<u64>,
     #[serde(alias = "end_value")]
     pub end_value: Option<f64>,
+
+    // Fault injection (`fault` action); no `fault` clears the faults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault: Option<FaultConfig>,
+    /// Client IP the fault applies to (defaults to all clients)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+
+    // Channel diagnostics (`channel_fault` action)
+    #[serde(default, alias = "channel_fault", skip_serializing_if = "Option::is_none")]
+    pub channel_fault: Option<ChannelFault>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,8 +85,7 @@ pub struct Scenario {
 
 #[derive(Debug, Clone)]
 struct ActiveRamp {
-    module_position: usize,
-    channel: u16,
+    channel: ChannelRef,
     start_value: f64,
     target_value: f64,
     start_time: Instant,
@@ -66,12 +94,25 @@ struct ActiveRamp {
 
 #[derive(Debug, Clone)]
 struct ActivePulse {
-    module_position: usize,
-    channel: u16,
+    channel: ChannelRef,
     original_value: f64,
     end_time: Instant,
 }
 
+#[derive(Debug, Clone)]
+struct ActiveChannelFault {
+    channel: ChannelRef,
+    end_time: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct ActiveFault {
+    client: Option<String>,
+    /// Faults to restore; `None` removes the client's override
+    original: Option<FaultConfig>,
+    end_time: Instant,
+}
+
 pub struct ScenarioEngine {
     pub running: bool,
     pub start_time: Option<Instant>,
@@ -79,10 +120,18 @@ pub struct ScenarioEngine {
     pub loaded_scenario: Option<Scenario>,
     active_ramps: Vec<ActiveRamp>,
     active_pulses: Vec<ActivePulse>,
+    active_faults: Vec<ActiveFault>,
+    active_channel_faults: Vec<ActiveChannelFault>,
     // Track if current step is waiting for its post-trigger delay
     step_delay_start: Option<Instant>,
 }
 
+impl Default for ScenarioEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ScenarioEngine {
     pub fn new() -> Self {
         Self {
@@ -92,6 +141,8 @@ impl ScenarioEngine {
             loaded_scenario: None,
             active_ramps: Vec::new(),
             active_pulses: Vec::new(),
+            active_faults: Vec::new(),
+            active_channel_faults: Vec::new(),
             step_delay_start: None,
         }
     }
@@ -108,6 +159,8 @@ impl ScenarioEngine {
             self.current_step_index = 0;
             self.active_ramps.clear();
             self.active_pulses.clear();
+            self.active_faults.clear();
+            self.active_channel_faults.clear();
             self.step_delay_start = None;
         }
     }
@@ -118,6 +171,8 @@ impl ScenarioEngine {
         self.current_step_index = 0;
         self.active_ramps.clear();
         self.active_pulses.clear();
+        self.active_faults.clear();
+        self.active_channel_faults.clear();
         self.step_delay_start = None;
     }
 
@@ -143,7 +198,8 @@ impl ScenarioEngine {
                     // Check if trigger is met
                     let trigger_met = if let (Some(m), Some(c), Some(v)) = (step.trigger_module, step.trigger_channel, step.trigger_value) {
                         // Value-based trigger
-                        let current = self.get_simulator_value(simulator, m, c);
+                        let trigger = ChannelRef { module_position: m, channel: c, rack: step.trigger_rack.clone() };
+                        let current = self.get_simulator_value(simulator, &trigger);
                         (current - v).abs() < 0.001
                     } else if let Some(offset) = step.time_offset_ms {
                         // Absolute time trigger
@@ -198,7 +254,11 @@ impl ScenarioEngine {
             if self.current_step_index >= scenario.steps.len() {
                 if scenario.loop_enabled {
                     loop_triggered = true;
-                } else if self.active_ramps.is_empty() && self.active_pulses.is_empty() {
+                } else if self.active_ramps.is_empty()
+                    && self.active_pulses.is_empty()
+                    && self.active_faults.is_empty()
+                    && self.active_channel_faults.is_empty()
+                {
                     finished = true;
                 }
             }
@@ -209,6 +269,8 @@ impl ScenarioEngine {
             self.start_time = Some(Instant::now());
             self.active_ramps.clear();
             self.active_pulses.clear();
+            self.active_faults.clear();
+            self.active_channel_faults.clear();
         } else if finished {
             self.running = false;
         }
@@ -221,13 +283,13 @@ impl ScenarioEngine {
             
             if ramp_elapsed >= ramp.duration {
                 // Ramp finished
-                self.set_simulator_value(simulator, ramp.module_position, ramp.channel, ramp.target_value);
+                self.set_simulator_value(simulator, &ramp.channel, ramp.target_value);
                 self.active_ramps.remove(i);
             } else {
                 // Interpolate
                 let progress = ramp_elapsed.as_secs_f64() / ramp.duration.as_secs_f64();
                 let current_val = ramp.start_value + (ramp.target_value - ramp.start_value) * progress;
-                self.set_simulator_value(simulator, ramp.module_position, ramp.channel, current_val);
+                self.set_simulator_value(simulator, &ramp.channel, current_val);
                 i += 1;
             }
         }
@@ -239,25 +301,50 @@ impl ScenarioEngine {
             let pulse = &self.active_pulses[i];
             if now >= pulse.end_time {
                 // Pulse finished, revert value
-                self.set_simulator_value(simulator, pulse.module_position, pulse.channel, pulse.original_value);
+                self.set_simulator_value(simulator, &pulse.channel, pulse.original_value);
                 self.active_pulses.remove(i);
             } else {
                 i += 1;
             }
         }
+
+        // Process active faults
+        let mut i = 0;
+        while i < self.active_faults.len() {
+            if now >= self.active_faults[i].end_time {
+                let fault = self.active_faults.remove(i);
+                match fault.original {
+                    Some(config) => simulator.faults.set(fault.client, config),
+                    None => simulator.faults.clear(fault.client.as_deref()),
+                }
+            } else {
+                i += 1;
+            }
+        }
+
+        // Process active channel faults
+        let mut i = 0;
+        while i < self.active_channel_faults.len() {
+            if now >= self.active_channel_faults[i].end_time {
+                let fault = self.active_channel_faults.remove(i);
+                self.set_simulator_fault(simulator, &fault.channel, None);
+            } else {
+                i += 1;
+            }
+        }
     }
 
     fn execute_step(&mut self, step: &ScenarioStep, simulator: &mut Simulator) {
+        let channel = ChannelRef { module_position: step.module_position, channel: step.channel, rack: step.rack.clone() };
         match step.action {
             ScenarioAction::Set => {
-                self.set_simulator_value(simulator, step.module_position, step.channel, step.value);
+                self.set_simulator_value(simulator, &channel, step.value);
             }
             ScenarioAction::Ramp => {
                 if let Some(duration_ms) = step.duration_ms {
-                    let start_val = self.get_simulator_value(simulator, step.module_position, step.channel);
+                    let start_val = self.get_simulator_value(simulator, &channel);
                     self.active_ramps.push(ActiveRamp {
-                        module_position: step.module_position,
-                        channel: step.channel,
+                        channel,
                         start_value: start_val,
                         target_value: step.value,
                         start_time: Instant::now(),
@@ -267,23 +354,49 @@ impl ScenarioEngine {
             }
             ScenarioAction::Pulse => {
                 if let Some(duration_ms) = step.duration_ms {
-                    let original_val = self.get_simulator_value(simulator, step.module_position, step.channel);
-                    self.set_simulator_value(simulator, step.module_position, step.channel, step.value);
+                    let original_val = self.get_simulator_value(simulator, &channel);
+                    self.set_simulator_value(simulator, &channel, step.value);
                     self.active_pulses.push(ActivePulse {
-                        module_position: step.module_position,
-                        channel: step.channel,
+                        channel,
                         original_value: original_val,
                         end_time: Instant::now() + Duration::from_millis(duration_ms),
                     });
                 }
             }
+            ScenarioAction::Fault => {
+                let state = simulator.faults.state();
+                let original = match &step.client {
+                    Some(client) => state.clients.get(client).cloned(),
+                    None => Some(state.global.clone()),
+                };
+                match &step.fault {
+                    Some(config) => simulator.faults.set(step.client.clone(), config.clone()),
+                    None => simulator.faults.clear(step.client.as_deref()),
+                }
+                if let Some(duration_ms) = step.duration_ms {
+                    self.active_faults.push(ActiveFault {
+                        client: step.client.clone(),
+                        original,
+                        end_time: Instant::now() + Duration::from_millis(duration_ms),
+                    });
+                }
+            }
+            ScenarioAction::ChannelFault => {
+                self.set_simulator_fault(simulator, &channel, step.channel_fault);
+                if let Some(duration_ms) = step.duration_ms {
+                    self.active_channel_faults.push(ActiveChannelFault {
+                        channel,
+                        end_time: Instant::now() + Duration::from_millis(duration_ms),
+                    });
+                }
+            }
         }
     }
 
-    fn get_simulator_value(&self, simulator: &Simulator, module_pos: usize, channel: u16) -> f64 {
-        if let Some(module) = simulator.modules.get(module_pos) {
+    fn get_simulator_value(&self, simulator: &Simulator, channel: &ChannelRef) -> f64 {
+        if let Some(module) = simulator.module_index(channel).and_then(|index| simulator.modules.get(index)) {
             let state = module.get_state();
-            if let Some(ch_state) = state.channels.get(channel as usize) {
+            if let Some(ch_state) = state.channels.get(channel.channel as usize) {
                 return match ch_state.value {
                     crate::models::ChannelValue::Bool(b) => if b { 1.0 } else { 0.0 },
                     crate::models::ChannelValue::Number(n) => n,
@@ -293,9 +406,19 @@ impl ScenarioEngine {
         0.0
     }
 
-    fn set_simulator_value(&self, simulator: &mut Simulator, module_pos: usize, channel: u16, value: f64) {
-        if let Some(module) = simulator.modules.get_mut(module_pos) {
-            module.set_channel_value(channel, value);
+    fn set_simulator_value(&self, simulator: &mut Simulator, channel: &ChannelRef, value: f64) {
+        if let Some(index) = simulator.module_index(channel) {
+            if let Some(module) = simulator.modules.get_mut(index) {
+                module.set_channel_value(channel.channel, value);
+            }
+        }
+    }
+
+    fn set_simulator_fault(&self, simulator: &mut Simulator, channel: &ChannelRef, fault: Option<ChannelFault>) {
+        if let Some(index) = simulator.module_index(channel) {
+            if let Some(module) = simulator.modules.get_mut(index) {
+                module.set_channel_fault(channel.channel, fault);
+            }
         }
     }
 }
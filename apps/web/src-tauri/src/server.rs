@@ -1,10 +1,24 @@
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::net::TcpListener;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio_modbus::prelude::*;
 use tokio_modbus::server::Service;
 use tokio_modbus::ExceptionCode;
-use crate::state::Simulator;
+use tokio_serial::{DataBits, Parity, SerialPortBuilderExt, SerialStream, StopBits};
+use crate::faults::FaultAction;
+use crate::models::{ServerStatus, SimulationState};
+use crate::pdu;
+use crate::sim_config::{SerialConfig, SerialParity, TransportKind, UnknownUnitPolicy};
+use crate::state::{DataArea, Simulator};
 
 struct ClientGuard {
     simulator: Arc<Mutex<Simulator>>,
@@ -19,125 +33,1075 @@ impl Drop for ClientGuard {
     }
 }
 
+/// Faults the service hands to the connection, which applies them when it
+/// frames the response (see `serve_mbap_frames` and `serve_rtu_frames`)
+#[derive(Default)]
+struct ConnectionFaults {
+    /// Close the connection instead of answering
+    close: AtomicBool,
+    /// Invert the transaction ID of the next response frame
+    corrupt_transaction_id: AtomicBool,
+}
+
 #[derive(Clone)]
 struct SimulatorService {
     simulator: Arc<Mutex<Simulator>>,
+    /// Configured listener the client connected to (selects the candidate racks);
+    /// `None` on a serial bus
+    endpoint: Option<(String, u16)>,
+    /// Client IP that per-client faults are keyed by; `None` on a serial bus
+    peer: Option<IpAddr>,
+    /// Whether responses are MBAP frames with a transaction ID
+    transaction_ids: bool,
+    connection: Arc<ConnectionFaults>,
     client_id: String,
     _guard: Arc<ClientGuard>,
 }
 
+impl SimulatorService {
+    /// Register a network client and build the service answering it.
+    fn connect(
+        simulator: Arc<Mutex<Simulator>>,
+        endpoint: (String, u16),
+        addr: SocketAddr,
+        transaction_ids: bool,
+    ) -> Self {
+        let client_id = simulator.lock().unwrap().register_modbus_client(addr);
+        let guard = Arc::new(ClientGuard {
+            simulator: simulator.clone(),
+            client_id: client_id.clone(),
+        });
+        Self {
+            simulator,
+            endpoint: Some(endpoint),
+            peer: Some(addr.ip()),
+            transaction_ids,
+            connection: Arc::new(ConnectionFaults::default()),
+            client_id,
+            _guard: guard,
+        }
+    }
+}
+
 impl Service for SimulatorService {
-    type Request = Request<'static>;
-    type Response = Response;
+    type Request = SlaveRequest<'static>;
+    // `None` suppresses the response (unknown unit with the `ignore` policy,
+    // frames for other slaves and broadcasts on a serial bus)
+    type Response = Option<Response>;
     type Exception = ExceptionCode;
-    type Future = std::future::Ready<Result<Self::Response, Self::Exception>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Exception>> + Send>>;
 
     fn call(&self, req: Self::Request) -> Self::Future {
-        let mut sim = self.simulator.lock().unwrap();
-        sim.touch_watchdog();
-        sim.note_client_activity(&self.client_id);
-        
-        let res = match req {
-            Request::ReadCoils(addr, cnt) => {
-                let coils = sim.read_coils();
-                let mut subset = Vec::new();
-                if (addr as usize) < coils.len() {
-                    let end = std::cmp::min(coils.len(), (addr + cnt) as usize);
-                    subset.extend_from_slice(&coils[addr as usize..end]);
-                }
-                if subset.len() < cnt as usize {
-                    subset.resize(cnt as usize, false);
-                }
-                Ok(Response::ReadCoils(subset))
-            }
-            Request::ReadDiscreteInputs(addr, cnt) => {
-                let inputs = sim.read_discrete_inputs();
-                let mut subset = Vec::new();
-                if (addr as usize) < inputs.len() {
-                    let end = std::cmp::min(inputs.len(), (addr + cnt) as usize);
-                    subset.extend_from_slice(&inputs[addr as usize..end]);
-                }
-                if subset.len() < cnt as usize {
-                    subset.resize(cnt as usize, false);
-                }
-                Ok(Response::ReadDiscreteInputs(subset))
-            }
-            Request::ReadHoldingRegisters(addr, cnt) => {
-                // Use the new general read method
-                let values = sim.read_holding_registers(addr, cnt);
-                Ok(Response::ReadHoldingRegisters(values))
-            }
-            Request::ReadInputRegisters(addr, cnt) => {
-                if let Some(special) = sim.read_special_input_registers(addr, cnt) {
-                    Ok(Response::ReadInputRegisters(special))
-                } else {
-                    let registers = sim.read_input_registers();
-                    let mut subset = Vec::new();
-                    if (addr as usize) < registers.len() {
-                        let end = std::cmp::min(registers.len(), (addr + cnt) as usize);
-                        subset.extend_from_slice(&registers[addr as usize..end]);
-                    }
-                    if subset.len() < cnt as usize {
-                        subset.resize(cnt as usize, 0);
-                    }
-                    Ok(Response::ReadInputRegisters(subset))
+        let (decision, result) = {
+            let mut sim = self.simulator.lock().unwrap();
+            sim.note_client_activity(&self.client_id);
+
+            // A watchdog timeout with 0x1009 set closes the connections it outlived
+            let connected_at = sim.modbus_clients.get(&self.client_id).map_or(0, |c| c.connected_at);
+            if let Some(endpoint) = &self.endpoint {
+                if sim.sockets_closed_since(endpoint, connected_at) {
+                    self.connection.close.store(true, Ordering::Relaxed);
+                    return Box::pin(std::future::ready(Ok(None)));
                 }
             }
-            Request::WriteSingleCoil(addr, val) => {
-                sim.write_coils(addr, &[val]);
-                Ok(Response::WriteSingleCoil(addr, val))
+
+            let decision = sim.faults.decide(self.peer);
+            (decision, route_with_fault(&mut sim, self.endpoint.as_ref(), req, decision.action))
+        };
+
+        // Socket-level faults only make sense on a connection; on a serial
+        // bus a close acts like a dropped response
+        match decision.action {
+            FaultAction::Close if self.endpoint.is_some() => {
+                self.connection.close.store(true, Ordering::Relaxed);
             }
-            Request::WriteMultipleCoils(addr, vals) => {
-                sim.write_coils(addr, &vals);
-                Ok(Response::WriteMultipleCoils(addr, vals.len() as u16))
+            FaultAction::CorruptTransactionId if self.transaction_ids && !matches!(result, Ok(None)) => {
+                self.connection.corrupt_transaction_id.store(true, Ordering::Relaxed);
             }
-            Request::WriteSingleRegister(addr, val) => {
-                sim.write_holding_registers(addr, &[val]);
-                Ok(Response::WriteSingleRegister(addr, val))
+            _ => {}
+        }
+
+        Box::pin(async move {
+            if !decision.delay.is_zero() {
+                tokio::time::sleep(decision.delay).await;
             }
-            Request::WriteMultipleRegisters(addr, vals) => {
-                sim.write_holding_registers(addr, &vals);
-                Ok(Response::WriteMultipleRegisters(addr, vals.len() as u16))
+            result
+        })
+    }
+}
+
+/// Route a request, or answer it as the injected fault dictates.
+fn route_with_fault(
+    sim: &mut Simulator,
+    endpoint: Option<&(String, u16)>,
+    req: SlaveRequest<'static>,
+    action: FaultAction,
+) -> Result<Option<Response>, ExceptionCode> {
+    match action {
+        // Only requests a rack would answer get the exception, so a serial
+        // slave still stays silent for frames addressed to other slaves
+        FaultAction::Exception(code) if sim.rack_for_unit(endpoint, req.slave).is_some() => {
+            Err(ExceptionCode::new(code))
+        }
+        FaultAction::Close => Ok(None),
+        FaultAction::Drop => {
+            // The device acts on the request; only the response is lost
+            let _ = route_request(sim, endpoint, req);
+            Ok(None)
+        }
+        _ => route_request(sim, endpoint, req),
+    }
+}
+
+/// Route a request to the rack answering on its unit ID, like a gateway.
+/// Shared by every transport so register semantics stay identical.
+fn route_request(
+    sim: &mut Simulator,
+    endpoint: Option<&(String, u16)>,
+    req: SlaveRequest<'static>,
+) -> Result<Option<Response>, ExceptionCode> {
+    let serial_bus = endpoint.is_none();
+    let function = pdu::function_code(&req.request);
+
+    // Unit 0 on a serial bus is a broadcast to every slave and is never answered
+    if serial_bus && req.slave == 0 {
+        for rack in 0..sim.racks.len() {
+            sim.touch_watchdog(rack, function);
+            let _ = handle_request(sim, rack, req.request.clone());
+        }
+        return Ok(None);
+    }
+
+    let Some(rack) = sim.rack_for_unit(endpoint, req.slave) else {
+        // A serial slave stays silent for frames addressed to other slaves
+        return match sim.unknown_unit_policy {
+            UnknownUnitPolicy::Ignore => Ok(None),
+            _ if serial_bus => Ok(None),
+            _ => Err(ExceptionCode::GatewayTargetDevice),
+        };
+    };
+    sim.touch_watchdog(rack, function);
+    handle_request(sim, rack, req.request).map(Some)
+}
+
+/// Answer a request from one rack's process image and registers.
+fn handle_request(sim: &mut Simulator, rack: usize, request: Request<'static>) -> Result<Response, ExceptionCode> {
+    if !sim.supports_function(rack, pdu::function_code(&request)) {
+        return Err(ExceptionCode::IllegalFunction);
+    }
+    for (area, addr, count, write) in addressed_ranges(&request) {
+        if !sim.is_mapped(rack, area, addr, count, write) {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+    }
+
+    match request {
+        Request::ReadCoils(addr, cnt) => {
+            Ok(Response::ReadCoils(sim.read_bit_range(rack, DataArea::Coils, addr, cnt)))
+        }
+        Request::ReadDiscreteInputs(addr, cnt) => {
+            Ok(Response::ReadDiscreteInputs(sim.read_bit_range(rack, DataArea::DiscreteInputs, addr, cnt)))
+        }
+        Request::ReadHoldingRegisters(addr, cnt) => {
+            // Use the new general read method
+            let values = sim.read_holding_registers(rack, addr, cnt);
+            Ok(Response::ReadHoldingRegisters(values))
+        }
+        Request::ReadInputRegisters(addr, cnt) => {
+            Ok(Response::ReadInputRegisters(sim.read_input_register_range(rack, addr, cnt)))
+        }
+        Request::WriteSingleCoil(addr, val) => {
+            sim.write_coils(rack, addr, &[val]);
+            Ok(Response::WriteSingleCoil(addr, val))
+        }
+        Request::WriteMultipleCoils(addr, vals) => {
+            sim.write_coils(rack, addr, &vals);
+            Ok(Response::WriteMultipleCoils(addr, vals.len() as u16))
+        }
+        Request::WriteSingleRegister(addr, val) => {
+            sim.write_holding_registers(rack, addr, &[val]);
+            Ok(Response::WriteSingleRegister(addr, val))
+        }
+        Request::WriteMultipleRegisters(addr, vals) => {
+            sim.write_holding_registers(rack, addr, &vals);
+            Ok(Response::WriteMultipleRegisters(addr, vals.len() as u16))
+        }
+        Request::MaskWriteRegister(addr, and_mask, or_mask) => {
+            sim.mask_write_holding_register(rack, addr, and_mask, or_mask);
+            Ok(Response::MaskWriteRegister(addr, and_mask, or_mask))
+        }
+        Request::ReadWriteMultipleRegisters(read_addr, cnt, write_addr, vals) => {
+            // The write is performed before the read
+            sim.write_holding_registers(rack, write_addr, &vals);
+            let values = sim.read_holding_registers(rack, read_addr, cnt);
+            Ok(Response::ReadWriteMultipleRegisters(values))
+        }
+        Request::ReadDeviceIdentification(read_code, object_id) => {
+            read_device_identification(sim, rack, read_code, object_id)
+        }
+        _ => {
+            Err(ExceptionCode::IllegalFunction)
+        }
+    }
+}
+
+/// FC43 (Encapsulated Interface Transport) and its Read Device Identification MEI type
+const ENCAPSULATED_INTERFACE_TRANSPORT: u8 = 0x2B;
+const MEI_READ_DEVICE_ID: u8 = 0x0E;
+/// Basic and regular identification, stream and individual access
+const DEVICE_ID_CONFORMITY: u8 = 0x82;
+
+/// Data areas, start addresses, item counts and directions a request touches,
+/// for address validation.
+fn addressed_ranges(request: &Request<'_>) -> Vec<(DataArea, u16, usize, bool)> {
+    match request {
+        Request::ReadCoils(addr, cnt) => vec![(DataArea::Coils, *addr, *cnt as usize, false)],
+        Request::ReadDiscreteInputs(addr, cnt) => vec![(DataArea::DiscreteInputs, *addr, *cnt as usize, false)],
+        Request::ReadHoldingRegisters(addr, cnt) => vec![(DataArea::HoldingRegisters, *addr, *cnt as usize, false)],
+        Request::ReadInputRegisters(addr, cnt) => vec![(DataArea::InputRegisters, *addr, *cnt as usize, false)],
+        Request::WriteSingleCoil(addr, _) => vec![(DataArea::Coils, *addr, 1, true)],
+        Request::WriteMultipleCoils(addr, vals) => vec![(DataArea::Coils, *addr, vals.len(), true)],
+        Request::WriteSingleRegister(addr, _) => vec![(DataArea::HoldingRegisters, *addr, 1, true)],
+        Request::WriteMultipleRegisters(addr, vals) => vec![(DataArea::HoldingRegisters, *addr, vals.len(), true)],
+        Request::MaskWriteRegister(addr, _, _) => vec![(DataArea::HoldingRegisters, *addr, 1, true)],
+        Request::ReadWriteMultipleRegisters(read_addr, cnt, write_addr, vals) => vec![
+            (DataArea::HoldingRegisters, *write_addr, vals.len(), true),
+            (DataArea::HoldingRegisters, *read_addr, *cnt as usize, false),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// FC43/14 Read Device Identification. All objects fit in one response,
+/// so "more follows" is never set.
+fn read_device_identification(
+    sim: &Simulator,
+    rack: usize,
+    read_code: ReadCode,
+    object_id: ObjectId,
+) -> Result<Response, ExceptionCode> {
+    let objects = sim.device_identification(rack);
+    let selected: Vec<&(u8, String)> = match read_code {
+        // Stream access: basic (0x00-0x02) or regular (everything we have);
+        // an unknown starting object restarts from the first one
+        ReadCode::Basic | ReadCode::Regular | ReadCode::Extended => {
+            let category: Vec<&(u8, String)> = objects
+                .iter()
+                .filter(|(id, _)| read_code != ReadCode::Basic || *id <= 0x02)
+                .collect();
+            let start = category.iter().position(|(id, _)| *id == object_id).unwrap_or(0);
+            category[start..].to_vec()
+        }
+        // Individual access
+        ReadCode::Specific => match objects.iter().find(|(id, _)| *id == object_id) {
+            Some(object) => vec![object],
+            None => return Err(ExceptionCode::IllegalDataAddress),
+        },
+    };
+
+    let mut pdu = vec![MEI_READ_DEVICE_ID, read_code.value(), DEVICE_ID_CONFORMITY, 0x00, 0x00, selected.len() as u8];
+    for (id, value) in selected {
+        pdu.push(*id);
+        pdu.push(value.len() as u8);
+        pdu.extend_from_slice(value.as_bytes());
+    }
+    Ok(Response::Custom(ENCAPSULATED_INTERFACE_TRANSPORT, pdu.into()))
+}
+
+/// Bind the Modbus TCP listener to the configured host and port.
+/// Port 0 asks the OS for a free port; use `local_addr()` on the result to find it.
+pub async fn bind_listener(host: &str, port: u16) -> std::io::Result<TcpListener> {
+    TcpListener::bind((host, port)).await
+}
+
+/// Serve the racks configured on `endpoint` until the listener fails or
+/// `shutdown` fires.
+pub async fn run_server(
+    simulator: Arc<Mutex<Simulator>>,
+    endpoint: (String, u16),
+    listener: TcpListener,
+    shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    serve_connections(simulator, endpoint, listener, shutdown, TcpFraming::Mbap).await
+}
+
+/// Serve RTU frames over TCP connections (RTU-over-TCP gateways), with the same
+/// unit ID routing as Modbus TCP, until the listener fails or `shutdown` fires.
+pub async fn run_rtu_over_tcp_server(
+    simulator: Arc<Mutex<Simulator>>,
+    endpoint: (String, u16),
+    listener: TcpListener,
+    shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    serve_connections(simulator, endpoint, listener, shutdown, TcpFraming::Rtu).await
+}
+
+/// How requests and responses are framed on a TCP connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TcpFraming {
+    /// MBAP header with transaction ID (Modbus TCP)
+    Mbap,
+    /// Unit ID and CRC, as on a serial line (RTU over TCP)
+    Rtu,
+}
+
+/// Accept clients on `listener` and answer each on its own task. Every
+/// connection ends when the server is asked to stop, so no socket lingers.
+async fn serve_connections(
+    simulator: Arc<Mutex<Simulator>>,
+    endpoint: (String, u16),
+    listener: TcpListener,
+    mut shutdown: watch::Receiver<bool>,
+    framing: TcpFraming,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown.wait_for(|stop| *stop) => return Ok(()),
+        };
+        let transaction_ids = framing == TcpFraming::Mbap;
+        let service = SimulatorService::connect(simulator.clone(), endpoint.clone(), addr, transaction_ids);
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let served = match framing {
+                TcpFraming::Mbap => serve_mbap_frames(stream, service, shutdown).await,
+                TcpFraming::Rtu => serve_rtu_frames(stream, service, shutdown).await,
+            };
+            if let Err(e) = served {
+                eprintln!("Modbus client error: {}", e);
             }
-            _ => {
-                Err(ExceptionCode::IllegalFunction)
+        });
+    }
+}
+
+/// Answer the MBAP frames arriving on one connection until the client
+/// disconnects or `shutdown` fires. A frame with a foreign protocol ID is
+/// dropped unanswered; a length field out of range closes the connection,
+/// as the stream can no longer be split into frames.
+async fn serve_mbap_frames(
+    mut stream: TcpStream,
+    service: SimulatorService,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        let len = tokio::select! {
+            read = stream.read(&mut chunk) => read?,
+            _ = shutdown.wait_for(|stop| *stop) => return Ok(()),
+        };
+        if len == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..len]);
+        while let Some(frame_len) = pdu::mbap_frame_len(&buf) {
+            if !(pdu::MBAP_HEADER_LEN + 1..=pdu::MBAP_HEADER_LEN + pdu::MAX_PDU_LEN).contains(&frame_len) {
+                return Ok(());
+            }
+            if buf.len() < frame_len {
+                break;
+            }
+            let frame: Vec<u8> = buf.drain(..frame_len).collect();
+            let Some((mut header, request_pdu)) = pdu::split_mbap(&frame) else { continue };
+            let result = match pdu::decode_request(request_pdu) {
+                Ok(request) => service.call(SlaveRequest { slave: header.unit_id, request }).await.transpose(),
+                Err(e) => Some(Err(e)),
+            };
+            if service.connection.close.load(Ordering::Relaxed) {
+                return Ok(());
             }
+            if let Some(result) = result {
+                if service.connection.corrupt_transaction_id.swap(false, Ordering::Relaxed) {
+                    header.transaction_id = !header.transaction_id;
+                }
+                let reply = pdu::encode_mbap(header, &pdu::encode_response(request_pdu[0], &result));
+                stream.write_all(&reply).await?;
+            }
+        }
+    }
+}
+
+/// Answer the RTU frames arriving on one connection until the client
+/// disconnects or `shutdown` fires. A frame with a bad CRC is dropped
+/// unanswered, like on a serial line, along with the rest of the segment.
+async fn serve_rtu_frames(
+    mut stream: TcpStream,
+    service: SimulatorService,
+    mut shutdown: watch::Receiver<bool>,
+) -> io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 256];
+    loop {
+        let len = tokio::select! {
+            read = stream.read(&mut chunk) => read?,
+            _ = shutdown.wait_for(|stop| *stop) => return Ok(()),
         };
-        
-        std::future::ready(res)
-    }
-}
-
-pub async fn run_server(simulator: Arc<Mutex<Simulator>>, port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let listener = TcpListener::bind(addr).await?;
-    let server = tokio_modbus::server::tcp::Server::new(listener);
-    
-    let on_connected = Box::new(move |stream, addr| {
-        let simulator = simulator.clone();
-        async move {
-            let client_id = {
-                let mut sim = simulator.lock().unwrap();
-                sim.register_modbus_client(addr)
+        if len == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..len]);
+        while let Some(frame_len) = pdu::rtu_request_len(&buf) {
+            let frame: Vec<u8> = buf.drain(..frame_len).collect();
+            let Some((unit_id, request_pdu)) = pdu::split_rtu(&frame) else {
+                buf.clear();
+                break;
             };
-            let guard = Arc::new(ClientGuard {
-                simulator: simulator.clone(),
-                client_id: client_id.clone(),
-            });
-            let service = SimulatorService {
-                simulator,
-                client_id,
-                _guard: guard,
+            let result = match pdu::decode_request(request_pdu) {
+                Ok(request) => service.call(SlaveRequest { slave: unit_id, request }).await.transpose(),
+                Err(e) => Some(Err(e)),
             };
-            Ok(Some((service, stream)))
+            if service.connection.close.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            if let Some(result) = result {
+                let reply = pdu::encode_rtu(unit_id, &pdu::encode_response(request_pdu[0], &result));
+                stream.write_all(&reply).await?;
+            }
         }
-    });
+    }
+}
+
+/// UDP peers are listed as clients until they have been silent this long
+const UDP_PEER_IDLE: Duration = Duration::from_secs(60);
+/// Most UDP peers tracked at once; the longest silent one makes room for a new one
+const MAX_UDP_PEERS: usize = 64;
+
+/// Serve Modbus UDP: one MBAP-framed request per datagram, answered to the
+/// sender. Each peer is listed as a client while it keeps sending.
+pub async fn run_udp_server(
+    simulator: Arc<Mutex<Simulator>>,
+    endpoint: (String, u16),
+    socket: UdpSocket,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let socket = Arc::new(socket);
+    let mut peers: HashMap<SocketAddr, (SimulatorService, Instant)> = HashMap::new();
+    let mut buf = [0u8; pdu::MBAP_HEADER_LEN + 260];
+    loop {
+        let (len, peer) = tokio::select! {
+            received = socket.recv_from(&mut buf) => received?,
+            _ = shutdown.wait_for(|stop| *stop) => return Ok(()),
+        };
+        let now = Instant::now();
+        if !peers.contains_key(&peer) {
+            // Dropping a service removes its client entry
+            peers.retain(|_, (_, last_seen)| now.duration_since(*last_seen) < UDP_PEER_IDLE);
+            if peers.len() >= MAX_UDP_PEERS {
+                let oldest = peers.iter().min_by_key(|(_, (_, last_seen))| *last_seen).map(|(addr, _)| *addr);
+                if let Some(oldest) = oldest {
+                    peers.remove(&oldest);
+                }
+            }
+        }
+        let (service, last_seen) = peers
+            .entry(peer)
+            .or_insert_with(|| (SimulatorService::connect(simulator.clone(), endpoint.clone(), peer, true), now));
+        *last_seen = now;
+        let (decision, reply) = {
+            let mut sim = simulator.lock().unwrap();
+            sim.note_client_activity(&service.client_id);
+            let decision = sim.faults.decide(Some(peer.ip()));
+            (decision, answer_mbap_frame(&mut sim, &endpoint, &buf[..len], decision.action))
+        };
+        let Some(reply) = reply else { continue };
+        if decision.delay.is_zero() {
+            socket.send_to(&reply, peer).await?;
+        } else {
+            // A delayed reply must not hold up other peers
+            let socket = socket.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(decision.delay).await;
+                let _ = socket.send_to(&reply, peer).await;
+            });
+        }
+    }
+}
 
-    let on_process_error = |err| {
-        eprintln!("Modbus server error: {:?}", err);
+/// Answer one MBAP-framed request; `None` means no reply is sent.
+/// There is no connection to close over UDP, so a close fault drops the reply.
+fn answer_mbap_frame(
+    sim: &mut Simulator,
+    endpoint: &(String, u16),
+    frame: &[u8],
+    fault: FaultAction,
+) -> Option<Vec<u8>> {
+    let (mut header, request_pdu) = pdu::split_mbap(frame)?;
+    let result = match pdu::decode_request(request_pdu) {
+        Ok(request) => route_with_fault(sim, Some(endpoint), SlaveRequest { slave: header.unit_id, request }, fault)
+            .transpose()?,
+        Err(e) => Err(e),
     };
+    if fault == FaultAction::CorruptTransactionId {
+        header.transaction_id = !header.transaction_id;
+    }
+    Some(pdu::encode_mbap(header, &pdu::encode_response(request_pdu[0], &result)))
+}
+
+/// Serve every rack as an RTU slave (by unit ID) on an open serial port until
+/// the port fails or `shutdown` fires.
+pub async fn run_rtu_server(
+    simulator: Arc<Mutex<Simulator>>,
+    port_name: String,
+    port: SerialStream,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // The bus master is not a socket peer, so it is not listed as a client
+    let client_id = format!("serial-{}", port_name);
+    let guard = Arc::new(ClientGuard {
+        simulator: simulator.clone(),
+        client_id: client_id.clone(),
+    });
+    let service = SimulatorService {
+        simulator,
+        endpoint: None,
+        peer: None,
+        transaction_ids: false,
+        connection: Arc::new(ConnectionFaults::default()),
+        client_id,
+        _guard: guard,
+    };
+    let server = tokio_modbus::server::rtu::Server::new(port);
+
+    tokio::select! {
+        result = server.serve_forever(service) => result?,
+        _ = shutdown.wait_for(|stop| *stop) => {}
+    }
 
-    server.serve(&on_connected, on_process_error).await?;
-    
     Ok(())
 }
+
+/// Open a serial port (or PTY) with the configured line settings.
+pub fn open_serial_port(serial: &SerialConfig) -> io::Result<SerialStream> {
+    let data_bits = match serial.data_bits {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        _ => DataBits::Eight,
+    };
+    let parity = match serial.parity {
+        SerialParity::None => Parity::None,
+        SerialParity::Even => Parity::Even,
+        SerialParity::Odd => Parity::Odd,
+    };
+    let stop_bits = match serial.stop_bits {
+        2 => StopBits::Two,
+        _ => StopBits::One,
+    };
+    tokio_serial::new(&serial.port, serial.baud_rate)
+        .data_bits(data_bits)
+        .parity(parity)
+        .stop_bits(stop_bits)
+        .open_native_async()
+        .map_err(io::Error::from)
+}
+
+/// e.g. `/dev/ttyUSB0 (19200 8E1)`
+fn describe_serial(serial: &SerialConfig) -> String {
+    let parity = match serial.parity {
+        SerialParity::None => 'N',
+        SerialParity::Even => 'E',
+        SerialParity::Odd => 'O',
+    };
+    format!("{} ({} {}{}{})", serial.port, serial.baud_rate, serial.data_bits, parity, serial.stop_bits)
+}
+
+// ============================================================================
+// Server Lifecycle
+// ============================================================================
+
+/// What the server is bound to: network endpoints served with a transport,
+/// or a serial line for RTU
+#[derive(Debug, Clone, PartialEq)]
+enum Binding {
+    Network(TransportKind, Vec<(String, u16)>),
+    Rtu(SerialConfig),
+}
+
+impl Binding {
+    fn configured(sim: &Simulator) -> Self {
+        match (sim.transport_kind, &sim.serial) {
+            (TransportKind::ModbusRtu, Some(serial)) => Binding::Rtu(serial.clone()),
+            (kind, _) => Binding::Network(kind, sim.listen_endpoints()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Binding::Network(_, endpoints) => endpoints
+                .iter()
+                .map(|(host, port)| format!("{}:{}", host, port))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Binding::Rtu(serial) => describe_serial(serial),
+        }
+    }
+}
+
+/// A bound network socket and the transport it serves
+enum NetworkListener {
+    Tcp(TcpListener),
+    RtuOverTcp(TcpListener),
+    Udp(UdpSocket),
+}
+
+impl NetworkListener {
+    async fn bind(kind: TransportKind, host: &str, port: u16) -> io::Result<(Self, SocketAddr)> {
+        let listener = match kind {
+            TransportKind::ModbusUdp => NetworkListener::Udp(UdpSocket::bind((host, port)).await?),
+            TransportKind::ModbusRtuOverTcp => NetworkListener::RtuOverTcp(bind_listener(host, port).await?),
+            _ => NetworkListener::Tcp(bind_listener(host, port).await?),
+        };
+        let addr = match &listener {
+            NetworkListener::Tcp(l) | NetworkListener::RtuOverTcp(l) => l.local_addr()?,
+            NetworkListener::Udp(socket) => socket.local_addr()?,
+        };
+        Ok((listener, addr))
+    }
+
+    async fn serve(
+        self,
+        simulator: Arc<Mutex<Simulator>>,
+        endpoint: (String, u16),
+        shutdown: watch::Receiver<bool>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            NetworkListener::Tcp(listener) => run_server(simulator, endpoint, listener, shutdown).await,
+            NetworkListener::RtuOverTcp(listener) => run_rtu_over_tcp_server(simulator, endpoint, listener, shutdown).await,
+            NetworkListener::Udp(socket) => run_udp_server(simulator, endpoint, socket, shutdown).await,
+        }
+    }
+}
+
+struct ServerHandle {
+    /// What the server was started with (used to detect config changes)
+    binding: Binding,
+    /// Shared by all listeners; a failing listener uses it to stop the others
+    shutdown: Arc<watch::Sender<bool>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    fn is_running(&self) -> bool {
+        self.tasks.iter().any(|task| !task.is_finished())
+    }
+
+    /// Stop the listeners and close their client sockets
+    async fn shut_down(self) {
+        let _ = self.shutdown.send(true);
+        for task in self.tasks {
+            task.abort();
+            // Wait for the listener to be dropped so a restart can rebind
+            let _ = task.await;
+        }
+    }
+}
+
+/// Owns the running Modbus listeners (one per distinct rack endpoint, or the
+/// serial line for RTU) so they can be stopped and restarted together.
+/// Status changes are mirrored into the Simulator for `get_rack_state`.
+#[derive(Default)]
+pub struct ServerManager {
+    handle: tokio::sync::Mutex<Option<ServerHandle>>,
+}
+
+impl ServerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind every configured endpoint (or open the serial port) and start serving.
+    /// Returns the bound TCP addresses. A no-op if the server is already listening
+    /// as configured; a server bound to another transport or endpoint is restarted.
+    /// If any endpoint fails to bind, none are served.
+    pub async fn start(&self, simulator: Arc<Mutex<Simulator>>) -> Result<Vec<SocketAddr>, String> {
+        let mut handle = self.handle.lock().await;
+        let binding = Binding::configured(&*simulator.lock().map_err(|e| e.to_string())?);
+        if let Some(running) = handle.take() {
+            if running.is_running() && running.binding == binding {
+                let addrs = simulator.lock().map_err(|e| e.to_string())?.listen_addresses.clone();
+                *handle = Some(running);
+                return Ok(addrs);
+            }
+            running.shut_down().await;
+        }
+
+        if let Ok(mut sim) = simulator.lock() {
+            sim.server_status = ServerStatus::Binding { address: binding.describe() };
+        }
+
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let shutdown = Arc::new(shutdown);
+        let (tasks, local_addrs) = match &binding {
+            Binding::Network(kind, endpoints) => {
+                let mut listeners = Vec::new();
+                for (host, port) in endpoints {
+                    let bound = NetworkListener::bind(*kind, host, *port).await;
+                    match bound {
+                        Ok(bound) => listeners.push(bound),
+                        Err(e) => {
+                            let reason = format!("Failed to bind Modbus server to {}:{}: {}", host, port, e);
+                            set_stopped(&simulator, ServerStatus::Failed { reason: reason.clone() });
+                            return Err(reason);
+                        }
+                    }
+                }
+                let local_addrs: Vec<SocketAddr> = listeners.iter().map(|(_, addr)| *addr).collect();
+                let tasks = endpoints
+                    .iter()
+                    .cloned()
+                    .zip(listeners)
+                    .map(|(endpoint, (listener, _))| {
+                        let shutdown_rx = shutdown_rx.clone();
+                        spawn_server(&simulator, &shutdown, move |simulator| {
+                            listener.serve(simulator, endpoint, shutdown_rx)
+                        })
+                    })
+                    .collect();
+                (tasks, local_addrs)
+            }
+            Binding::Rtu(serial) => {
+                let port = match open_serial_port(serial) {
+                    Ok(port) => port,
+                    Err(e) => {
+                        let reason = format!("Failed to open serial port {}: {}", serial.port, e);
+                        set_stopped(&simulator, ServerStatus::Failed { reason: reason.clone() });
+                        return Err(reason);
+                    }
+                };
+                let port_name = serial.port.clone();
+                let task = spawn_server(&simulator, &shutdown, move |simulator| {
+                    run_rtu_server(simulator, port_name, port, shutdown_rx)
+                });
+                (vec![task], Vec::new())
+            }
+        };
+
+        {
+            let mut sim = simulator.lock().map_err(|e| e.to_string())?;
+            sim.simulation_state = SimulationState::Running;
+            sim.listen_addresses = local_addrs.clone();
+            sim.server_status = ServerStatus::Listening {
+                address: match &binding {
+                    Binding::Network(..) => local_addrs.iter().map(|addr| addr.to_string()).collect::<Vec<_>>().join(", "),
+                    Binding::Rtu(serial) => describe_serial(serial),
+                },
+            };
+        }
+
+        *handle = Some(ServerHandle {
+            binding,
+            shutdown,
+            tasks,
+        });
+        Ok(local_addrs)
+    }
+
+    /// Stop accepting connections, close all client sockets and release the ports.
+    pub async fn stop(&self, simulator: &Arc<Mutex<Simulator>>) {
+        let mut handle = self.handle.lock().await;
+        if let Some(running) = handle.take() {
+            running.shut_down().await;
+        }
+        set_stopped(simulator, ServerStatus::Stopped);
+    }
+
+    pub async fn restart(&self, simulator: Arc<Mutex<Simulator>>) -> Result<Vec<SocketAddr>, String> {
+        self.stop(&simulator).await;
+        self.start(simulator).await
+    }
+
+    /// Restart a running server if the configured transport or endpoints changed.
+    pub async fn apply_config(&self, simulator: Arc<Mutex<Simulator>>) -> Result<(), String> {
+        let running_binding = {
+            let handle = self.handle.lock().await;
+            handle
+                .as_ref()
+                .filter(|running| running.is_running())
+                .map(|running| running.binding.clone())
+        };
+
+        if let Some(binding) = running_binding {
+            let wanted = Binding::configured(&*simulator.lock().map_err(|e| e.to_string())?);
+            if binding != wanted {
+                self.restart(simulator).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Spawn one server task. A runtime error marks the server failed and, since
+/// all racks are served as one simulator, takes the other listeners down too.
+fn spawn_server<F, Fut>(
+    simulator: &Arc<Mutex<Simulator>>,
+    shutdown: &Arc<watch::Sender<bool>>,
+    serve: F,
+) -> JoinHandle<()>
+where
+    F: FnOnce(Arc<Mutex<Simulator>>) -> Fut,
+    Fut: Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send + 'static,
+{
+    let task_simulator = simulator.clone();
+    let task_shutdown = shutdown.clone();
+    let server = serve(simulator.clone());
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("Modbus server error: {}", e);
+            let _ = task_shutdown.send(true);
+            set_stopped(&task_simulator, ServerStatus::Failed { reason: e.to_string() });
+        }
+    })
+}
+
+fn set_stopped(simulator: &Arc<Mutex<Simulator>>, status: ServerStatus) {
+    if let Ok(mut sim) = simulator.lock() {
+        sim.simulation_state = SimulationState::Stopped;
+        sim.listen_addresses.clear();
+        sim.server_status = status;
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::faults::FaultConfig;
+
+    const RACKS: &str = r#"
+version: 2
+sim: { name: 'test', seed: 1, tick_ms: 10 }
+transport:
+  kind: 'modbus_tcp'
+  listen: { host: '127.0.0.1', port: 0 }
+  unit_id: 1
+process_image: { layout: 'wago_750_default', word_endian: 'big', align_modules_to: 2 }
+modbus_map:
+  inputs: { kind: 'input_registers', base: 0 }
+  outputs: { kind: 'holding_registers', base: 0 }
+racks:
+  - id: 'rack0'
+    name: 'Rack 0'
+    modules:
+      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }
+  - id: 'rack1'
+    name: 'Rack 1'
+    unit_id: 7
+    modules:
+      - { id: 'r1m0', model: '750-1515', name: 'DO', channels: [] }
+"#;
+
+    fn simulator(yaml: &str) -> Arc<Mutex<Simulator>> {
+        let simulator = Arc::new(Mutex::new(Simulator::new()));
+        simulator.lock().unwrap().load_from_yaml_string(yaml).unwrap();
+        simulator
+    }
+
+    async fn read_reply(stream: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut reply = vec![0; len];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut reply)).await.unwrap().unwrap();
+        reply
+    }
+
+    /// Send one Modbus TCP request and return the response PDU.
+    async fn transact(stream: &mut TcpStream, unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x00, 0x01, 0x00, 0x00];
+        frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        frame.push(unit_id);
+        frame.extend_from_slice(pdu);
+        stream.write_all(&frame).await.unwrap();
+
+        let header = read_reply(stream, 7).await;
+        assert_eq!(header[..4], frame[..4]);
+        assert_eq!(header[6], unit_id);
+        read_reply(stream, u16::from_be_bytes([header[4], header[5]]) as usize - 1).await
+    }
+
+    #[tokio::test]
+    async fn test_rtu_over_tcp() {
+        let simulator = simulator(&RACKS.replace("kind: 'modbus_tcp'", "kind: 'modbus_rtu_over_tcp'"));
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut stream = TcpStream::connect(addrs[0]).await.unwrap();
+
+        // A frame split across segments is answered once complete
+        let write_coil = pdu::encode_rtu(7, &[0x05, 0x00, 0x00, 0xFF, 0x00]);
+        stream.write_all(&write_coil[..3]).await.unwrap();
+        stream.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        stream.write_all(&write_coil[3..]).await.unwrap();
+        assert_eq!(read_reply(&mut stream, 8).await, write_coil);
+
+        // A bad CRC is not answered
+        let mut corrupted = pdu::encode_rtu(7, &[0x01, 0x00, 0x00, 0x00, 0x01]);
+        corrupted[7] ^= 0xFF;
+        stream.write_all(&corrupted).await.unwrap();
+        let mut byte = [0u8; 1];
+        assert!(tokio::time::timeout(Duration::from_millis(100), stream.read(&mut byte)).await.is_err());
+
+        // Routed by unit ID like Modbus TCP; the coil reads back at 0x0200
+        stream.write_all(&pdu::encode_rtu(7, &[0x01, 0x02, 0x00, 0x00, 0x01])).await.unwrap();
+        assert_eq!(read_reply(&mut stream, 6).await, pdu::encode_rtu(7, &[0x01, 0x01, 0x01]));
+        stream.write_all(&pdu::encode_rtu(9, &[0x01, 0x00, 0x00, 0x00, 0x01])).await.unwrap();
+        assert_eq!(read_reply(&mut stream, 5).await, pdu::encode_rtu(9, &[0x81, 0x0B]));
+
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_tcp_units_0_and_255_reach_the_coupler() {
+        let one_rack = &RACKS[..RACKS.find("  - id: 'rack1'").unwrap()];
+        let simulator = simulator(one_rack);
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut stream = TcpStream::connect(addrs[0]).await.unwrap();
+
+        let read_input = [0x02, 0x00, 0x00, 0x00, 0x01];
+        let answers: [(u16, u8, &[u8]); 3] = [(1, 0, &[0x02, 0x01, 0x00]), (2, 255, &[0x02, 0x01, 0x00]), (3, 2, &[0x82, 0x0B])];
+        for (transaction_id, unit_id, reply) in answers {
+            let header = pdu::MbapHeader { transaction_id, unit_id };
+            stream.write_all(&pdu::encode_mbap(header, &read_input)).await.unwrap();
+            let expected = pdu::encode_mbap(header, reply);
+            assert_eq!(read_reply(&mut stream, expected.len()).await, expected);
+        }
+
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_udp_peers_are_bounded() {
+        let simulator = simulator(&RACKS.replace("kind: 'modbus_tcp'", "kind: 'modbus_udp'"));
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+
+        let request = pdu::encode_mbap(pdu::MbapHeader { transaction_id: 1, unit_id: 1 }, &[0x02, 0x00, 0x00, 0x00, 0x01]);
+        let mut sockets = Vec::new();
+        for _ in 0..MAX_UDP_PEERS + 8 {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            socket.send_to(&request, addrs[0]).await.unwrap();
+            let mut reply = [0u8; 32];
+            tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut reply)).await.unwrap().unwrap();
+            sockets.push(socket);
+        }
+        assert_eq!(simulator.lock().unwrap().modbus_clients.len(), MAX_UDP_PEERS);
+
+        manager.stop(&simulator).await;
+        assert!(simulator.lock().unwrap().modbus_clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_transaction_id_in_pipelined_frames() {
+        let simulator = simulator(RACKS);
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut stream = TcpStream::connect(addrs[0]).await.unwrap();
+
+        // Two requests in one segment; only the transaction IDs change
+        let read_input = [0x02, 0x00, 0x00, 0x00, 0x01];
+        let first = pdu::MbapHeader { transaction_id: 0x0102, unit_id: 1 };
+        let second = pdu::MbapHeader { transaction_id: 0x0304, unit_id: 1 };
+        let mut requests = pdu::encode_mbap(first, &read_input);
+        requests.extend(pdu::encode_mbap(second, &read_input));
+        let config = FaultConfig { corrupt_transaction_id_rate: 1.0, ..Default::default() };
+        simulator.lock().unwrap().faults.set(None, config);
+        stream.write_all(&requests).await.unwrap();
+
+        for header in [first, second] {
+            let corrupted = pdu::MbapHeader { transaction_id: !header.transaction_id, ..header };
+            let expected = pdu::encode_mbap(corrupted, &[0x02, 0x01, 0x00]);
+            assert_eq!(read_reply(&mut stream, expected.len()).await, expected);
+        }
+
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_stop_releases_the_port() {
+        let simulator = simulator(RACKS);
+        let manager = ServerManager::new();
+        let port = manager.start(simulator.clone()).await.unwrap()[0].port();
+        manager.stop(&simulator).await;
+        assert_eq!(simulator.lock().unwrap().server_status, ServerStatus::Stopped);
+
+        // Bind the same port again
+        let fixed = RACKS.replace("port: 0", &format!("port: {}", port));
+        simulator.lock().unwrap().load_from_yaml_string(&fixed).unwrap();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        assert_eq!(addrs[0].port(), port);
+        TcpStream::connect(addrs[0]).await.unwrap();
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_restart_rebinds_and_closes_clients() {
+        let simulator = simulator(RACKS);
+        let manager = ServerManager::new();
+        let port = manager.start(simulator.clone()).await.unwrap()[0].port();
+        manager.stop(&simulator).await;
+        let fixed = RACKS.replace("port: 0", &format!("port: {}", port));
+        simulator.lock().unwrap().load_from_yaml_string(&fixed).unwrap();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut client = TcpStream::connect(addrs[0]).await.unwrap();
+
+        assert_eq!(manager.restart(simulator.clone()).await.unwrap(), addrs);
+        let mut byte = [0u8; 1];
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read(&mut byte)).await.unwrap();
+        assert!(matches!(read, Ok(0) | Err(_)));
+        TcpStream::connect(addrs[0]).await.unwrap();
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_start_while_running() {
+        let simulator = simulator(RACKS);
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut client = TcpStream::connect(addrs[0]).await.unwrap();
+
+        // Same binding: nothing changes and connected clients stay served
+        assert_eq!(manager.start(simulator.clone()).await.unwrap(), addrs);
+        let header = pdu::MbapHeader { transaction_id: 1, unit_id: 1 };
+        client.write_all(&pdu::encode_mbap(header, &[0x02, 0x00, 0x00, 0x00, 0x01])).await.unwrap();
+        let expected = pdu::encode_mbap(header, &[0x02, 0x01, 0x00]);
+        assert_eq!(read_reply(&mut client, expected.len()).await, expected);
+
+        // Another transport: the server is rebound
+        let udp = RACKS.replace("kind: 'modbus_tcp'", "kind: 'modbus_udp'");
+        simulator.lock().unwrap().load_from_yaml_string(&udp).unwrap();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket.send_to(&pdu::encode_mbap(header, &[0x02, 0x00, 0x00, 0x00, 0x01]), addrs[0]).await.unwrap();
+        let mut reply = [0u8; 32];
+        let len = tokio::time::timeout(Duration::from_secs(5), socket.recv(&mut reply)).await.unwrap().unwrap();
+        assert_eq!(&reply[..len], &expected[..]);
+        manager.stop(&simulator).await;
+    }
+
+    #[tokio::test]
+    async fn test_read_device_identification() {
+        let simulator = simulator(RACKS);
+        let objects = simulator.lock().unwrap().device_identification(1);
+        let manager = ServerManager::new();
+        let addrs = manager.start(simulator.clone()).await.unwrap();
+        let mut stream = TcpStream::connect(addrs[0]).await.unwrap();
+
+        // Basic stream access returns vendor, product code and revision
+        let mut expected = vec![0x2B, 0x0E, 0x01, 0x82, 0x00, 0x00, 0x03];
+        for (id, value) in &objects[..3] {
+            expected.extend([*id, value.len() as u8]);
+            expected.extend_from_slice(value.as_bytes());
+        }
+        assert_eq!(transact(&mut stream, 7, &[0x2B, 0x0E, 0x01, 0x00]).await, expected);
+
+        // Individual access to one object, and to one that does not exist
+        let (id, value) = &objects[1];
+        let mut expected = vec![0x2B, 0x0E, 0x04, 0x82, 0x00, 0x00, 0x01, *id, value.len() as u8];
+        expected.extend_from_slice(value.as_bytes());
+        assert_eq!(transact(&mut stream, 7, &[0x2B, 0x0E, 0x04, 0x01]).await, expected);
+        assert_eq!(transact(&mut stream, 7, &[0x2B, 0x0E, 0x04, 0x80]).await, vec![0xAB, 0x02]);
+
+        manager.stop(&simulator).await;
+    }
+}
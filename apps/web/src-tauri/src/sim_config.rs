@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::scenario::Scenario;
 use crate::reactive::ReactiveScenario;
+use crate::models::CouplerMetadata;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SimConfigRoot {
@@ -10,6 +11,10 @@ pub struct SimConfigRoot {
     pub transport: TransportConfig,
     pub process_image: ProcessImageConfig,
     pub modbus_map: ModbusMapConfig,
+    /// Path of a module catalog file whose descriptors replace or extend the
+    /// built-in ones by part number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module_catalog: Option<String>,
     pub racks: Vec<RackDefinition>,
     /// Scripted scenarios (step-by-step actions)
     pub scenarios: Option<Vec<Scenario>>,
@@ -27,43 +32,209 @@ pub struct SimSettings {
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TransportConfig {
-    pub kind: String,
+    pub kind: TransportKind,
     pub listen: ListenConfig,
+    /// Serial line settings, required for `modbus_rtu`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<SerialConfig>,
     pub unit_id: u8,
+    /// What to do with requests for a unit ID no rack answers on
+    #[serde(default)]
+    pub unknown_unit: UnknownUnitPolicy,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Modbus transport the simulator serves
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportKind {
+    /// Modbus TCP on `listen` (and any per-rack listeners)
+    #[default]
+    ModbusTcp,
+    /// Modbus RTU on the serial port in `serial`; racks are slaves on the bus
+    ModbusRtu,
+    /// Modbus UDP on `listen`, one MBAP frame per datagram
+    ModbusUdp,
+    /// RTU frames (with CRC) over TCP connections on `listen`
+    ModbusRtuOverTcp,
+}
+
+/// Serial line for Modbus RTU. Defaults to 19200 baud, 8E1 as in the Modbus spec.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct SerialConfig {
+    /// Device path, e.g. `/dev/ttyUSB0` or one end of a PTY pair
+    pub port: String,
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+    #[serde(default)]
+    pub parity: SerialParity,
+    #[serde(default = "default_data_bits")]
+    pub data_bits: u8,
+    #[serde(default = "default_stop_bits")]
+    pub stop_bits: u8,
+}
+
+fn default_baud_rate() -> u32 {
+    19200
+}
+
+fn default_data_bits() -> u8 {
+    8
+}
+
+fn default_stop_bits() -> u8 {
+    1
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialParity {
+    None,
+    #[default]
+    Even,
+    Odd,
+}
+
+/// Handling of requests addressed to a unit ID that no rack answers on
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownUnitPolicy {
+    /// Answer with exception 0x0B (gateway target device failed to respond)
+    #[default]
+    Reject,
+    /// Send no response, like a gateway whose target is silent
+    Ignore,
+    /// Answer from the primary rack regardless of unit ID
+    Any,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ListenConfig {
     pub host: String,
     pub port: u16,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// How module data is arranged in the input and output process images.
+/// The defaults reproduce a 750-352/362 coupler.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ProcessImageConfig {
-    pub layout: String,
-    pub word_endian: String,
+    pub layout: ProcessImageLayout,
+    /// Register order of 32-bit values (counter values and presets)
+    pub word_endian: Endianness,
+    /// Byte order inside each register: `big` (standard Modbus) sends each
+    /// register as the module reports it; `little` swaps its two bytes
+    #[serde(default)]
+    pub byte_order_in_word: Endianness,
+    /// Each module's data starts on a multiple of this many bytes
     pub align_modules_to: u8,
 }
 
+impl Default for ProcessImageConfig {
+    fn default() -> Self {
+        Self {
+            layout: ProcessImageLayout::default(),
+            word_endian: Endianness::Little,
+            byte_order_in_word: Endianness::Big,
+            align_modules_to: 2,
+        }
+    }
+}
+
+/// Order of modules in the process image
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessImageLayout {
+    /// Analog (word) modules first, then digital modules, each in slot order
+    #[default]
+    #[serde(rename = "wago_750_default")]
+    Wago750Default,
+    /// All modules in slot order, regardless of type
+    SlotOrder,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    #[default]
+    Big,
+    Little,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ModbusMapConfig {
     pub inputs: ModbusArea,
     pub outputs: ModbusArea,
+    /// How requests outside the mapped addresses are answered
+    #[serde(default)]
+    pub address_check: AddressCheck,
+    /// Also serve the WAGO coupler areas: inputs at 0x0000, output read-back
+    /// at 0x0200, and the second areas for images past 256 words / 512 bits
+    #[serde(default = "default_mirror_areas")]
+    pub mirror_areas: bool,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+fn default_mirror_areas() -> bool {
+    true
+}
+
+/// Handling of reads and writes outside the process image and coupler registers
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressCheck {
+    /// Answer with exception 0x02 (Illegal Data Address), like a real coupler
+    #[default]
+    Strict,
+    /// Pad reads with zeros and drop writes (simulator behavior before strict checks)
+    Lenient,
+}
+
+/// Where a process image is exposed: register table and first address
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 pub struct ModbusArea {
-    pub kind: String,
+    pub kind: RegisterArea,
     pub base: u16,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterArea {
+    /// FC04, read-only
+    InputRegisters,
+    /// FC03/06/16/22/23
+    HoldingRegisters,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RackDefinition {
     pub id: String,
     pub name: String,
+    /// Modbus unit ID this rack answers on (defaults to transport.unit_id + rack index)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_id: Option<u8>,
+    /// Dedicated listener for this rack (defaults to transport.listen)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<ListenConfig>,
+    /// Coupler model plus the MAC, network and firmware details served in its registers
+    #[serde(default, skip_serializing_if = "CouplerDefinition::is_empty")]
+    pub coupler: CouplerDefinition,
     pub modules: Vec<ModuleDefinition>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct CouplerDefinition {
+    /// Coupler part number from the catalog in `couplers.rs`, e.g. `750-352`.
+    /// Defaults to 750-362, or 750-315 for `modbus_rtu`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub metadata: CouplerMetadata,
+}
+
+impl CouplerDefinition {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ModuleDefinition {
     pub id: String,
@@ -71,6 +242,9 @@ pub struct ModuleDefinition {
     pub name: String,
     pub channels: Vec<ChannelDefinition>,
     pub module_config: Option<HashMap<String, String>>,
+    /// Output values by channel applied when the watchdog trips (default 0)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub safe_values: HashMap<u16, f64>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -84,4 +258,71 @@ pub struct SignalDefinition {
     pub name: String,
     #[serde(rename = "type")]
     pub signal_type: String,
+    /// Overrides of the module's scaling for this channel
+    #[serde(flatten)]
+    pub scaling: ScalingOverride,
+}
+
+/// Raw-to-engineering scaling overrides of an analog channel, given per module
+/// in `module_config` or per signal in `channels`. By default the catalog's raw
+/// range maps linearly onto `min_value..max_value` and values are clamped to it;
+/// an explicit `scale` or `offset` (EU = scale * raw + offset) turns off the
+/// computed scaling and clamping.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct ScalingOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<String>,
+}
+
+impl ScalingOverride {
+    /// Overrides from a module's `module_config` (`scale`, `offset`,
+    /// `min_value`, `max_value`, `units`); other keys are ignored
+    pub fn from_module_config(config: &HashMap<String, String>) -> Result<Self, String> {
+        let number = |key: &str| {
+            config
+                .get(key)
+                .map(|value| value.trim().parse::<f64>().map_err(|_| format!("{} '{}' is not a number", key, value)))
+                .transpose()
+        };
+        let overrides = Self {
+            scale: number("scale")?,
+            offset: number("offset")?,
+            min_value: number("min_value")?,
+            max_value: number("max_value")?,
+            units: config.get("units").cloned(),
+        };
+        overrides.validate()?;
+        Ok(overrides)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scale == Some(0.0) {
+            return Err("scale must not be 0".to_string());
+        }
+        if let (Some(min), Some(max)) = (self.min_value, self.max_value) {
+            if min >= max {
+                return Err(format!("min_value {} must be below max_value {}", min, max));
+            }
+        }
+        Ok(())
+    }
+
+    /// These overrides, falling back to `base` for the fields not given
+    pub fn or(&self, base: &ScalingOverride) -> ScalingOverride {
+        ScalingOverride {
+            scale: self.scale.or(base.scale),
+            offset: self.offset.or(base.offset),
+            min_value: self.min_value.or(base.min_value),
+            max_value: self.max_value.or(base.max_value),
+            units: self.units.clone().or_else(|| base.units.clone()),
+        }
+    }
 }
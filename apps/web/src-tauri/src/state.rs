@@ -1,62 +1,235 @@
 use std::sync::{Arc, Mutex};
-use crate::models::{ChannelValue, ConnectionState, ModbusClientInfo, ModuleState, RackConfig, SimulationState};
+use crate::models::{ChannelFault, ChannelValue, ConnectionState, ModbusClientInfo, ModuleState, RackConfig, ServerStatus, SimulationState};
+use crate::catalog::{self, ModuleCatalog, ModuleDescriptor, ModuleKind};
 use crate::modules::{Module, create_module};
+use crate::couplers::{self, CouplerModel};
+use crate::faults::FaultInjector;
+use crate::registers::{RegisterContext, RegisterMap};
+use crate::watchdog::{self, Watchdog};
 use crate::scenario::{Scenario, ScenarioEngine};
-use crate::reactive::{ReactiveScenarioManager, ChannelRef};
+use crate::reactive::{validate_scenarios, ReactiveScenarioManager, ChannelRef};
+use crate::sim_config::{
+    AddressCheck, Endianness, ModbusArea, ProcessImageConfig, ProcessImageLayout, RegisterArea, SerialConfig,
+    TransportKind, UnknownUnitPolicy,
+};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// WAGO coupler areas served with `modbus_map.mirror_areas`, as (first address,
+/// last address, image offset of the first address). Reads return the input or
+/// output image; writes to any of these windows set outputs.
+const INPUT_REGISTER_WINDOWS: &[(u16, u16, usize)] = &[(0x0000, 0x00FF, 0), (0x6000, 0x62FC, 256)];
+const OUTPUT_REGISTER_WINDOWS: &[(u16, u16, usize)] = &[(0x0200, 0x02FF, 0), (0x7000, 0x72FC, 256)];
+const INPUT_BIT_WINDOWS: &[(u16, u16, usize)] = &[(0x0000, 0x01FF, 0), (0x8000, 0x85F7, 512)];
+const OUTPUT_BIT_WINDOWS: &[(u16, u16, usize)] = &[(0x0200, 0x03FF, 0), (0x9000, 0x95F7, 512)];
+
+fn window_offset(windows: &[(u16, u16, usize)], addr: u16) -> Option<usize> {
+    windows
+        .iter()
+        .find(|&&(first, last, _)| (first..=last).contains(&addr))
+        .map(|&(first, _, offset)| offset + (addr - first) as usize)
+}
+
+/// Address of image item `index` in the windows; inverse of `window_offset`
+fn window_address(windows: &[(u16, u16, usize)], index: usize) -> Option<u16> {
+    windows
+        .iter()
+        .find(|&&(first, last, offset)| (offset..=offset + (last - first) as usize).contains(&index))
+        .map(|&(first, _, offset)| first + (index - offset) as u16)
+}
+
+/// Simulated time per `Simulator::tick`, the period of the app's simulation loop
+pub const TICK_MS: u64 = 100;
+
+/// Process image item (register or bit) an address resolves to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageRef {
+    Input(usize),
+    Output(usize),
+}
+
+/// Modbus data area addressed by a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataArea {
+    Coils,
+    DiscreteInputs,
+    InputRegisters,
+    HoldingRegisters,
+}
+
+/// Runtime state of one simulated coupler. A rack's modules live contiguously
+/// in `Simulator::modules` so scenarios can address any channel by position.
+pub struct Rack {
+    pub config: RackConfig,
+    /// Catalog entry of the coupler (`config.coupler.module_number`)
+    pub coupler: &'static CouplerModel,
+    /// Index of this rack's first module in `Simulator::modules`
+    pub module_offset: usize,
+    pub module_count: usize,
+    /// Output process image registers as last written
+    pub output_registers: Vec<u16>,
+    /// Registers outside the coupler map written with `address_check: lenient`
+    pub user_memory: HashMap<u16, u16>,
+    pub watchdog: Watchdog,
+}
+
+impl Rack {
+    fn module_range(&self) -> std::ops::Range<usize> {
+        self.module_offset..self.module_offset + self.module_count
+    }
+
+    /// Host and port of the listener this rack is served on
+    pub fn endpoint(&self) -> (String, u16) {
+        (self.config.coupler.ip_address.clone(), self.config.coupler.modbus_port)
+    }
+}
+
 pub struct Simulator {
+    /// Primary rack (the one edited from the UI)
     pub config: Option<RackConfig>,
+    /// All racks, each answering on its own unit ID
+    pub racks: Vec<Rack>,
     pub modules: Vec<Box<dyn Module>>,
     pub simulation_state: SimulationState,
-    pub last_modbus_activity: u64, // Timestamp in ms
-    pub watchdog_timeout: u64, // ms, default 0 (disabled)
-    pub holding_registers: Vec<u16>, // General purpose storage for simulation
+    pub last_modbus_activity: u64, // Timestamp in ms, across all racks
+    /// Transport the server speaks (`transport.kind`)
+    pub transport_kind: TransportKind,
+    /// Serial line for `modbus_rtu`
+    pub serial: Option<SerialConfig>,
+    /// How requests for a unit ID that no rack answers on are handled
+    pub unknown_unit_policy: UnknownUnitPolicy,
+    /// Whether unmapped addresses are rejected (`modbus_map.address_check`)
+    pub address_check: AddressCheck,
+    /// Register table and first address of the input image (`modbus_map.inputs`)
+    pub input_area: ModbusArea,
+    /// First holding register of the output image (`modbus_map.outputs`)
+    pub output_area: ModbusArea,
+    /// Whether the WAGO coupler areas are served as well (`modbus_map.mirror_areas`)
+    pub mirror_areas: bool,
+    /// Layout, alignment and byte order of the process images
+    pub process_image: ProcessImageConfig,
     pub modbus_clients: HashMap<String, ModbusClientInfo>,
     pub scenario_engine: ScenarioEngine,
     pub available_scenarios: Vec<Scenario>,
     /// Reactive scenario manager (continuous I/O behaviors)
     pub reactive_manager: ReactiveScenarioManager,
+    /// Addresses the Modbus listeners are bound to, once listening
+    pub listen_addresses: Vec<SocketAddr>,
+    /// Lifecycle status reported by the server manager
+    pub server_status: ServerStatus,
+    /// Wire-level faults applied by the Modbus server
+    pub faults: FaultInjector,
+    /// `sim.seed` of the loaded config, kept so a saved config reproduces the faults
+    pub seed: Option<u64>,
+    /// Handlers serving the FC03/FC04 register space
+    pub register_map: Arc<RegisterMap>,
+    /// Module descriptors by part number (`module_catalog`)
+    pub module_catalog: ModuleCatalog,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Simulator {
     pub fn new() -> Self {
         Self {
             config: None,
+            racks: Vec::new(),
             modules: Vec::new(),
             simulation_state: SimulationState::Stopped,
             last_modbus_activity: 0,
-            watchdog_timeout: 0,
-            holding_registers: vec![0; 1024], // Initialize 1024 registers
+            transport_kind: TransportKind::default(),
+            serial: None,
+            unknown_unit_policy: UnknownUnitPolicy::default(),
+            address_check: AddressCheck::default(),
+            input_area: ModbusArea { kind: RegisterArea::InputRegisters, base: 0 },
+            output_area: ModbusArea { kind: RegisterArea::HoldingRegisters, base: 0 },
+            mirror_areas: true,
+            process_image: ProcessImageConfig::default(),
             modbus_clients: HashMap::new(),
             scenario_engine: ScenarioEngine::new(),
             available_scenarios: Vec::new(),
             reactive_manager: ReactiveScenarioManager::new(),
+            listen_addresses: Vec::new(),
+            server_status: ServerStatus::Stopped,
+            faults: FaultInjector::new(None),
+            seed: None,
+            register_map: Arc::new(RegisterMap::wago_coupler()),
+            module_catalog: ModuleCatalog::embedded(),
         }
     }
     
     pub fn load_rack(&mut self, config: RackConfig) {
+        self.load_racks(vec![config]);
+    }
+
+    /// Instantiate every rack's modules. Register contents and the watchdog
+    /// survive a reload for racks whose ID and coupler model are unchanged
+    /// (e.g. when a module is added); a rack with another coupler starts afresh.
+    pub fn load_racks(&mut self, configs: Vec<RackConfig>) {
+        let mut previous: HashMap<_, _> = self.racks
+            .drain(..)
+            .map(|rack| {
+                let key = (rack.config.id, rack.coupler.part_number);
+                (key, (rack.output_registers, rack.user_memory, rack.watchdog))
+            })
+            .collect();
         self.modules.clear();
-        
-        // Sort modules by slot position
-        let mut sorted_modules = config.modules.clone();
-        sorted_modules.sort_by_key(|m| m.slot_position);
-        
-        for mod_config in sorted_modules {
-            if let Some(module) = create_module(mod_config) {
-                self.modules.push(module);
+
+        for config in configs {
+            // Sort modules by slot position
+            let mut sorted_modules = config.modules.clone();
+            sorted_modules.sort_by_key(|m| m.slot_position);
+
+            let module_offset = self.modules.len();
+            for mod_config in sorted_modules {
+                if let Some(module) = create_module(mod_config, &self.module_catalog) {
+                    self.modules.push(module);
+                }
             }
+
+            let coupler = couplers::lookup(&config.coupler.module_number)
+                .unwrap_or_else(|| couplers::default_for(self.transport_kind));
+            let (output_registers, user_memory, watchdog) = previous
+                .remove(&(config.id.clone(), coupler.part_number))
+                .unwrap_or_else(|| (Vec::new(), HashMap::new(), Watchdog::with_time(coupler.watchdog_time)));
+            self.racks.push(Rack {
+                coupler,
+                module_offset,
+                module_count: self.modules.len() - module_offset,
+                output_registers,
+                user_memory,
+                watchdog,
+                config,
+            });
+            // One register per output image word
+            let rack = self.racks.len() - 1;
+            let output_words = self.output_module_slices(rack).1.div_ceil(2);
+            self.racks[rack].output_registers.resize(output_words, 0);
         }
-        self.config = Some(config);
+        self.config = self.racks.first().map(|rack| rack.config.clone());
+    }
+
+    /// Replace the primary rack's configuration, keeping any other racks.
+    pub fn update_primary_rack(&mut self, config: RackConfig) {
+        let mut configs: Vec<RackConfig> = self.racks.iter().map(|rack| rack.config.clone()).collect();
+        match configs.first_mut() {
+            Some(first) => *first = config,
+            None => configs.push(config),
+        }
+        self.load_racks(configs);
     }
 
     pub fn clear_rack(&mut self) {
         self.config = None;
+        self.racks.clear();
         self.modules.clear();
         self.simulation_state = SimulationState::Stopped;
-        self.holding_registers.fill(0);
         self.scenario_engine.stop();
         self.available_scenarios.clear();
         self.reactive_manager = ReactiveScenarioManager::new();
@@ -64,47 +237,139 @@ impl Simulator {
 
     pub fn load_from_yaml_string(&mut self, yaml_content: &str) -> Result<(), Box<dyn std::error::Error>> {
         let root: crate::sim_config::SimConfigRoot = serde_yaml::from_str(yaml_content)?;
-
-        // Load scripted scenarios if present
-        if let Some(scenarios) = root.scenarios {
-            self.available_scenarios = scenarios;
-        } else {
-            self.available_scenarios.clear();
+        if root.transport.kind == TransportKind::ModbusRtu && root.transport.serial.is_none() {
+            return Err("transport.kind 'modbus_rtu' requires transport.serial".into());
+        }
+        if root.modbus_map.outputs.kind != RegisterArea::HoldingRegisters {
+            return Err("modbus_map.outputs.kind must be 'holding_registers'".into());
         }
+        let module_catalog = match root.module_catalog.as_deref() {
+            Some(path) => ModuleCatalog::with_user_file(path)?,
+            None => ModuleCatalog::embedded(),
+        };
+
+        // Racks without an explicit unit_id take consecutive IDs after transport.unit_id.
+        // Unit IDs only need to be unique among racks sharing a listener.
+        let mut configs = Vec::new();
+        let mut unit_owners: HashMap<(String, u16, u8), String> = HashMap::new();
+        for (rack_idx, rack_def) in root.racks.iter().enumerate() {
+            let unit_id = rack_def
+                .unit_id
+                .unwrap_or_else(|| root.transport.unit_id.wrapping_add(rack_idx as u8));
+            let listen = rack_def.listen.as_ref().unwrap_or(&root.transport.listen);
+            let coupler = match rack_def.coupler.model.as_deref() {
+                Some(model) => couplers::lookup(model)
+                    .ok_or_else(|| format!("Rack '{}' uses unknown coupler '{}'", rack_def.id, model))?,
+                None => couplers::default_for(root.transport.kind),
+            };
+            if !coupler.supports_transport(root.transport.kind) {
+                return Err(format!(
+                    "Rack '{}': coupler {} does not support the configured transport.kind",
+                    rack_def.id, coupler.part_number
+                ).into());
+            }
+            let owner_key = (listen.host.clone(), listen.port, unit_id);
+            if let Some(owner) = unit_owners.insert(owner_key, rack_def.id.clone()) {
+                return Err(format!(
+                    "Racks '{}' and '{}' both use unit_id {} on {}:{}",
+                    owner, rack_def.id, unit_id, listen.host, listen.port
+                ).into());
+            }
 
-        // Take the first rack for MVP
-        if let Some(rack_def) = root.racks.first() {
             let mut modules = Vec::new();
             for (i, mod_def) in rack_def.modules.iter().enumerate() {
+                let Some(descriptor) = module_catalog.get(&mod_def.model) else {
+                    return Err(format!(
+                        "Module '{}' in rack '{}' uses unknown model '{}'",
+                        mod_def.id, rack_def.id, mod_def.model
+                    ).into());
+                };
                 let instance = crate::models::ModuleInstance {
                     id: mod_def.id.clone(),
                     module_number: mod_def.model.clone(),
                     slot_position: i as u16,
                     label: Some(mod_def.name.clone()),
+                    channels: mod_def.channels.clone(),
+                    module_config: mod_def.module_config.clone().unwrap_or_default(),
                 };
+                crate::modules::check_module_config(&instance, descriptor)
+                    .map_err(|e| format!("Module '{}' in rack '{}': {}", mod_def.id, rack_def.id, e))?;
                 modules.push(instance);
             }
 
-            let config = crate::models::RackConfig {
+            configs.push(crate::models::RackConfig {
                 id: rack_def.id.clone(),
                 name: rack_def.name.clone(),
                 description: None,
                 coupler: crate::models::CouplerConfig {
-                    module_number: "750-362".to_string(), // Default coupler
-                    ip_address: root.transport.listen.host.clone(),
-                    modbus_port: root.transport.listen.port,
-                    unit_id: root.transport.unit_id,
+                    module_number: coupler.part_number.to_string(),
+                    ip_address: listen.host.clone(),
+                    modbus_port: listen.port,
+                    unit_id,
+                    metadata: rack_def.coupler.metadata.clone(),
                 },
                 modules,
                 created_at: chrono::Utc::now().to_rfc3339(),
                 updated_at: chrono::Utc::now().to_rfc3339(),
-            };
+            });
+        }
+
+        // Check module counts and image sizes on a scratch copy so a rejected
+        // config leaves the loaded racks untouched
+        let mut scratch = Simulator::new();
+        scratch.transport_kind = root.transport.kind;
+        scratch.process_image = root.process_image.clone();
+        scratch.module_catalog = module_catalog.clone();
+        scratch.load_racks(configs.clone());
+        for rack in 0..scratch.racks.len() {
+            scratch.check_coupler_limits(rack)?;
+        }
 
-            self.load_rack(config);
+        self.transport_kind = root.transport.kind;
+        self.serial = root.transport.serial;
+        self.unknown_unit_policy = root.transport.unknown_unit;
+        self.address_check = root.modbus_map.address_check;
+        self.input_area = root.modbus_map.inputs;
+        self.output_area = root.modbus_map.outputs;
+        self.mirror_areas = root.modbus_map.mirror_areas;
+        self.process_image = root.process_image;
+        self.module_catalog = module_catalog;
+        self.seed = root.sim.seed;
+        self.faults.reseed(self.seed);
+        if !configs.is_empty() {
+            self.load_racks(configs);
+            for (rack, rack_def) in self.racks.iter_mut().zip(&root.racks) {
+                rack.watchdog.safe_values = rack_def
+                    .modules
+                    .iter()
+                    .filter(|module| !module.safe_values.is_empty())
+                    .map(|module| (module.id.clone(), module.safe_values.clone()))
+                    .collect();
+            }
+        }
+
+        // Load scripted scenarios if present
+        if let Some(scenarios) = root.scenarios {
+            for step in scenarios.iter().flat_map(|s| s.steps.iter()) {
+                self.check_rack_reference(step.rack.as_deref(), step.module_position)?;
+                if let Some(trigger_module) = step.trigger_module {
+                    self.check_rack_reference(step.trigger_rack.as_deref(), trigger_module)?;
+                }
+            }
+            self.available_scenarios = scenarios;
+        } else {
+            self.available_scenarios.clear();
         }
 
         // Load and validate reactive scenarios
         if let Some(reactive_scenarios) = root.reactive_scenarios {
+            for behavior in reactive_scenarios.iter().flat_map(|s| s.behaviors.iter()) {
+                if let Some(source) = &behavior.source {
+                    self.check_rack_reference(source.rack.as_deref(), source.module_position)?;
+                }
+                self.check_rack_reference(behavior.target.rack.as_deref(), behavior.target.module_position)?;
+            }
+
             // Get channel counts for validation
             let module_count = self.modules.len();
             let channel_counts: Vec<usize> = self.modules
@@ -112,15 +377,25 @@ impl Simulator {
                 .map(|m| m.get_state().channels.len())
                 .collect();
 
-            self.reactive_manager.load_scenarios(
-                reactive_scenarios,
-                module_count,
-                &channel_counts,
-            );
+            // Validate against the current layout; the stored references stay
+            // relative to their rack. The manager is swapped out to resolve them.
+            let mut manager = std::mem::take(&mut self.reactive_manager);
+            manager.load_scenarios_with(reactive_scenarios, |scenarios| {
+                let mut resolved = scenarios.to_vec();
+                for behavior in resolved.iter_mut().flat_map(|s| s.behaviors.iter_mut()) {
+                    let refs = behavior.source.iter_mut().chain(std::iter::once(&mut behavior.target));
+                    for channel_ref in refs {
+                        if let Some(index) = self.module_index(channel_ref) {
+                            *channel_ref = ChannelRef::new(index, channel_ref.channel);
+                        }
+                    }
+                }
+                validate_scenarios(&resolved, module_count, &channel_counts)
+            });
+            self.reactive_manager = manager;
 
             // Auto-activate the default scenario if one exists
-            // Using 100ms tick rate (matching the simulation tick rate)
-            if let Err(e) = self.reactive_manager.auto_activate_default(100) {
+            if let Err(e) = self.reactive_manager.auto_activate_default(TICK_MS) {
                 eprintln!("Warning: Failed to auto-activate default reactive scenario: {}", e);
             }
         } else {
@@ -139,7 +414,7 @@ impl Simulator {
         // Option 2: Use internal mutability (not great here)
         // Option 3: Swap out scenario engine (safest for borrow checker)
 
-        let mut engine = std::mem::replace(&mut self.scenario_engine, ScenarioEngine::new());
+        let mut engine = std::mem::take(&mut self.scenario_engine);
         engine.tick(self);
         self.scenario_engine = engine;
 
@@ -156,30 +431,60 @@ impl Simulator {
 
         // Step 2: Evaluate reactive scenario and collect values to apply
         let reactive_updates = self.evaluate_reactive_scenario();
+        let fault_behaviors: HashMap<String, ChannelFault> = self
+            .reactive_manager
+            .get_active_scenario()
+            .map(|scenario| {
+                scenario.behaviors.iter().filter_map(|b| Some((b.id.clone(), b.fault?))).collect()
+            })
+            .unwrap_or_default();
 
         // Step 3-5: Apply updates respecting ownership precedence
-        for (channel_ref, value, _behavior_id) in reactive_updates {
+        for (channel_ref, value, behavior_id) in reactive_updates {
+            let Some(index) = self.module_index(&channel_ref) else { continue };
+            let channel = channel_ref.channel;
+
+            // Fault behaviors switch a channel fault instead of the value
+            if let Some(&fault) = fault_behaviors.get(&behavior_id) {
+                if let Some(module) = self.modules.get_mut(index) {
+                    module.set_channel_fault(channel, (value > 0.5).then_some(fault));
+                }
+                continue;
+            }
+
             // Check if channel is blocked by force or manual override
-            if self.reactive_manager.is_forced(&channel_ref) {
+            if self.forced_channel(index, channel).is_some() {
                 continue; // Force takes precedence
             }
-            if self.reactive_manager.has_manual_override(&channel_ref) {
+            if self.has_manual_override(index, channel) {
                 continue; // Manual takes precedence over scenario
             }
 
             // Apply the value to the module
-            if let Some(module) = self.modules.get_mut(channel_ref.module_position) {
-                module.set_channel_value(channel_ref.channel, value);
+            if let Some(module) = self.modules.get_mut(index) {
+                module.set_channel_value(channel, value);
             }
         }
 
         // Apply force overrides (highest priority)
-        for force_info in self.reactive_manager.get_forces() {
-            if let Some(module) = self.modules.get_mut(force_info.module_position) {
-                module.set_channel_value(force_info.channel as u16, force_info.value);
+        let forces: Vec<(Option<usize>, u16, f64)> = self
+            .reactive_manager
+            .forces
+            .iter()
+            .filter(|(_, force)| force.enabled)
+            .map(|(channel_ref, force)| (self.module_index(channel_ref), channel_ref.channel, force.value))
+            .collect();
+        for (index, channel, value) in forces {
+            if let Some(module) = index.and_then(|index| self.modules.get_mut(index)) {
+                module.set_channel_value(channel, value);
             }
         }
 
+        // Let the tick's time pass with the values just applied
+        for module in &mut self.modules {
+            module.advance(TICK_MS);
+        }
+
         // Check watchdog
         self.check_watchdog();
     }
@@ -204,10 +509,12 @@ impl Simulator {
             .collect();
 
         // Swap out the reactive manager to avoid borrow issues
-        let mut manager = std::mem::replace(&mut self.reactive_manager, ReactiveScenarioManager::new());
+        let mut manager = std::mem::take(&mut self.reactive_manager);
 
         let get_channel_value = |ch: &ChannelRef| {
-            channel_values.get(&(ch.module_position, ch.channel)).copied().unwrap_or(0.0)
+            self.module_index(ch)
+                .and_then(|index| channel_values.get(&(index, ch.channel)).copied())
+                .unwrap_or(0.0)
         };
 
         let results = manager.evaluate_active_scenario(get_channel_value);
@@ -234,8 +541,283 @@ impl Simulator {
         }
     }
 
-    pub fn touch_watchdog(&mut self) {
-        self.last_modbus_activity = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    /// Inject a fault on a channel, or clear it with `None`
+    pub fn set_channel_fault(&mut self, module_id: &str, channel: u16, fault: Option<ChannelFault>) {
+        if let Some(module) = self.modules.iter_mut().find(|m| m.get_id() == module_id) {
+            module.set_channel_fault(channel, fault);
+        }
+    }
+
+    /// Distinct host/port pairs the Modbus server should bind, taken from each
+    /// rack's coupler configuration (`racks[].listen` or `transport.listen` in YAML).
+    /// Racks sharing an endpoint are told apart by unit ID.
+    pub fn listen_endpoints(&self) -> Vec<(String, u16)> {
+        let mut endpoints: Vec<(String, u16)> = Vec::new();
+        for rack in &self.racks {
+            let endpoint = rack.endpoint();
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+        if endpoints.is_empty() {
+            endpoints.push(("0.0.0.0".to_string(), 502));
+        }
+        endpoints
+    }
+
+    /// Rack answering on a Modbus unit ID at the given listener (`None` on a
+    /// serial bus, where every rack is a slave). Over the network, units 0 and
+    /// 255 address the coupler itself as on a real 750-352/362, so unless a
+    /// rack claims them they go to the first rack served on that listener, as
+    /// do all unmatched units with `UnknownUnitPolicy::Any`.
+    pub fn rack_for_unit(&self, endpoint: Option<&(String, u16)>, unit_id: u8) -> Option<usize> {
+        let mut on_endpoint = self.racks
+            .iter()
+            .enumerate()
+            .filter(|(_, rack)| endpoint.map_or(true, |endpoint| rack.endpoint() == *endpoint))
+            .map(|(idx, rack)| (idx, rack.config.coupler.unit_id));
+        let first = on_endpoint.clone().next().map(|(idx, _)| idx);
+        on_endpoint
+            .find(|(_, unit)| *unit == unit_id)
+            .map(|(idx, _)| idx)
+            .or(match self.unknown_unit_policy {
+                UnknownUnitPolicy::Any => first,
+                _ if endpoint.is_some() && matches!(unit_id, 0 | 255) => first,
+                _ => None,
+            })
+    }
+
+    /// Index of the rack with the given ID
+    pub fn rack_index(&self, rack_id: &str) -> Option<usize> {
+        self.racks.iter().position(|rack| rack.config.id == rack_id)
+    }
+
+    /// Check that a rack-relative module position names a module of that rack
+    fn check_rack_reference(&self, rack: Option<&str>, module_position: usize) -> Result<(), String> {
+        let Some(rack_id) = rack else {
+            return Ok(());
+        };
+        let Some(index) = self.rack_index(rack_id) else {
+            return Err(format!("Scenario references unknown rack '{}'", rack_id));
+        };
+        let module_count = self.racks[index].module_count;
+        if module_position >= module_count {
+            return Err(format!(
+                "Scenario references module position {} of rack '{}', which has {} modules",
+                module_position, rack_id, module_count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Index into `modules` of a channel's module. A position relative to a
+    /// named rack is resolved against the current layout, so the reference
+    /// follows its rack when modules are added or removed on other racks.
+    /// Positions past the end of their rack resolve to nothing.
+    pub fn module_index(&self, channel_ref: &ChannelRef) -> Option<usize> {
+        match &channel_ref.rack {
+            Some(rack_id) => {
+                let rack = &self.racks[self.rack_index(rack_id)?];
+                (channel_ref.module_position < rack.module_count)
+                    .then(|| rack.module_offset + channel_ref.module_position)
+            }
+            None => Some(channel_ref.module_position),
+        }
+    }
+
+    /// Whether a reference (relative to any rack) points at `channel` of the module at `index`
+    fn refers_to(&self, channel_ref: &ChannelRef, index: usize, channel: u16) -> bool {
+        channel_ref.channel == channel && self.module_index(channel_ref) == Some(index)
+    }
+
+    /// Forced channel matching the module at `index`, however the force references it
+    fn forced_channel(&self, index: usize, channel: u16) -> Option<ChannelRef> {
+        self.reactive_manager
+            .forces
+            .iter()
+            .find(|(channel_ref, force)| force.enabled && self.refers_to(channel_ref, index, channel))
+            .map(|(channel_ref, _)| channel_ref.clone())
+    }
+
+    fn has_manual_override(&self, index: usize, channel: u16) -> bool {
+        self.reactive_manager
+            .manual_overrides
+            .keys()
+            .any(|channel_ref| self.refers_to(channel_ref, index, channel))
+    }
+
+    pub(crate) fn rack_module_range(&self, rack: usize) -> std::ops::Range<usize> {
+        self.racks.get(rack).map(|r| r.module_range()).unwrap_or(0..0)
+    }
+
+    /// Whether a rack's coupler answers `function` at all
+    pub fn supports_function(&self, rack: usize, function: u8) -> bool {
+        self.racks.get(rack).is_some_and(|r| r.coupler.supports_function(function))
+    }
+
+    /// Module count and process image sizes of a rack against its coupler's limits
+    pub fn check_coupler_limits(&self, rack: usize) -> Result<(), String> {
+        let Some(r) = self.racks.get(rack) else { return Ok(()) };
+        r.coupler
+            .check_limits(r.config.modules.len(), self.read_input_registers(rack).len(), r.output_registers.len())
+            .map_err(|e| format!("Rack '{}': {}", r.config.id, e))
+    }
+
+    pub(crate) fn rack_coupler_part_number(&self, rack: usize) -> u16 {
+        self.racks
+            .get(rack)
+            .and_then(|r| r.config.coupler.module_number.split('-').nth(1).and_then(|s| s.parse::<u16>().ok()))
+            .unwrap_or(362) // Default to 750-362
+    }
+
+    /// Whether `count` items from `addr` are all mapped in `area` on a rack,
+    /// the way a coupler decides between answering and exception 0x02.
+    /// Always true with `AddressCheck::Lenient`.
+    pub fn is_mapped(&self, rack: usize, area: DataArea, addr: u16, count: usize, write: bool) -> bool {
+        if self.address_check == AddressCheck::Lenient {
+            return true;
+        }
+        let start = addr as usize;
+        let end = start + count;
+        // Every item must resolve to a register or bit that exists in its image
+        let in_images = |resolve: &dyn Fn(u16) -> Option<ImageRef>, input_len: usize, output_len: usize| {
+            (start..end).all(|a| match u16::try_from(a).ok().and_then(resolve) {
+                Some(ImageRef::Input(index)) => index < input_len,
+                Some(ImageRef::Output(index)) => index < output_len,
+                None => false,
+            })
+        };
+
+        match area {
+            DataArea::DiscreteInputs | DataArea::Coils => in_images(
+                &|a| self.resolve_bit(area, a, write),
+                self.read_discrete_inputs(rack).len(),
+                self.read_coils(rack).len(),
+            ),
+            DataArea::InputRegisters | DataArea::HoldingRegisters => {
+                let ctx = RegisterContext::new(self, rack);
+                // Multi-word registers are read-only and read from their first address
+                if let Some(record) = self.register_map.read_record(&ctx, addr) {
+                    return !write && count <= record.len();
+                }
+                (start..end).all(|a| {
+                    u16::try_from(a).is_ok_and(|a| {
+                        if write {
+                            self.register_map.writable(&ctx, a)
+                        } else {
+                            self.register_map.read(&ctx, area, a).is_some()
+                        }
+                    })
+                })
+            }
+        }
+    }
+
+    /// Resolve a register address against the configured areas and, with
+    /// `mirror_areas`, the WAGO windows. Where areas overlap, reads return
+    /// inputs as long as the input image (`input_words`) reaches that far, and
+    /// writes always set outputs.
+    pub(crate) fn resolve_register(&self, area: DataArea, addr: u16, write: bool, input_words: usize) -> Option<ImageRef> {
+        let mirror = |windows| if self.mirror_areas { window_offset(windows, addr) } else { None };
+        let output = match area {
+            DataArea::HoldingRegisters => self.output_index(addr),
+            _ => None,
+        };
+        if write {
+            return mirror(INPUT_REGISTER_WINDOWS)
+                .or(mirror(OUTPUT_REGISTER_WINDOWS))
+                .or(output)
+                .map(ImageRef::Output);
+        }
+
+        let input_area = match self.input_area.kind {
+            RegisterArea::InputRegisters => DataArea::InputRegisters,
+            RegisterArea::HoldingRegisters => DataArea::HoldingRegisters,
+        };
+        let input = (area == input_area)
+            .then(|| addr.checked_sub(self.input_area.base).map(usize::from))
+            .flatten();
+        [input, mirror(INPUT_REGISTER_WINDOWS)]
+            .into_iter()
+            .flatten()
+            .find(|&index| index < input_words)
+            .map(ImageRef::Input)
+            .or_else(|| mirror(OUTPUT_REGISTER_WINDOWS).or(output).map(ImageRef::Output))
+    }
+
+    /// Resolve a bit address. Without `mirror_areas`, discrete inputs read the
+    /// input bits and coils the output bits, both from 0. The WAGO map treats
+    /// FC01 and FC02 alike: inputs from 0x0000, output read-back from 0x0200.
+    fn resolve_bit(&self, area: DataArea, addr: u16, write: bool) -> Option<ImageRef> {
+        if !self.mirror_areas {
+            return Some(match (area, write) {
+                (DataArea::DiscreteInputs, false) => ImageRef::Input(addr as usize),
+                _ => ImageRef::Output(addr as usize),
+            });
+        }
+        if write {
+            return window_offset(INPUT_BIT_WINDOWS, addr)
+                .or_else(|| window_offset(OUTPUT_BIT_WINDOWS, addr))
+                .map(ImageRef::Output);
+        }
+        window_offset(INPUT_BIT_WINDOWS, addr)
+            .map(ImageRef::Input)
+            .or_else(|| window_offset(OUTPUT_BIT_WINDOWS, addr).map(ImageRef::Output))
+    }
+
+    /// Register table and address a master uses for an image register: the
+    /// WAGO areas with `mirror_areas`, else the configured `modbus_map` bases.
+    pub(crate) fn register_address(&self, image: ImageRef) -> Option<(DataArea, u16)> {
+        match image {
+            ImageRef::Input(index) => {
+                let area = match self.input_area.kind {
+                    RegisterArea::InputRegisters => DataArea::InputRegisters,
+                    RegisterArea::HoldingRegisters => DataArea::HoldingRegisters,
+                };
+                let addr = if self.mirror_areas {
+                    window_address(INPUT_REGISTER_WINDOWS, index)
+                } else {
+                    u16::try_from(index).ok().and_then(|i| self.input_area.base.checked_add(i))
+                };
+                addr.map(|addr| (area, addr))
+            }
+            ImageRef::Output(index) => {
+                let addr = if self.mirror_areas {
+                    window_address(OUTPUT_REGISTER_WINDOWS, index)
+                } else {
+                    u16::try_from(index).ok().and_then(|i| self.output_area.base.checked_add(i))
+                };
+                addr.map(|addr| (DataArea::HoldingRegisters, addr))
+            }
+        }
+    }
+
+    /// Bit address of an input or output bit; inverse of `resolve_bit`. With
+    /// `mirror_areas` outputs are given at their read-back address.
+    pub(crate) fn bit_address(&self, image: ImageRef) -> Option<u16> {
+        match (image, self.mirror_areas) {
+            (ImageRef::Input(index), true) => window_address(INPUT_BIT_WINDOWS, index),
+            (ImageRef::Output(index), true) => window_address(OUTPUT_BIT_WINDOWS, index),
+            (ImageRef::Input(index) | ImageRef::Output(index), false) => u16::try_from(index).ok(),
+        }
+    }
+
+    /// A request with `function` reached a rack; retriggers its watchdog if
+    /// the function code is enabled in the watchdog mask.
+    pub fn touch_watchdog(&mut self, rack: usize, function: u8) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        self.last_modbus_activity = now;
+        if let Some(rack) = self.racks.get_mut(rack) {
+            rack.watchdog.on_request(function, now);
+        }
+    }
+
+    /// Whether a watchdog timeout closed the connections on `endpoint` at or
+    /// after `since` (ms), for racks with 0x1009 set.
+    pub fn sockets_closed_since(&self, endpoint: &(String, u16), since: u64) -> bool {
+        self.racks
+            .iter()
+            .any(|rack| rack.endpoint() == *endpoint && rack.watchdog.sockets_closed_at >= since.max(1))
     }
 
     pub fn register_modbus_client(&mut self, addr: SocketAddr) -> String {
@@ -266,25 +848,76 @@ impl Simulator {
         ConnectionState {
             modbus_clients: clients,
             last_activity: self.last_modbus_activity,
+            listen_address: self.listen_addresses.first().map(|addr| addr.to_string()),
+            listen_addresses: self.listen_addresses.iter().map(|addr| addr.to_string()).collect(),
+            server_status: self.server_status.clone(),
+            watchdog_tripped_racks: self.racks
+                .iter()
+                .filter(|rack| rack.watchdog.is_tripped())
+                .map(|rack| rack.config.id.clone())
+                .collect(),
         }
     }
 
+    /// Each rack has its own watchdog; an expired one only drives that rack's outputs
+    /// to their safe values.
     pub fn check_watchdog(&mut self) {
-        if self.watchdog_timeout == 0 {
-            return;
-        }
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
-        if now.saturating_sub(self.last_modbus_activity) > self.watchdog_timeout {
-            for module in &mut self.modules {
-                if Self::is_digital_output(module.get_config().module_number.as_str()) {
-                    for i in 0..16 {
-                        module.set_channel_value(i, 0.0);
-                    }
-                }
+        self.poll_watchdogs(now);
+    }
+
+    fn poll_watchdogs(&mut self, now: u64) {
+        for rack in 0..self.racks.len() {
+            if self.racks[rack].watchdog.poll(now) {
+                self.apply_safe_outputs(rack);
             }
         }
     }
 
+    /// Drive a rack's digital and analog outputs to their watchdog safe values
+    /// (0 unless configured) and mirror them in the output registers.
+    fn apply_safe_outputs(&mut self, rack: usize) {
+        let (slices, total_len) = self.output_module_slices(rack);
+        let mut bytes = self.output_image_bytes_from_registers(rack, total_len);
+        for (index, offset, len) in slices {
+            let module_number = self.modules[index].get_config().module_number.clone();
+            let digital = self.is_digital_output(&module_number);
+            let kind = self.module_kind(&module_number);
+            // Counters sit in the output image too, but only for their control words
+            if kind == Some(ModuleKind::Counter) {
+                continue;
+            }
+            let safe_values = self.racks[rack]
+                .watchdog
+                .safe_values
+                .get(self.modules[index].get_id())
+                .cloned()
+                .unwrap_or_default();
+
+            let module = &mut self.modules[index];
+            module.write_outputs(&vec![0; len]);
+            for (&channel, &value) in &safe_values {
+                module.set_channel_value(channel, value);
+            }
+
+            let image = if digital {
+                Self::pack_digital_output_bytes(module.as_ref(), len)
+            } else if matches!(kind, Some(ModuleKind::Pwm | ModuleKind::PulseTrain)) {
+                // Cleared control words, each followed by the data word
+                module.get_state().channels.iter().take(len / 4).flat_map(|ch| {
+                    let [low, high] = ch.raw_value.to_le_bytes();
+                    [0, 0, low, high]
+                }).collect()
+            } else {
+                module.get_state().channels.iter().flat_map(|ch| ch.raw_value.to_le_bytes()).collect()
+            };
+            if let (Some(target), Some(source)) = (bytes.get_mut(offset..offset + len), image.get(..len)) {
+                target.copy_from_slice(source);
+            }
+        }
+        self.write_output_registers_from_bytes(rack, &bytes);
+    }
+
     fn append_aligned(bytes: &mut Vec<u8>, data: &[u8], align: usize) {
         bytes.extend_from_slice(data);
         if align > 1 {
@@ -295,80 +928,145 @@ impl Simulator {
         }
     }
 
-    fn build_input_image_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        let align = 2;
-
-        for module in &self.modules {
-            if Self::is_analog_input(module.get_config().module_number.as_str()) {
-                let data = module.read_inputs();
-                Self::append_aligned(&mut bytes, &data, align);
+    /// Modules of a rack in process image order, as positions in `self.modules`:
+    /// analog (word) modules before digital ones for the WAGO layout, or plain slot order.
+    fn image_module_order(
+        &self,
+        rack: usize,
+        is_analog: fn(&Self, &str) -> bool,
+        is_digital: fn(&Self, &str) -> bool,
+    ) -> Vec<usize> {
+        let range = self.rack_module_range(rack);
+        let numbers: Vec<(usize, &str)> = range
+            .map(|index| (index, self.modules[index].get_config().module_number.as_str()))
+            .collect();
+        match self.process_image.layout {
+            ProcessImageLayout::Wago750Default => {
+                let analog = numbers.iter().filter(|(_, n)| is_analog(self, n));
+                let digital = numbers.iter().filter(|(_, n)| is_digital(self, n));
+                analog.chain(digital).map(|&(index, _)| index).collect()
             }
+            ProcessImageLayout::SlotOrder => numbers
+                .iter()
+                .filter(|(_, n)| is_analog(self, n) || is_digital(self, n))
+                .map(|&(index, _)| index)
+                .collect(),
         }
+    }
+
+    fn image_align(&self) -> usize {
+        self.process_image.align_modules_to.max(1) as usize
+    }
 
-        for module in &self.modules {
-            if Self::is_digital_input(module.get_config().module_number.as_str()) {
-                let data = module.read_inputs();
-                Self::append_aligned(&mut bytes, &data, align);
+    /// Swap the words of a module's 32-bit values for `word_endian: big`.
+    /// The swap is its own inverse, so it serves inputs and outputs alike.
+    fn order_dwords(&self, module: &dyn Module, data: &mut [u8]) {
+        if self.process_image.word_endian != Endianness::Big {
+            return;
+        }
+        for &offset in module.dword_offsets() {
+            if let Some(value) = data.get_mut(offset..offset + 4) {
+                value.rotate_left(2);
             }
         }
+    }
+
+    /// Bit of a register that carries bit `bit` of image byte `byte`, honoring
+    /// `byte_order_in_word`
+    pub(crate) fn register_bit(&self, byte: usize, bit: u8) -> u8 {
+        let high = match self.process_image.byte_order_in_word {
+            Endianness::Big => byte % 2 == 1,
+            Endianness::Little => byte % 2 == 0,
+        };
+        if high { bit + 8 } else { bit }
+    }
+
+    /// Register value of two process image bytes, honoring `byte_order_in_word`
+    fn image_word(&self, first: u8, second: u8) -> u16 {
+        match self.process_image.byte_order_in_word {
+            Endianness::Big => u16::from_le_bytes([first, second]),
+            Endianness::Little => u16::from_be_bytes([first, second]),
+        }
+    }
+
+    /// Process image bytes of a register value; inverse of `image_word`
+    fn image_bytes(&self, word: u16) -> [u8; 2] {
+        match self.process_image.byte_order_in_word {
+            Endianness::Big => word.to_le_bytes(),
+            Endianness::Little => word.to_be_bytes(),
+        }
+    }
+
+    /// Input image layout for a rack as (module index, byte offset, byte length),
+    /// matching `build_input_image_bytes`. Module indices are positions in `self.modules`.
+    pub(crate) fn input_module_slices(&self, rack: usize) -> Vec<(usize, usize, usize)> {
+        let align = self.image_align();
+        let mut offset = 0usize;
+        self.image_module_order(rack, Self::is_analog_input, Self::is_digital_input)
+            .into_iter()
+            .map(|index| {
+                let len = self.modules[index].get_input_image_size();
+                let slice = (index, offset, len);
+                offset += len.next_multiple_of(align);
+                slice
+            })
+            .collect()
+    }
+
+    fn build_input_image_bytes(&self, rack: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let align = self.image_align();
+
+        for index in self.image_module_order(rack, Self::is_analog_input, Self::is_digital_input) {
+            let module = self.modules[index].as_ref();
+            let mut data = module.read_inputs();
+            self.order_dwords(module, &mut data);
+            Self::append_aligned(&mut bytes, &data, align);
+        }
 
         bytes
     }
 
-    fn output_module_slices(&self) -> (Vec<(usize, usize, usize)>, usize) {
+    /// Output image layout for a rack as (module index, byte offset, byte length).
+    /// Module indices are positions in `self.modules`.
+    pub(crate) fn output_module_slices(&self, rack: usize) -> (Vec<(usize, usize, usize)>, usize) {
         let mut slices = Vec::new();
         let mut offset = 0usize;
-        let align = 2;
+        let align = self.image_align();
 
-        let mut add_module = |index: usize, len: usize| {
+        for index in self.image_module_order(rack, Self::is_analog_output, Self::is_digital_output) {
+            let len = self.modules[index].get_output_image_size();
             if len == 0 {
-                return;
+                continue;
             }
             slices.push((index, offset, len));
-            offset += len;
-            if align > 1 {
-                let rem = len % align;
-                if rem != 0 {
-                    offset += align - rem;
-                }
-            }
-        };
-
-        for (index, module) in self.modules.iter().enumerate() {
-            if Self::is_analog_output(module.get_config().module_number.as_str()) {
-                add_module(index, module.get_output_image_size());
-            }
-        }
-
-        for (index, module) in self.modules.iter().enumerate() {
-            if Self::is_digital_output(module.get_config().module_number.as_str()) {
-                add_module(index, module.get_output_image_size());
-            }
+            offset += len.next_multiple_of(align);
         }
 
         (slices, offset)
     }
 
-    fn output_image_bytes_from_registers(&self, total_len: usize) -> Vec<u8> {
-        let total_words = total_len / 2;
-        let mut bytes = Vec::with_capacity(total_len);
+    /// Output image bytes held in the rack's holding registers; `total_len` is
+    /// rounded up to whole registers.
+    fn output_image_bytes_from_registers(&self, rack: usize, total_len: usize) -> Vec<u8> {
+        let total_words = total_len.div_ceil(2);
+        let mut bytes = Vec::with_capacity(total_words * 2);
+        let registers = self.racks.get(rack).map(|r| r.output_registers.as_slice()).unwrap_or(&[]);
         for i in 0..total_words {
-            let val = self.holding_registers.get(i).copied().unwrap_or(0);
-            bytes.push((val & 0xFF) as u8);
-            bytes.push((val >> 8) as u8);
+            let val = registers.get(i).copied().unwrap_or(0);
+            bytes.extend_from_slice(&self.image_bytes(val));
         }
         bytes
     }
 
-    fn write_output_registers_from_bytes(&mut self, bytes: &[u8]) {
-        for (i, chunk) in bytes.chunks(2).enumerate() {
-            if i >= self.holding_registers.len() {
-                break;
-            }
-            let low = chunk[0] as u16;
-            let high = if chunk.len() > 1 { chunk[1] as u16 } else { 0 };
-            self.holding_registers[i] = low | (high << 8);
+    fn write_output_registers_from_bytes(&mut self, rack: usize, bytes: &[u8]) {
+        let words: Vec<u16> = bytes
+            .chunks(2)
+            .map(|chunk| self.image_word(chunk[0], chunk.get(1).copied().unwrap_or(0)))
+            .collect();
+        let Some(rack) = self.racks.get_mut(rack) else { return };
+        for (register, word) in rack.output_registers.iter_mut().zip(words) {
+            *register = word;
         }
     }
 
@@ -393,27 +1091,29 @@ impl Simulator {
         bytes
     }
 
-    fn apply_output_registers_to_modules(&mut self) {
-        let (slices, total_len) = self.output_module_slices();
+    fn apply_output_registers_to_modules(&mut self, rack: usize) {
+        let (slices, total_len) = self.output_module_slices(rack);
         if total_len == 0 {
             return;
         }
-        let bytes = self.output_image_bytes_from_registers(total_len);
+        let bytes = self.output_image_bytes_from_registers(rack, total_len);
         for (index, offset, len) in slices {
             if let Some(slice) = bytes.get(offset..offset + len) {
-                self.modules[index].write_outputs(slice);
+                let mut data = slice.to_vec();
+                self.order_dwords(self.modules[index].as_ref(), &mut data);
+                self.modules[index].write_outputs(&data);
             }
         }
     }
 
-    fn sync_output_registers_from_digital_outputs(&mut self) {
-        let (slices, total_len) = self.output_module_slices();
+    fn sync_output_registers_from_digital_outputs(&mut self, rack: usize) {
+        let (slices, total_len) = self.output_module_slices(rack);
         if total_len == 0 {
             return;
         }
-        let mut bytes = self.output_image_bytes_from_registers(total_len);
+        let mut bytes = self.output_image_bytes_from_registers(rack, total_len);
         for (index, offset, len) in slices {
-            if !Self::is_digital_output(self.modules[index].get_config().module_number.as_str()) {
+            if !self.is_digital_output(self.modules[index].get_config().module_number.as_str()) {
                 continue;
             }
             let packed = Self::pack_digital_output_bytes(self.modules[index].as_ref(), len);
@@ -421,79 +1121,67 @@ impl Simulator {
                 target.copy_from_slice(&packed);
             }
         }
-        self.write_output_registers_from_bytes(&bytes);
+        self.write_output_registers_from_bytes(rack, &bytes);
     }
     
     // Modbus Helpers
     
-    fn is_digital_input(module_number: &str) -> bool {
-        matches!(module_number, "750-1405" | "750-1415" | "750-430" | "753-440")
+    /// Catalog entry of a module's part number
+    pub(crate) fn module_descriptor(&self, module_number: &str) -> Option<&ModuleDescriptor> {
+        self.module_catalog.get(module_number)
     }
-    
-    fn is_digital_output(module_number: &str) -> bool {
-        matches!(module_number, "750-1504" | "750-1515" | "750-530" | "750-515")
+
+    pub(crate) fn module_kind(&self, module_number: &str) -> Option<ModuleKind> {
+        self.module_descriptor(module_number).map(|descriptor| descriptor.kind)
     }
-    
-    fn is_analog_input(module_number: &str) -> bool {
+
+    pub(crate) fn is_digital_input(&self, module_number: &str) -> bool {
+        self.module_kind(module_number) == Some(ModuleKind::DigitalInput)
+    }
+
+    pub(crate) fn is_digital_output(&self, module_number: &str) -> bool {
+        self.module_kind(module_number) == Some(ModuleKind::DigitalOutput)
+    }
+
+    pub(crate) fn is_analog_input(&self, module_number: &str) -> bool {
         // Counters are also Input Registers
-        matches!(module_number, "750-455" | "750-454" | "750-461" | "750-464" | "750-404" | "750-633")
+        self.module_descriptor(module_number)
+            .is_some_and(|descriptor| !descriptor.kind.is_digital() && descriptor.input_bytes() > 0)
     }
 
-    fn is_analog_output(module_number: &str) -> bool {
+    pub(crate) fn is_analog_output(&self, module_number: &str) -> bool {
         // Counters are also Holding Registers (Output)
-        matches!(module_number, "750-563" | "750-555" | "750-404" | "750-633")
-    }
-
-    /// Encode module ID according to WAGO discovery spec:
-    /// - Digital I/O modules: 0x8000 | (channel_count << 8) | (is_output << 1) | is_input
-    /// - Analog/Special modules: part number as decimal (e.g., 455 for 750-455)
-    fn encode_module_id(module_number: &str) -> u16 {
-        // Parse part number from "750-XXX" format
-        let part_num = module_number
-            .split('-')
-            .nth(1)
-            .and_then(|s| s.parse::<u16>().ok())
-            .unwrap_or(0);
-
-        // Digital I/O modules use special encoding
-        match module_number {
-            // Digital Inputs
-            "750-1405" => 0x8000 | (16 << 8) | 0x01,  // 16-Ch DI: 0x9001
-            "750-1415" => 0x8000 | (8 << 8) | 0x01,   // 8-Ch DI: 0x8801
-            "750-430"  => 0x8000 | (8 << 8) | 0x01,   // 8-Ch DI: 0x8801
-            "753-440"  => 0x8000 | (4 << 8) | 0x01,   // 4-Ch DI: 0x8401
-            
-            // Digital Outputs
-            "750-1504" => 0x8000 | (16 << 8) | 0x02,  // 16-Ch DO: 0x9002
-            "750-1515" => 0x8000 | (8 << 8) | 0x02,   // 8-Ch DO: 0x8802
-            "750-530"  => 0x8000 | (8 << 8) | 0x02,   // 8-Ch DO: 0x8802
-            "750-515"  => 0x8000 | (4 << 8) | 0x02,   // 4-Ch DO: 0x8402
-            
-            // Analog/Special modules return part number as decimal
-            _ => part_num,
-        }
+        self.module_descriptor(module_number)
+            .is_some_and(|descriptor| !descriptor.kind.is_digital() && descriptor.output_bytes() > 0)
+    }
+
+    /// Module ID served at 0x2031+, from the catalog (see `ModuleDescriptor::discovery_id`)
+    pub(crate) fn encode_module_id(&self, module_number: &str) -> u16 {
+        self.module_descriptor(module_number)
+            .map(|descriptor| descriptor.discovery_id())
+            .unwrap_or_else(|| catalog::part_number_id(module_number))
     }
 
     /// Calculate I/O bit counts for registers 0x1022-0x1025
-    fn calculate_io_bit_counts(&self) -> (u16, u16, u16, u16) {
+    pub(crate) fn calculate_io_bit_counts(&self, rack: usize) -> (u16, u16, u16, u16) {
         let mut output_analog_bytes = 0u16;
         let mut input_analog_bytes = 0u16;
         let mut output_digital_bits = 0u16;
         let mut input_digital_bits = 0u16;
 
-        for module in &self.modules {
+        for module in &self.modules[self.rack_module_range(rack)] {
             let module_number = module.get_config().module_number.as_str();
             
-            if Self::is_analog_output(module_number) {
+            if self.is_analog_output(module_number) {
                 output_analog_bytes += module.get_output_image_size() as u16;
             }
-            if Self::is_analog_input(module_number) {
+            if self.is_analog_input(module_number) {
                 input_analog_bytes += module.get_input_image_size() as u16;
             }
-            if Self::is_digital_output(module_number) {
+            if self.is_digital_output(module_number) {
                 output_digital_bits += (module.get_output_image_size() * 8) as u16;
             }
-            if Self::is_digital_input(module_number) {
+            if self.is_digital_input(module_number) {
                 input_digital_bits += (module.get_input_image_size() * 8) as u16;
             }
         }
@@ -503,10 +1191,10 @@ impl Simulator {
         (output_analog_bytes * 8, input_analog_bytes * 8, output_digital_bits, input_digital_bits)
     }
     
-    pub fn read_discrete_inputs(&self) -> Vec<bool> {
+    pub fn read_discrete_inputs(&self, rack: usize) -> Vec<bool> {
         let mut bits = Vec::new();
-        for module in &self.modules {
-            if Self::is_digital_input(module.get_config().module_number.as_str()) {
+        for module in &self.modules[self.rack_module_range(rack)] {
+            if self.is_digital_input(module.get_config().module_number.as_str()) {
                 let bytes = module.read_inputs();
                 // 2 bytes = 16 bits, 1 byte = 8 bits
                 for byte in bytes {
@@ -519,10 +1207,10 @@ impl Simulator {
         bits
     }
     
-    pub fn read_coils(&self) -> Vec<bool> {
+    pub fn read_coils(&self, rack: usize) -> Vec<bool> {
         let mut bits = Vec::new();
-        for module in &self.modules {
-            if Self::is_digital_output(module.get_config().module_number.as_str()) {
+        for module in &self.modules[self.rack_module_range(rack)] {
+            if self.is_digital_output(module.get_config().module_number.as_str()) {
                 let state = module.get_state();
                 for ch in state.channels {
                     match ch.value {
@@ -535,393 +1223,1092 @@ impl Simulator {
         bits
     }
     
-    pub fn read_input_registers(&self) -> Vec<u16> {
-        let bytes = self.build_input_image_bytes();
-        let mut words = Vec::new();
-        for chunk in bytes.chunks(2) {
-            if chunk.len() == 2 {
-                let val = (chunk[0] as u16) | ((chunk[1] as u16) << 8);
-                words.push(val);
-            }
-        }
-        words
+    pub fn read_input_registers(&self, rack: usize) -> Vec<u16> {
+        // With byte alignment an odd-length image ends in a half-filled register
+        self.build_input_image_bytes(rack)
+            .chunks(2)
+            .map(|chunk| self.image_word(chunk[0], chunk.get(1).copied().unwrap_or(0)))
+            .collect()
     }
 
-    pub fn read_special_input_registers(&self, addr: u16, cnt: u16) -> Option<Vec<u16>> {
-        // Handle WAGO discovery registers (0x1022+, 0x2000+)
-        if addr >= 0x1022 && addr <= 0x1025 {
-            // I/O Bit Count Registers
-            let (output_analog_bits, input_analog_bits, output_digital_bits, input_digital_bits) = 
-                self.calculate_io_bit_counts();
-            
-            let mut result = Vec::new();
-            for i in 0..cnt {
-                let val = match addr + i {
-                    0x1022 => output_analog_bits,
-                    0x1023 => input_analog_bits,
-                    0x1024 => output_digital_bits,
-                    0x1025 => input_digital_bits,
-                    _ => 0,
-                };
-                result.push(val);
-            }
-            return Some(result);
-        }
-        
-        if addr >= 0x2000 {
-            let mut result = Vec::new();
-            for i in 0..cnt {
-                let reg_addr = addr + i;
-                let val = match reg_addr {
-                    0x2010 => 0x0100, // FW Version 1.0
-                    0x2011 => 0x0750, // Series 750
-                    0x2012 => {
-                        // Return coupler part number
-                        if let Some(ref config) = self.config {
-                            let coupler = &config.coupler.module_number;
-                            coupler.split('-')
-                                .nth(1)
-                                .and_then(|s| s.parse::<u16>().ok())
-                                .unwrap_or(362)
-                        } else {
-                            362 // Default to 750-362
-                        }
-                    },
-                    // Module Discovery Registers (Batch 0-3)
-                    0x2030 => {
-                        // First register is always coupler
-                        if let Some(ref config) = self.config {
-                            let coupler = &config.coupler.module_number;
-                            coupler.split('-')
-                                .nth(1)
-                                .and_then(|s| s.parse::<u16>().ok())
-                                .unwrap_or(362)
-                        } else {
-                            362
-                        }
-                    },
-                    a if a >= 0x2031 && a <= 0x2070 => {
-                        // Batch 0: 0x2031-0x2070 (indices 1-64 for modules)
-                        let module_idx = (a - 0x2031) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0 // End of rack / empty slot
-                        }
-                    },
-                    a if a >= 0x2071 && a <= 0x20AE => {
-                        // Batch 1: 0x2071-0x20AE (64 modules)
-                        let module_idx = 64 + (a - 0x2071) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    a if a >= 0x20AF && a <= 0x20EC => {
-                        // Batch 2: 0x20AF-0x20EC (64 modules)
-                        let module_idx = 128 + (a - 0x20AF) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    a if a >= 0x20ED && a <= 0x2129 => {
-                        // Batch 3: 0x20ED-0x2129 (63 modules)
-                        let module_idx = 192 + (a - 0x20ED) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    _ => 0
-                };
-                result.push(val);
-            }
-            return Some(result);
-        }
-        None
+    /// FC01/FC02: `cnt` bits from `addr`; unmapped bits read as off.
+    pub fn read_bit_range(&self, rack: usize, area: DataArea, addr: u16, cnt: u16) -> Vec<bool> {
+        let inputs = self.read_discrete_inputs(rack);
+        let outputs = self.read_coils(rack);
+        (0..cnt)
+            .map(|i| match addr.checked_add(i).and_then(|a| self.resolve_bit(area, a, false)) {
+                Some(ImageRef::Input(index)) => inputs.get(index).copied().unwrap_or(false),
+                Some(ImageRef::Output(index)) => outputs.get(index).copied().unwrap_or(false),
+                None => false,
+            })
+            .collect()
     }
 
-    // General Holding Register Read (Watchdog + Discovery + AO + General Storage)
-    pub fn read_holding_registers(&self, addr: u16, cnt: u16) -> Vec<u16> {
-        // Handle WAGO discovery registers (0x1022+, 0x2000+) via Holding Registers (FC3)
-        if addr >= 0x1022 && addr <= 0x1025 {
-            // I/O Bit Count Registers
-            let (output_analog_bits, input_analog_bits, output_digital_bits, input_digital_bits) = 
-                self.calculate_io_bit_counts();
-            
-            let mut result = Vec::new();
-            for i in 0..cnt {
-                let val = match addr + i {
-                    0x1022 => output_analog_bits,
-                    0x1023 => input_analog_bits,
-                    0x1024 => output_digital_bits,
-                    0x1025 => input_digital_bits,
-                    _ => 0,
-                };
-                result.push(val);
-            }
-            return result;
+    /// FC04: registers served by the rack's register map; unmapped registers read 0.
+    pub fn read_input_register_range(&self, rack: usize, addr: u16, cnt: u16) -> Vec<u16> {
+        self.read_register_range(rack, DataArea::InputRegisters, addr, cnt)
+    }
+
+    /// FC03: registers served by the rack's register map; unmapped registers read 0.
+    pub fn read_holding_registers(&self, rack: usize, addr: u16, cnt: u16) -> Vec<u16> {
+        self.read_register_range(rack, DataArea::HoldingRegisters, addr, cnt)
+    }
+
+    fn read_register_range(&self, rack: usize, area: DataArea, addr: u16, cnt: u16) -> Vec<u16> {
+        let ctx = RegisterContext::new(self, rack);
+        if let Some(mut record) = self.register_map.read_record(&ctx, addr) {
+            record.resize(cnt as usize, 0);
+            return record;
         }
-        
-        if addr >= 0x2000 {
-            let mut result = Vec::new();
-            for i in 0..cnt {
-                let reg_addr = addr + i;
-                let val = match reg_addr {
-                    0x2010 => 0x0100, // FW Version 1.0
-                    0x2011 => 0x0750, // Series 750
-                    0x2012 => {
-                        // Return coupler part number
-                        if let Some(ref config) = self.config {
-                            let coupler = &config.coupler.module_number;
-                            coupler.split('-')
-                                .nth(1)
-                                .and_then(|s| s.parse::<u16>().ok())
-                                .unwrap_or(362)
-                        } else {
-                            362 // Default to 750-362
-                        }
-                    },
-                    // Module Discovery Registers (Batch 0-3)
-                    0x2030 => {
-                        // First register is always coupler
-                        if let Some(ref config) = self.config {
-                            let coupler = &config.coupler.module_number;
-                            coupler.split('-')
-                                .nth(1)
-                                .and_then(|s| s.parse::<u16>().ok())
-                                .unwrap_or(362)
-                        } else {
-                            362
-                        }
-                    },
-                    a if a >= 0x2031 && a <= 0x2070 => {
-                        // Batch 0: 0x2031-0x2070 (indices 1-64 for modules)
-                        let module_idx = (a - 0x2031) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0 // End of rack / empty slot
-                        }
-                    },
-                    a if a >= 0x2071 && a <= 0x20AE => {
-                        // Batch 1: 0x2071-0x20AE (64 modules)
-                        let module_idx = 64 + (a - 0x2071) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    a if a >= 0x20AF && a <= 0x20EC => {
-                        // Batch 2: 0x20AF-0x20EC (64 modules)
-                        let module_idx = 128 + (a - 0x20AF) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    a if a >= 0x20ED && a <= 0x2129 => {
-                        // Batch 3: 0x20ED-0x2129 (63 modules)
-                        let module_idx = 192 + (a - 0x20ED) as usize;
-                        if module_idx < self.modules.len() {
-                            let module_number = &self.modules[module_idx].get_config().module_number;
-                            Self::encode_module_id(module_number)
-                        } else {
-                            0
-                        }
-                    },
-                    _ => 0
-                };
-                result.push(val);
+        (0..cnt)
+            .map(|i| addr.checked_add(i).and_then(|a| self.register_map.read(&ctx, area, a)).unwrap_or(0))
+            .collect()
+    }
+
+    /// Index into a rack's output storage for holding register `addr`,
+    /// `None` below `modbus_map.outputs.base`
+    fn output_index(&self, addr: u16) -> Option<usize> {
+        addr.checked_sub(self.output_area.base).map(usize::from)
+    }
+
+    /// FC22: `(current AND and_mask) OR (or_mask AND NOT and_mask)` applied to
+    /// the value last written to a holding register.
+    pub fn mask_write_holding_register(&mut self, rack: usize, addr: u16, and_mask: u16, or_mask: u16) -> u16 {
+        let current = match addr {
+            watchdog::FIRST_REGISTER..=watchdog::LAST_REGISTER => {
+                self.racks.get(rack).map(|r| r.watchdog.read(addr)).unwrap_or(0)
             }
-            return result;
-        }
-        
-        // Handle Input Process Image at address 0 (DI + AI data)
-        if addr == 0 {
-            // Return input process image as holding registers (FC3)
-            // This matches the C++ implementation where address 0 returns the input process image
-            let all_input_regs = self.read_input_registers();
-            return all_input_regs.iter().take(cnt as usize).copied().collect();
-        }
-        
-        // Standard holding registers
-        let mut result = Vec::new();
-        for i in 0..cnt {
-            let reg_addr = addr + i;
-            match reg_addr {
-                0x1000 => result.push(self.watchdog_timeout as u16),
-                0x1003 => result.push(0), // Watchdog trigger (write-only, returns 0)
-                0x1009 => result.push(0), // Socket close config (returns stored value or 0)
-                _ => {
-                    // Return from general storage
-                    if (reg_addr as usize) < self.holding_registers.len() {
-                        result.push(self.holding_registers[reg_addr as usize]);
-                    } else {
-                        result.push(0);
-                    }
+            // Writes resolve to outputs, so the current value is the output
+            // register rather than the input FC03 returns at the same address
+            _ => match self.resolve_register(DataArea::HoldingRegisters, addr, true, 0) {
+                Some(ImageRef::Output(index)) => {
+                    self.racks.get(rack).and_then(|r| r.output_registers.get(index)).copied().unwrap_or(0)
                 }
-            }
-        }
-        result
+                _ => self.read_holding_registers(rack, addr, 1)[0],
+            },
+        };
+        let value = (current & and_mask) | (or_mask & !and_mask);
+        self.write_holding_registers(rack, addr, &[value]);
+        value
     }
-    
-    pub fn write_coils(&mut self, addr: u16, values: &[bool]) {
-        let mut current_addr = 0;
+
+    /// Device identification objects (FC43/14) for a rack as (object ID, value),
+    /// built from the firmware, series and part number registers at 0x2010-0x2012.
+    pub fn device_identification(&self, rack: usize) -> Vec<(u8, String)> {
+        let info = self.read_input_register_range(rack, 0x2010, 3);
+        let (firmware, series, part_number) = (info[0], info[1], info[2]);
+        // The series register reads as hex digits (0x0750 -> "750")
+        let model = format!("{:X}-{}", series, part_number);
+        vec![
+            (0x00, "WAGO GmbH & Co. KG".to_string()),
+            (0x01, model.clone()),
+            (0x02, format!("{:02}.{:02}", firmware >> 8, firmware & 0xFF)),
+            (0x03, "www.wago.com".to_string()),
+            (0x04, format!("WAGO-I/O-SYSTEM {:X}", series)),
+            (0x05, model),
+        ]
+    }
+
+    pub fn write_coils(&mut self, rack: usize, addr: u16, values: &[bool]) {
+        // A tripped watchdog holds the outputs at their safe values
+        if self.racks.get(rack).map_or(true, |r| r.watchdog.is_tripped()) {
+            return;
+        }
+        // Output bit index for each written coil
+        let targets: Vec<(usize, bool)> = values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &val)| {
+                let coil = addr.checked_add(i as u16)?;
+                match self.resolve_bit(DataArea::Coils, coil, true)? {
+                    ImageRef::Output(bit) => Some((bit, val)),
+                    ImageRef::Input(_) => None,
+                }
+            })
+            .collect();
+        let mut current_addr = 0usize;
+        let range = self.rack_module_range(rack);
         // First, collect module positions for digital outputs
         let do_module_positions: Vec<usize> = self.modules
             .iter()
             .enumerate()
-            .filter(|(_, m)| Self::is_digital_output(m.get_config().module_number.as_str()))
+            .take(range.end)
+            .skip(range.start)
+            .filter(|(_, m)| self.is_digital_output(m.get_config().module_number.as_str()))
             .map(|(i, _)| i)
             .collect();
 
         for module_pos in do_module_positions {
             let module = &mut self.modules[module_pos];
             let output_size = module.get_output_image_size() * 8; // bits
-            let module_end = current_addr + output_size as u16;
-
-            let start = addr;
-            let end = addr + values.len() as u16;
-
-            if start < module_end && end > current_addr {
-                for (i, &val) in values.iter().enumerate() {
-                    let target_addr = start + i as u16;
-                    if target_addr >= current_addr && target_addr < module_end {
-                        let channel = target_addr - current_addr;
-                        let channel_ref = ChannelRef::new(module_pos, channel);
-                        let write_value = if val { 1.0 } else { 0.0 };
-
-                        // Check if channel is forced - record shadow write instead
-                        if self.reactive_manager.is_forced(&channel_ref) {
-                            self.reactive_manager.record_shadow_write(&channel_ref, write_value);
-                            // Don't actually apply the value - force takes precedence
-                        } else {
-                            self.modules[module_pos].set_channel_value(channel, write_value);
-                        }
+            let module_end = current_addr + output_size;
+
+            for &(target_addr, val) in &targets {
+                if target_addr >= current_addr && target_addr < module_end {
+                    let channel = (target_addr - current_addr) as u16;
+                    let write_value = if val { 1.0 } else { 0.0 };
+
+                    // Check if channel is forced - record shadow write instead
+                    if let Some(channel_ref) = self.forced_channel(module_pos, channel) {
+                        self.reactive_manager.record_shadow_write(&channel_ref, write_value);
+                        // Don't actually apply the value - force takes precedence
+                    } else {
+                        self.modules[module_pos].set_channel_value(channel, write_value);
                     }
                 }
             }
 
             current_addr = module_end;
         }
-        self.sync_output_registers_from_digital_outputs();
+        self.sync_output_registers_from_digital_outputs(rack);
     }
     
-    pub fn write_holding_registers(&mut self, addr: u16, values: &[u16]) {
+    pub fn write_holding_registers(&mut self, rack: usize, addr: u16, values: &[u16]) {
+        if rack >= self.racks.len() {
+            return;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        // A tripped watchdog holds the outputs at their safe values
+        let outputs_locked = self.racks[rack].watchdog.is_tripped();
+        let register_map = Arc::clone(&self.register_map);
         for (i, &val) in values.iter().enumerate() {
-            let reg_addr = addr + i as u16;
-            
-            match reg_addr {
-                // Watchdog timeout configuration
-                0x1000 => {
-                    self.watchdog_timeout = val as u64;
-                    self.touch_watchdog();
-                },
-                // Watchdog trigger (any write resets watchdog)
-                0x1003 => {
-                    self.touch_watchdog();
-                },
-                // Socket close on watchdog config
-                0x1009 => {
-                    if (reg_addr as usize) < self.holding_registers.len() {
-                        self.holding_registers[reg_addr as usize] = val;
-                    }
-                },
-                // General storage
-                _ => {
-                    if (reg_addr as usize) < self.holding_registers.len() {
-                        self.holding_registers[reg_addr as usize] = val;
-                    }
-                }
-            }
+            let Some(reg_addr) = addr.checked_add(i as u16) else { break };
+            register_map.write(self, rack, reg_addr, val, now);
+        }
+        if outputs_locked {
+            return;
         }
         
-        // Update AO modules
-        // This iterates all AO modules and updates them based on the written values
-        // We need to check if the written range overlaps with AO map
-        let write_start = addr;
-        let write_end = addr + values.len() as u16;
-
-        // Collect AO module positions and their register ranges
-        let ao_modules: Vec<(usize, usize, u16, u16)> = {
-            let mut result = Vec::new();
-            let mut ao_addr = 0u16;
-            for (idx, module) in self.modules.iter().enumerate() {
-                if Self::is_analog_output(module.get_config().module_number.as_str()) {
-                    let output_len = module.get_output_image_size();
-                    let output_words = (output_len / 2) as u16;
-                    result.push((idx, output_len, ao_addr, ao_addr + output_words));
-                    ao_addr += output_words;
-                }
+        // Record what the PLC tried to write to forced analog output channels;
+        // the force keeps precedence when the modules are updated below
+        let written: Vec<usize> = (0..values.len())
+            .filter_map(|i| addr.checked_add(i as u16))
+            .filter_map(|a| match self.resolve_register(DataArea::HoldingRegisters, a, true, 0) {
+                Some(ImageRef::Output(index)) => Some(index),
+                _ => None,
+            })
+            .collect();
+        let (slices, total_len) = self.output_module_slices(rack);
+        let bytes = self.output_image_bytes_from_registers(rack, total_len);
+        for (module_pos, offset, len) in slices {
+            if !self.is_analog_output(self.modules[module_pos].get_config().module_number.as_str()) {
+                continue;
             }
-            result
-        };
-
-        for (module_pos, output_len, module_start, module_end) in ao_modules {
-            if write_start < module_end && write_end > module_start {
-                // Overlap - construct byte buffer for module
-                let mut bytes = vec![0u8; output_len];
-                let output_words = output_len / 2;
-
-                // For AO modules, typically 1 word = 1 channel (16-bit value)
-                for w in 0..output_words {
-                    let word_addr = module_start + w as u16;
-                    let channel = w as u16; // Channel corresponds to word index
-                    let channel_ref = ChannelRef::new(module_pos, channel);
-
-                    // Determine the value to write
-                    let val = if word_addr >= write_start && word_addr < write_end {
-                        // This register is being written
-                        let val_idx = (word_addr - write_start) as usize;
-                        values[val_idx]
-                    } else {
-                        // Not being written, keep old value from holding registers
-                        if (word_addr as usize) < self.holding_registers.len() {
-                            self.holding_registers[word_addr as usize]
-                        } else { 0 }
-                    };
-
-                    // Check if this channel is forced
-                    if self.reactive_manager.is_forced(&channel_ref) {
-                        // Record the shadow write (what PLC tried to write)
-                        if word_addr >= write_start && word_addr < write_end {
-                            self.reactive_manager.record_shadow_write(&channel_ref, val as f64);
-                        }
-                        // Use the forced value instead
-                        if let Some(forced_val) = self.reactive_manager.get_forced_value(&channel_ref) {
-                            let forced_u16 = forced_val as u16;
-                            bytes[w*2] = (forced_u16 & 0xFF) as u8;
-                            bytes[w*2+1] = (forced_u16 >> 8) as u8;
-                        } else {
-                            bytes[w*2] = (val & 0xFF) as u8;
-                            bytes[w*2+1] = (val >> 8) as u8;
-                        }
-                    } else {
-                        // Not forced, apply normally
-                        bytes[w*2] = (val & 0xFF) as u8;
-                        bytes[w*2+1] = (val >> 8) as u8;
-                    }
+            // For AO modules, typically 1 word = 1 channel (16-bit value)
+            for channel in 0..len / 2 {
+                let first = offset + channel * 2;
+                let was_written = written.iter().any(|&word| word * 2 < first + 2 && word * 2 + 2 > first);
+                let forced = if was_written { self.forced_channel(module_pos, channel as u16) } else { None };
+                if let Some(channel_ref) = forced {
+                    let raw = u16::from_le_bytes([bytes[first], bytes[first + 1]]);
+                    self.reactive_manager.record_shadow_write(&channel_ref, raw as f64);
                 }
-                self.modules[module_pos].write_outputs(&bytes);
             }
         }
 
-        self.apply_output_registers_to_modules();
+        self.apply_output_registers_to_modules(rack);
     }
 }
 
 pub struct AppState(pub Arc<Mutex<Simulator>>);
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_RACKS: &str = r#"
+version: 2
+sim: { name: 'test', seed: 1, tick_ms: 10 }
+transport:
+  kind: 'modbus_tcp'
+  listen: { host: '127.0.0.1', port: 0 }
+  unit_id: 1
+process_image: { layout: 'wago_750_default', word_endian: 'big', align_modules_to: 2 }
+modbus_map:
+  inputs: { kind: 'input_registers', base: 0 }
+  outputs: { kind: 'holding_registers', base: 0 }
+racks:
+  - id: 'rack0'
+    name: 'Rack 0'
+    modules:
+      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }
+  - id: 'rack1'
+    name: 'Rack 1'
+    unit_id: 7
+    modules:
+      - { id: 'r1m0', model: '750-1415', name: 'DI', channels: [] }
+      - { id: 'r1m1', model: '750-1515', name: 'DO', channels: [] }
+"#;
+
+    #[test]
+    fn test_racks_answer_on_their_unit_ids() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+
+        assert_eq!(sim.racks.len(), 2);
+        assert_eq!(sim.modules.len(), 3);
+        let endpoint = ("127.0.0.1".to_string(), 0);
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 1), Some(0));
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 7), Some(1));
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 2), None);
+
+        // Each rack has its own process image
+        sim.set_channel_value("r1m0", 3, 1.0);
+        assert!(!sim.read_discrete_inputs(0)[3]);
+        assert!(sim.read_discrete_inputs(1)[3]);
+        sim.write_coils(1, 0, &[true]);
+        assert!(sim.read_coils(1)[0]);
+        assert!(sim.read_coils(0).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_unit_any_falls_back_to_primary_rack() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&TWO_RACKS.replace("unit_id: 1", "unit_id: 1\n  unknown_unit: any"))
+            .unwrap();
+        assert_eq!(sim.rack_for_unit(Some(&("127.0.0.1".to_string(), 0)), 42), Some(0));
+    }
+
+    #[test]
+    fn test_units_0_and_255_address_the_coupler() {
+        let mut sim = Simulator::new();
+        let one_rack = &TWO_RACKS[..TWO_RACKS.find("  - id: 'rack1'").unwrap()];
+        sim.load_from_yaml_string(one_rack).unwrap();
+        let endpoint = ("127.0.0.1".to_string(), 0);
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 0), Some(0));
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 255), Some(0));
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 2), None);
+        // On a serial bus 0 is a broadcast and 255 an ordinary address
+        assert_eq!(sim.rack_for_unit(None, 255), None);
+
+        // A rack configured on 255 keeps it
+        sim.load_from_yaml_string(&TWO_RACKS.replace("unit_id: 7", "unit_id: 255")).unwrap();
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 255), Some(1));
+        assert_eq!(sim.rack_for_unit(Some(&endpoint), 0), Some(0));
+    }
+
+    #[test]
+    fn test_duplicate_unit_ids_are_rejected() {
+        let mut sim = Simulator::new();
+        let yaml = TWO_RACKS.replace("unit_id: 7", "unit_id: 1");
+        assert!(sim.load_from_yaml_string(&yaml).is_err());
+    }
+
+    #[test]
+    fn test_rack_with_own_listener() {
+        let mut sim = Simulator::new();
+        let yaml = TWO_RACKS.replace(
+            "unit_id: 7",
+            "unit_id: 1\n    listen: { host: '127.0.0.2', port: 5020 }",
+        );
+        sim.load_from_yaml_string(&yaml).unwrap();
+
+        assert_eq!(
+            sim.listen_endpoints(),
+            vec![("127.0.0.1".to_string(), 0), ("127.0.0.2".to_string(), 5020)]
+        );
+        // Same unit ID, told apart by listener
+        assert_eq!(sim.rack_for_unit(Some(&("127.0.0.1".to_string(), 0)), 1), Some(0));
+        assert_eq!(sim.rack_for_unit(Some(&("127.0.0.2".to_string(), 5020)), 1), Some(1));
+    }
+
+    #[test]
+    fn test_rtu_racks_are_slaves_on_one_bus() {
+        let mut sim = Simulator::new();
+        let rtu = TWO_RACKS.replace("kind: 'modbus_tcp'", "kind: 'modbus_rtu'");
+        assert!(sim.load_from_yaml_string(&rtu).is_err());
+
+        let rtu = rtu.replace(
+            "unit_id: 1\nprocess_image",
+            "unit_id: 1\n  serial: { port: '/dev/pts/7', baud_rate: 9600 }\nprocess_image",
+        );
+        sim.load_from_yaml_string(&rtu).unwrap();
+        assert_eq!(sim.transport_kind, TransportKind::ModbusRtu);
+        let serial = sim.serial.as_ref().unwrap();
+        assert_eq!((serial.baud_rate, serial.data_bits, serial.stop_bits), (9600, 8, 1));
+        assert_eq!(sim.rack_for_unit(None, 7), Some(1));
+        assert_eq!(sim.rack_for_unit(None, 3), None);
+    }
+
+    #[test]
+    fn test_watchdog_is_per_rack() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+        sim.write_coils(1, 0, &[true]);
+        sim.write_holding_registers(0, 0x1000, &[1]);
+        assert_eq!(sim.read_holding_registers(0, 0x1000, 1), vec![1]);
+        assert_eq!(sim.read_holding_registers(1, 0x1000, 1), vec![0]);
+
+        // Rack 0 expiring must not clear rack 1's outputs
+        sim.poll_watchdogs(u64::MAX);
+        assert!(sim.racks[0].watchdog.is_tripped());
+        assert!(sim.read_coils(1)[0]);
+        assert_eq!(sim.get_connection_state().watchdog_tripped_racks, vec!["rack0".to_string()]);
+    }
+
+    #[test]
+    fn test_watchdog_drives_outputs_to_safe_values() {
+        let yaml = TWO_RACKS.replace(
+            "      - { id: 'r1m1', model: '750-1515', name: 'DO', channels: [] }",
+            "      - { id: 'r1m1', model: '750-1515', name: 'DO', channels: [], safe_values: { 1: 1 } }\n      \
+             - { id: 'r1m2', model: '750-563', name: 'AO', channels: [], safe_values: { 0: 12.0 } }",
+        );
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&yaml).unwrap();
+        sim.write_coils(1, 0, &[true, false]);
+        sim.write_holding_registers(1, 0, &[0x7FF8, 0x7FF8]);
+        sim.write_holding_registers(1, 0x1000, &[10]); // 1 s
+
+        sim.poll_watchdogs(u64::MAX);
+        assert_eq!(&sim.read_coils(1)[..2], &[false, true]);
+        assert_eq!(&sim.racks[1].output_registers[..2], &[0x3FFC, 0x0000]);
+        let ao = sim.modules[3].get_state();
+        assert!(matches!(ao.channels[0].value, ChannelValue::Number(v) if (v - 12.0).abs() < 1e-9));
+
+        // Outputs hold until the watchdog is restarted
+        sim.write_coils(1, 0, &[true]);
+        sim.write_holding_registers(1, 1, &[0x1234]);
+        assert!(!sim.read_coils(1)[0]);
+        assert_eq!(sim.racks[1].output_registers[1], 0x0000);
+        sim.write_holding_registers(1, 0x1007, &[1]);
+        sim.write_coils(1, 0, &[true]);
+        assert!(sim.read_coils(1)[0]);
+        assert_eq!(sim.read_holding_registers(1, 0x1006, 1), vec![1]);
+    }
+
+    #[test]
+    fn test_process_image_options() {
+        let image = |process_image: &str| {
+            let yaml = TWO_RACKS
+                .replace(
+                    "process_image: { layout: 'wago_750_default', word_endian: 'big', align_modules_to: 2 }",
+                    process_image,
+                )
+                .replace(
+                    "      - { id: 'r1m1', model: '750-1515', name: 'DO', channels: [] }",
+                    "      - { id: 'r1m1', model: '750-404', name: 'CNT', channels: [] }\n      \
+                     - { id: 'r1m2', model: '750-1415', name: 'DI2', channels: [] }",
+                );
+            let mut sim = Simulator::new();
+            sim.load_from_yaml_string(&yaml).unwrap();
+            sim.modules[1].set_channel_value(0, 1.0);
+            sim.modules[2].set_channel_value(0, 0x0001_0002 as f64);
+            sim.modules[3].set_channel_value(1, 1.0);
+            sim.read_input_registers(1)
+        };
+
+        // Counter first (analog before digital), count low word first
+        let default = image("process_image: { layout: 'wago_750_default', word_endian: 'little', align_modules_to: 2 }");
+        assert_eq!(&default[1..], &[0x0002, 0x0001, 0x0001, 0x0002]);
+
+        let words = image("process_image: { layout: 'wago_750_default', word_endian: 'big', align_modules_to: 2 }");
+        assert_eq!(&words[1..3], &[0x0001, 0x0002]);
+
+        let bytes = image(
+            "process_image: { layout: 'wago_750_default', word_endian: 'little', byte_order_in_word: 'little', align_modules_to: 2 }",
+        );
+        assert_eq!(&bytes[1..], &[0x0200, 0x0100, 0x0100, 0x0200]);
+
+        // Byte alignment packs both 8-channel modules into one register
+        let packed = image("process_image: { layout: 'wago_750_default', word_endian: 'little', align_modules_to: 1 }");
+        assert_eq!(&packed[1..], &[0x0002, 0x0001, 0x0201]);
+
+        let slots = image("process_image: { layout: 'slot_order', word_endian: 'little', align_modules_to: 2 }");
+        assert_eq!(slots[0], 0x0001);
+        assert_eq!(&slots[2..], &[0x0002, 0x0001, 0x0002]);
+    }
+
+    #[test]
+    fn test_counter_handshake() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&TWO_RACKS.replace(
+            "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }",
+            "      - { id: 'cnt', model: '750-404', name: 'CNT', channels: [] }",
+        ))
+        .unwrap();
+        let pulse = |sim: &mut Simulator| {
+            sim.set_channel_value("cnt", 1, 1.0);
+            sim.set_channel_value("cnt", 1, 0.0);
+        };
+
+        // Set counter loads the preset (MSW first with big word endian) and is acknowledged
+        sim.write_holding_registers(0, 0, &[0x0020, 0x0001, 0x0000]);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0020, 0x0001, 0x0000]);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 2, 1), vec![0x0000]);
+        sim.write_holding_registers(0, 0, &[0x0000]);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0000, 0x0001, 0x0001]);
+
+        // Count down with U/D on; a locked gate ignores pulses, O1 follows the control byte
+        sim.set_channel_value("cnt", 2, 1.0);
+        pulse(&mut sim);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0002, 0x0000, 0xFFFF]);
+        sim.write_holding_registers(0, 0, &[0x0014]);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0016, 0x0000, 0xFFFF]);
+        assert!(matches!(sim.get_module_state("cnt").unwrap().channels[3].value, ChannelValue::Bool(true)));
+
+        // Wrapping sets the underflow and overflow flags until the counter is set again
+        sim.write_holding_registers(0, 0, &[0x0020, 0x0000, 0x0000]);
+        sim.write_holding_registers(0, 0, &[0x0000]);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0082, 0xFFFF, 0xFFFF]);
+        sim.set_channel_value("cnt", 2, 0.0);
+        pulse(&mut sim);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x00C0, 0x0000, 0x0000]);
+        sim.write_holding_registers(0, 0, &[0x0020, 0x0000, 0x0005]);
+        assert_eq!(sim.read_input_register_range(0, 0, 3), vec![0x0020, 0x0000, 0x0005]);
+
+        // Only the count is in the address map
+        let entries = crate::address_map::build(&sim);
+        assert_eq!(entries.iter().filter(|e| e.module_id == "cnt").count(), 1);
+    }
+
+    #[test]
+    fn test_counter_signal_models() {
+        let with_counter = |config: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }",
+                &format!("      - {{ id: 'cnt', model: '750-404', name: 'CNT', channels: [], module_config: {} }}", config),
+            )
+        };
+        let mut sim = Simulator::new();
+
+        // 30 GPM through a 2 pulses per gallon flowmeter is 1 pulse/s
+        sim.load_from_yaml_string(&with_counter("{ model: 'flowmeter', K: '2', GPM: '30' }")).unwrap();
+        for _ in 0..25 {
+            sim.tick();
+        }
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 0x0002]);
+        // Twice the flow, with the half pulse carried over
+        sim.set_channel_value("cnt", 5, 60.0);
+        for _ in 0..5 {
+            sim.tick();
+        }
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 0x0003]);
+        // A locked gate counts nothing
+        sim.write_holding_registers(0, 0, &[0x0010]);
+        sim.tick();
+        sim.tick();
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 0x0003]);
+        assert_eq!(sim.modules[0].scaling(0), None);
+
+        // Frequency mode: 1500 RPM at 4 PPR is 100 Hz, read in units of 0.5 Hz
+        sim.load_from_yaml_string(&with_counter("{ mode: 'freq', prescale: '0.5', model: 'fan', PPR: '4', RPM: '1500' }"))
+            .unwrap();
+        sim.tick();
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 200]);
+        assert_eq!(sim.modules[0].scaling(0).unwrap().to_string(), "0x0000..0xFFFF = 0..491512.5 RPM");
+        sim.set_channel_value("cnt", 0, 100.0);
+        assert!(matches!(sim.get_module_state("cnt").unwrap().channels[5].value, ChannelValue::Number(rpm) if rpm == 750.0));
+
+        // The default model reports the signal in Hz; the counter keys are checked on load
+        sim.load_from_yaml_string(&with_counter("{ mode: 'freq', frequency: '1234' }")).unwrap();
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 1234]);
+        // The frequency saturates at the top of the scaling range
+        sim.set_channel_value("cnt", 5, 100_000.0);
+        assert_eq!(sim.read_input_register_range(0, 1, 2), vec![0x0000, 0xFFFF]);
+        assert_eq!(sim.get_module_state("cnt").unwrap().channels[0].raw_value, 0xFFFF);
+        let error = sim.load_from_yaml_string(&with_counter("{ model: 'pump' }")).unwrap_err();
+        assert!(error.to_string().contains("Module 'cnt' in rack 'rack0': unknown counter model 'pump'"));
+        let error = sim.load_from_yaml_string(&with_counter("{ model: 'fan', PPR: '0' }")).unwrap_err();
+        assert!(error.to_string().contains("PPR '0' must be at least 1"));
+        assert!(sim.load_from_yaml_string(&with_counter("{ mode: 'gated' }")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_counter_and_pwm_config_is_rejected() {
+        // The module constructors fall back to defaults, so every setting they
+        // parse must be checked when the config is loaded
+        let with_module = |model: &str, config: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }",
+                &format!("      - {{ id: 'm', model: '{}', name: 'M', channels: [], module_config: {} }}", model, config),
+            )
+        };
+        let mut sim = Simulator::new();
+        for config in ["{ mode: 'gated' }", "{ model: 'pump' }", "{ prescale: '0' }", "{ prescale: 'fast' }"] {
+            let error = sim.load_from_yaml_string(&with_module("750-404", config)).unwrap_err();
+            assert!(error.to_string().starts_with("Module 'm' in rack 'rack0'"), "{}: {}", config, error);
+        }
+        for config in ["{ period_ms: '0' }", "{ period_ms: '-4' }", "{ period_ms: 'slow' }"] {
+            let error = sim.load_from_yaml_string(&with_module("750-511", config)).unwrap_err();
+            assert!(error.to_string().contains("period_ms"), "{}: {}", config, error);
+        }
+        sim.load_from_yaml_string(&with_module("750-404", "{ mode: 'freq', prescale: '2' }")).unwrap();
+        sim.load_from_yaml_string(&with_module("750-511", "{ period_ms: '10' }")).unwrap();
+    }
+
+    #[test]
+    fn test_pwm_outputs() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&TWO_RACKS.replace(
+            "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }",
+            "      - { id: 'pwm', model: '750-511', name: 'HEATERS', channels: [], module_config: { period_ms: '10' }, safe_values: { 1: 20.0 } }\n      \
+             - { id: 'pt', model: '750-673', name: 'STEPPER', channels: [] }",
+        ))
+        .unwrap();
+        let number = |sim: &Simulator, id: &str, ch: usize| match sim.get_module_state(id).unwrap().channels[ch].value {
+            ChannelValue::Number(value) => value,
+            ChannelValue::Bool(_) => panic!("channel {} of {} is not a number", ch, id),
+        };
+
+        // Duty cycles from the data words and periods from module_config; the inputs echo both words
+        sim.write_holding_registers(0, 0, &[0x0003, 0x4000, 0x0000, 0x7FFF]);
+        assert!((number(&sim, "pwm", 0) - 50.0).abs() < 0.01);
+        assert_eq!((number(&sim, "pwm", 1), number(&sim, "pwm", 2)), (100.0, 10.0));
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0x0003, 0x4000, 0x0000, 0x7FFF]);
+
+        // Duty cycles and periods can be set as channel values; a short circuit shows in the status byte
+        sim.set_channel_value("pwm", 0, 25.0);
+        sim.set_channel_value("pwm", 3, 20.0);
+        sim.set_channel_fault("pwm", 1, Some(ChannelFault::ShortCircuit));
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0x0003, 0x2000, 0x0040, 0x7FFF]);
+        assert_eq!(number(&sim, "pwm", 3), 20.0);
+
+        // A pulse train runs at 50 % duty with the period of its frequency
+        sim.write_holding_registers(0, 5, &[1000]);
+        assert_eq!((number(&sim, "pt", 0), number(&sim, "pt", 1)), (50.0, 1.0));
+        sim.set_channel_value("pt", 1, 4.0);
+        assert_eq!(sim.read_input_register_range(0, 5, 1), vec![250]);
+        sim.write_holding_registers(0, 5, &[0]);
+        assert_eq!((number(&sim, "pt", 0), number(&sim, "pt", 1)), (0.0, 0.0));
+
+        // Only the data words are in the address map
+        let entries = crate::address_map::build(&sim);
+        let registers: Vec<_> = entries.iter().filter(|e| e.rack == "rack0").map(|e| (e.area, e.register)).collect();
+        assert_eq!(
+            registers,
+            vec![(DataArea::HoldingRegisters, 0x0201), (DataArea::HoldingRegisters, 0x0203), (DataArea::HoldingRegisters, 0x0205)]
+        );
+        assert_eq!(entries[2].scaling.as_deref(), Some("0x0000..0x7FFF = 0..32767 Hz"));
+
+        // The watchdog clears the control words and drives the outputs to their safe values
+        sim.write_holding_registers(0, 0x1000, &[10]);
+        sim.poll_watchdogs(u64::MAX);
+        assert_eq!(&sim.racks[0].output_registers[..6], &[0x0000, 0x0000, 0x0000, 0x1999, 0x0000, 0x0000]);
+        assert_eq!(number(&sim, "pwm", 1), 20.0);
+    }
+
+    #[test]
+    fn test_unmapped_addresses_are_rejected() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+
+        // Rack 1 has 8 DI and 8 DO bits
+        assert!(sim.is_mapped(1, DataArea::DiscreteInputs, 0, 8, false));
+        assert!(!sim.is_mapped(1, DataArea::DiscreteInputs, 4, 8, false));
+        assert!(sim.is_mapped(1, DataArea::Coils, 7, 1, true));
+        assert!(!sim.is_mapped(0, DataArea::Coils, 0, 1, true));
+        assert!(sim.is_mapped(1, DataArea::HoldingRegisters, 0x1000, 1, true));
+        assert!(!sim.is_mapped(1, DataArea::HoldingRegisters, 0x2010, 1, true));
+        assert!(sim.is_mapped(1, DataArea::InputRegisters, 0x2010, 3, false));
+        assert!(!sim.is_mapped(1, DataArea::InputRegisters, 0x2014, 2, false));
+        assert!(!sim.is_mapped(1, DataArea::HoldingRegisters, 0xFFFF, 2, false));
+
+        sim.load_from_yaml_string(&TWO_RACKS.replace(
+            "outputs: { kind: 'holding_registers', base: 0 }",
+            "outputs: { kind: 'holding_registers', base: 0 }\n  address_check: 'lenient'",
+        ))
+        .unwrap();
+        assert!(sim.is_mapped(1, DataArea::DiscreteInputs, 4, 8, false));
+        // Lenient reads at the top of the address space pad instead of overflowing
+        assert_eq!(sim.read_holding_registers(1, 0xFFFF, 2), vec![0, 0]);
+        sim.write_holding_registers(1, 0xFFFF, &[1, 2]);
+        sim.write_coils(1, 0xFFFF, &[true, true]);
+    }
+
+    #[test]
+    fn test_modbus_map_bases() {
+        let map = |inputs: &str, outputs: &str| {
+            TWO_RACKS
+                .replace("inputs: { kind: 'input_registers', base: 0 }", inputs)
+                .replace("outputs: { kind: 'holding_registers', base: 0 }", &format!("{}\n  mirror_areas: false", outputs))
+        };
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&map(
+            "inputs: { kind: 'holding_registers', base: 0x0100 }",
+            "outputs: { kind: 'holding_registers', base: 0x0200 }",
+        ))
+        .unwrap();
+        sim.modules[1].set_channel_value(0, 1.0);
+
+        // Inputs are only exposed through holding registers
+        assert!(sim.is_mapped(1, DataArea::HoldingRegisters, 256, 1, false));
+        assert!(!sim.is_mapped(1, DataArea::InputRegisters, 0, 1, false));
+        assert_eq!(sim.read_holding_registers(1, 256, 1), vec![0x0001]);
+
+        assert!(!sim.is_mapped(1, DataArea::HoldingRegisters, 0, 1, true));
+        assert!(sim.is_mapped(1, DataArea::HoldingRegisters, 512, 1, true));
+        sim.write_holding_registers(1, 512, &[0x0001]);
+        assert!(sim.read_coils(1)[0]);
+        assert_eq!(sim.read_holding_registers(1, 512, 1), vec![0x0001]);
+
+        sim.load_from_yaml_string(&map(
+            "inputs: { kind: 'input_registers', base: 256 }",
+            "outputs: { kind: 'holding_registers', base: 0 }",
+        ))
+        .unwrap();
+        sim.modules[1].set_channel_value(0, 1.0);
+        assert!(sim.is_mapped(1, DataArea::InputRegisters, 256, 1, false));
+        assert!(!sim.is_mapped(1, DataArea::InputRegisters, 0, 1, false));
+        assert_eq!(sim.read_input_register_range(1, 255, 2), vec![0, 0x0001]);
+
+        let error = sim
+            .load_from_yaml_string(&map(
+                "inputs: { kind: 'input_registers', base: 0 }",
+                "outputs: { kind: 'input_registers', base: 0 }",
+            ))
+            .unwrap_err();
+        assert!(error.to_string().contains("modbus_map.outputs.kind"));
+    }
+
+    #[test]
+    fn test_wago_mirror_areas() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+        sim.modules[1].set_channel_value(2, 1.0);
+
+        // Inputs at 0x0000 and output read-back at 0x0200, in both FC03 and FC04
+        sim.write_holding_registers(1, 0x0000, &[0x0081]);
+        assert_eq!(sim.read_holding_registers(1, 0x0000, 1), vec![0x0004]);
+        assert_eq!(sim.read_input_register_range(1, 0x0000, 1), vec![0x0004]);
+        assert_eq!(sim.read_holding_registers(1, 0x0200, 1), vec![0x0081]);
+        assert_eq!(sim.read_input_register_range(1, 0x0200, 1), vec![0x0081]);
+        sim.write_holding_registers(1, 0x0200, &[0x0002]);
+        assert_eq!(sim.read_input_register_range(1, 0x0200, 1), vec![0x0002]);
+        assert!(sim.is_mapped(1, DataArea::InputRegisters, 0x0200, 1, false));
+        assert!(!sim.is_mapped(1, DataArea::InputRegisters, 0x0201, 1, false));
+        assert!(!sim.is_mapped(1, DataArea::HoldingRegisters, 0x6000, 1, false));
+
+        // FC01 and FC02 read inputs from 0x0000 and outputs from 0x0200
+        sim.write_coils(1, 0x0200, &[true]);
+        assert_eq!(sim.read_bit_range(1, DataArea::Coils, 0x0000, 3), vec![false, false, true]);
+        assert_eq!(sim.read_bit_range(1, DataArea::DiscreteInputs, 0x0200, 2), vec![true, true]);
+        sim.write_coils(1, 0x0001, &[false]);
+        assert_eq!(sim.read_bit_range(1, DataArea::Coils, 0x0200, 2), vec![true, false]);
+        assert!(!sim.is_mapped(1, DataArea::Coils, 0x0100, 1, false));
+    }
+
+    #[test]
+    fn test_mask_write_and_device_identification() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+
+        sim.write_holding_registers(0, 0x1000, &[0x0012]);
+        // Modbus spec example: 0x12 AND 0xF2 OR (0x25 AND NOT 0xF2) = 0x17
+        assert_eq!(sim.mask_write_holding_register(0, 0x1000, 0x00F2, 0x0025), 0x0017);
+        assert_eq!(sim.read_holding_registers(0, 0x1000, 1), vec![0x0017]);
+
+        let objects = sim.device_identification(1);
+        assert_eq!(objects[1], (0x01, "750-362".to_string()));
+        assert_eq!(objects[2], (0x02, "01.00".to_string()));
+    }
+
+    #[test]
+    fn test_coupler_model_limits() {
+        let with_coupler = |coupler: &str| {
+            TWO_RACKS.replace("    unit_id: 7\n", &format!("    unit_id: 7\n    coupler: {}\n", coupler))
+        };
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&with_coupler("{ model: '750-352', gateway: '10.0.0.1' }")).unwrap();
+        assert_eq!(sim.racks[0].coupler.part_number, "750-362");
+        assert_eq!(sim.racks[1].coupler.part_number, "750-352");
+        assert_eq!(sim.racks[1].config.coupler.metadata.gateway.as_deref(), Some("10.0.0.1"));
+        assert_eq!(sim.read_input_register_range(1, 0x2012, 1), vec![352]);
+        // FC43 is a 4th generation feature
+        assert!(sim.supports_function(0, 0x2B));
+        assert!(!sim.supports_function(1, 0x2B));
+
+        let error = sim.load_from_yaml_string(&with_coupler("{ model: '750-999' }")).unwrap_err();
+        assert!(error.to_string().contains("unknown coupler"));
+        assert!(sim.load_from_yaml_string(&with_coupler("{ model: '750-315' }")).is_err());
+        // The rejected configs left the loaded racks in place
+        assert_eq!(sim.racks[1].coupler.part_number, "750-352");
+
+        // A serial coupler is the default for RTU, with its own watchdog time and module limit
+        let modules: String = (0..65)
+            .map(|i| format!("      - {{ id: 'm{}', model: '750-1415', name: 'DI', channels: [] }}\n", i))
+            .collect();
+        let rtu = TWO_RACKS
+            .replace("kind: 'modbus_tcp'", "kind: 'modbus_rtu'")
+            .replace("unit_id: 1\nprocess_image", "unit_id: 1\n  serial: { port: '/dev/pts/7' }\nprocess_image");
+        sim.load_from_yaml_string(&rtu).unwrap();
+        assert_eq!(sim.racks[0].coupler.part_number, "750-315");
+        assert_eq!(sim.read_holding_registers(0, 0x1000, 1), vec![100]);
+        assert!(!sim.is_mapped(0, DataArea::InputRegisters, 0x1031, 3, false));
+        let error = sim
+            .load_from_yaml_string(&rtu.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }\n",
+                &modules,
+            ))
+            .unwrap_err();
+        assert!(error.to_string().contains("65 modules exceed the 750-315 limit of 64"));
+    }
+
+    #[test]
+    fn test_reload_keeps_state_only_for_same_coupler() {
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+        sim.write_holding_registers(1, 0x1000, &[50]);
+        sim.write_holding_registers(1, 0x0200, &[0x0005]);
+
+        // Same rack ID and coupler: watchdog time and outputs survive
+        sim.load_from_yaml_string(TWO_RACKS).unwrap();
+        assert_eq!(sim.read_holding_registers(1, 0x1000, 1), vec![50]);
+        assert_eq!(sim.read_holding_registers(1, 0x0200, 1), vec![0x0005]);
+
+        // Another coupler under the same rack ID starts with its own defaults
+        let yaml = TWO_RACKS.replace("    unit_id: 7\n", "    unit_id: 7\n    coupler: { model: '750-352' }\n");
+        sim.load_from_yaml_string(&yaml).unwrap();
+        assert_eq!(sim.read_holding_registers(1, 0x1000, 1), vec![0]);
+        assert_eq!(sim.read_holding_registers(1, 0x0200, 1), vec![0]);
+    }
+
+    #[test]
+    fn test_user_module_catalog() {
+        let path = std::env::temp_dir().join(format!("module_catalog_{}.yaml", std::process::id()));
+        std::fs::write(
+            &path,
+            "modules:\n- { part_number: '750-496', kind: analog_input, channels: 8, range: [0, 20], unit: 'mA' }\n",
+        )
+        .unwrap();
+        let with_module = |model: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r1m1', model: '750-1515', name: 'DO', channels: [] }\n",
+                &format!(
+                    "      - {{ id: 'r1m1', model: '750-1515', name: 'DO', channels: [] }}\n      - {{ id: 'r1m2', model: '{}', name: 'AI', channels: [] }}\n",
+                    model
+                ),
+            )
+        };
+        let mut sim = Simulator::new();
+        let error = sim.load_from_yaml_string(&with_module("750-496")).unwrap_err();
+        assert!(error.to_string().contains("Module 'r1m2' in rack 'rack1' uses unknown model '750-496'"));
+
+        let yaml = with_module("750-496").replace(
+            "racks:\n",
+            &format!("module_catalog: '{}'\nracks:\n", path.display()),
+        );
+        sim.load_from_yaml_string(&yaml).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Eight AI words ahead of the DI byte, and the catalog's discovery ID
+        sim.set_channel_value("r1m2", 0, 10.0);
+        assert_eq!(sim.read_input_registers(1).len(), 9);
+        assert_eq!(sim.read_input_register_range(1, 0, 1), vec![0x4000]);
+        assert_eq!(sim.read_input_register_range(1, 0x2031, 3), vec![0x8801, 0x8802, 496]);
+        assert_eq!(sim.module_catalog.source.as_deref(), Some(path.to_str().unwrap()));
+
+        let error = sim.load_from_yaml_string(&yaml).unwrap_err();
+        assert!(error.to_string().contains("Failed to read module catalog"));
+    }
+
+    #[test]
+    fn test_analog_channel_ranges() {
+        let with_modules = |module_config: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }\n",
+                &format!(
+                    "      - id: 'ai'\n        model: '750-456'\n        name: 'AI'\n        channels:\n          \
+                     - {{ ch: 1, signal: {{ name: 'pressure', type: 'analog', min_value: 0, max_value: 16, units: 'bar' }} }}\n      \
+                     - id: 'level'\n        model: '750-459'\n        name: 'AI'\n        module_config: {}\n        channels:\n          \
+                     - {{ ch: 2, signal: {{ name: 'raw', type: 'analog', scale: 0.01, offset: 0 }} }}\n      \
+                     - {{ id: 'ao', model: '750-563', name: 'AO', channels: [] }}\n",
+                    module_config
+                ),
+            )
+        };
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&with_modules("{ min_value: '0', max_value: '100', units: '%' }")).unwrap();
+
+        // ±10 V in two's complement, then the channel's 0..16 bar
+        sim.set_channel_value("ai", 0, -10.0);
+        assert_eq!(sim.read_input_register_range(0, 0, 1), vec![0x8008]);
+        sim.set_channel_value("ai", 0, 5.0);
+        sim.set_channel_value("ai", 1, 8.0);
+        assert_eq!(sim.read_input_register_range(0, 0, 2), vec![0x3FFC, 0x0000]);
+        assert_eq!(sim.modules[0].scaling(1).unwrap().to_string(), "-32760..32760 = 0..16 bar");
+
+        // Module-wide 0..100 % (over range reads 0x7FFF), and one channel with an explicit scale
+        sim.set_channel_value("level", 0, 50.0);
+        sim.set_channel_value("level", 2, 12.34);
+        sim.set_channel_value("level", 3, 150.0);
+        assert_eq!(sim.read_input_register_range(0, 2, 4), vec![0x3FFC, 0x0000, 1234, 0x7FFF]);
+        assert_eq!(sim.modules[1].scaling(0).unwrap().to_string(), "0x0000..0x7FF8 = 0..100 %");
+
+        // Outputs are scaled back from the written raw value
+        sim.write_holding_registers(0, 0, &[0x3FFC]);
+        let ao = sim.modules[2].get_state();
+        assert!(matches!(ao.channels[0].value, ChannelValue::Number(v) if (v - 12.0).abs() < 1e-9));
+
+        let error = sim.load_from_yaml_string(&with_modules("{ scale: 'abc' }")).unwrap_err();
+        assert!(error.to_string().contains("Module 'level' in rack 'rack0': scale 'abc' is not a number"));
+    }
+
+    #[test]
+    fn test_analog_channel_faults() {
+        let mut sim = Simulator::new();
+        let yaml = TWO_RACKS.replace(
+            "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }\n",
+            "      - { id: 'ai', model: '750-454', name: 'AI', channels: [], module_config: { status_byte: 'true' } }\n      \
+             - { id: 'ao', model: '750-563', name: 'AO', channels: [] }\n      \
+             - { id: 'di', model: '750-1415', name: 'DI', channels: [] }\n",
+        ) + r#"
+reactive_scenarios:
+  - name: 'broken wire'
+    default: true
+    behaviors:
+      - id: 'wire'
+        source: { modulePosition: 2, channel: 0 }
+        target: { modulePosition: 0, channel: 0 }
+        fault: 'open-circuit'
+"#;
+        sim.load_from_yaml_string(&yaml).unwrap();
+
+        // Out-of-range values read 0x7FFF / the low end of the raw range with status bits
+        sim.set_channel_value("ai", 0, 12.0);
+        sim.set_channel_value("ai", 1, 21.0);
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0, 0x3FFC, 0x42, 0x7FFF]);
+        sim.set_channel_value("ai", 1, 2.0);
+        assert_eq!(sim.read_input_register_range(0, 2, 2), vec![0x41, 0]);
+        assert_eq!(sim.get_module_state("ai").unwrap().channels[1].fault, Some(ChannelFault::UnderRange));
+
+        sim.set_channel_fault("ai", 0, Some(ChannelFault::ModuleFault));
+        assert_eq!(sim.read_input_register_range(0, 0, 2), vec![0x40, 0x7FFF]);
+        sim.set_channel_fault("ai", 0, None);
+        assert_eq!(sim.read_input_register_range(0, 0, 2), vec![0, 0x3FFC]);
+
+        // Output faults are diagnostics only
+        sim.set_channel_fault("ao", 1, Some(ChannelFault::OpenCircuit));
+        let ao = sim.get_module_state("ao").unwrap();
+        assert_eq!((ao.channels[1].fault, ao.channels[1].status), (Some(ChannelFault::OpenCircuit), 0x40));
+
+        // A reactive behavior breaks the wire while its source is on
+        sim.set_channel_value("di", 0, 1.0);
+        sim.tick();
+        assert_eq!(sim.read_input_register_range(0, 0, 2), vec![0x42, 0x7FFF]);
+        sim.set_channel_value("di", 0, 0.0);
+        sim.tick();
+        assert_eq!(sim.read_input_register_range(0, 0, 2), vec![0, 0x3FFC]);
+
+        // Scripted step
+        let scenario: Scenario = serde_yaml::from_str(
+            "{ name: 'short', version: '1', loop_enabled: false, steps: [\
+             { action: 'channel_fault', module_position: 0, channel: 1, channel_fault: 'short-circuit' }] }",
+        )
+        .unwrap();
+        sim.scenario_engine.load_scenario(scenario);
+        sim.scenario_engine.play();
+        sim.tick();
+        let ai = sim.get_module_state("ai").unwrap();
+        assert_eq!((ai.channels[1].raw_value, ai.channels[1].fault), (0x7FFF, Some(ChannelFault::ShortCircuit)));
+    }
+
+    #[test]
+    fn test_thermocouple_modules() {
+        let with_types = |types: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }\n",
+                &format!(
+                    "      - {{ id: 'tc', model: '750-469', name: 'TC', channels: [], \
+                     module_config: {{ thermocouple: '{}', cold_junction: '30', status_byte: 'true' }} }}\n      \
+                     - {{ id: 'tc8', model: '750-458', name: 'TC', channels: [], \
+                     module_config: {{ cold_junction_compensation: 'false' }} }}\n",
+                    types
+                ),
+            )
+        };
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&with_types("J, S")).unwrap();
+
+        // Status word ahead of each value; channels start at the cold junction temperature
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0, 300, 0, 300]);
+        sim.set_channel_value("tc", 0, 1250.0);
+        sim.set_channel_value("tc", 1, -20.0);
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0x42, 12000, 0x41, 0]);
+        assert_eq!(sim.get_module_state("tc").unwrap().channels[0].fault, Some(ChannelFault::OverRange));
+
+        // Wire break reads upscale; a short circuit measures the cold junction
+        sim.set_channel_fault("tc", 0, Some(ChannelFault::ShortCircuit));
+        sim.set_channel_fault("tc", 1, Some(ChannelFault::OpenCircuit));
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0, 300, 0x42, 0x7FFF]);
+        let state = sim.get_module_state("tc").unwrap();
+        assert_eq!((state.channels[1].status, state.channels[1].fault), (0x42, Some(ChannelFault::OpenCircuit)));
+        sim.set_channel_fault("tc", 1, None);
+        assert_eq!(sim.read_input_register_range(0, 3, 1), vec![0]);
+
+        // Without compensation the reading is relative to the 25 °C terminals
+        sim.set_channel_value("tc8", 0, 125.5);
+        sim.set_channel_value("tc8", 1, 0.0);
+        assert_eq!(sim.read_input_register_range(0, 4, 2), vec![1005, (-250i16) as u16]);
+
+        let entries = crate::address_map::build(&sim);
+        let tc1 = entries.iter().find(|e| e.module_id == "tc" && e.channel == 1).unwrap();
+        assert_eq!((tc1.address, tc1.scaling.as_deref()), (3, Some("0x0000..0x4268 = 0..1700 °C")));
+
+        let error = sim.load_from_yaml_string(&with_types("X")).unwrap_err();
+        assert!(error.to_string().contains("unknown thermocouple type 'X'"));
+    }
+
+    #[test]
+    fn test_rtd_sensor_types() {
+        let with_config = |rtd: &str, rtd2: &str| {
+            TWO_RACKS.replace(
+                "      - { id: 'r0m0', model: '750-1415', name: 'DI', channels: [] }\n",
+                &format!(
+                    "      - {{ id: 'rtd', model: '750-461', name: 'RTD', channels: [], module_config: {{ {} }} }}\n      \
+                     - {{ id: 'rtd2', model: '750-464', name: 'RTD', channels: [], module_config: {{ {} }} }}\n",
+                    rtd, rtd2
+                ),
+            )
+        };
+        let mut sim = Simulator::new();
+        sim.load_from_yaml_string(&with_config(
+            "sensor: 'pt1000, resistance', status_byte: 'true'",
+            "sensor: 'ni1000', wiring: '2', lead_resistance: '12.36'",
+        ))
+        .unwrap();
+
+        // 20 °C and 100.0 Ω; the 2-wire Ni1000 reads 2 °C high from its leads
+        assert_eq!(sim.read_input_register_range(0, 0, 6), vec![0, 200, 0, 1000, 220, 220]);
+        sim.set_channel_value("rtd", 1, 1500.0);
+        sim.set_channel_value("rtd2", 0, -100.0);
+        assert_eq!(sim.read_input_register_range(0, 2, 3), vec![0x42, 12000, (-600i16) as u16]);
+        assert_eq!(sim.get_module_state("rtd2").unwrap().channels[0].fault, Some(ChannelFault::UnderRange));
+
+        // Short circuit reads the lowest value, wire break 0x7FFF
+        sim.set_channel_fault("rtd", 0, Some(ChannelFault::ShortCircuit));
+        sim.set_channel_fault("rtd", 1, Some(ChannelFault::OpenCircuit));
+        assert_eq!(sim.read_input_register_range(0, 0, 4), vec![0x41, 0x8000, 0x42, 0x7FFF]);
+        assert_eq!(sim.get_module_state("rtd").unwrap().channels[0].status, 0x41);
+
+        let entries = crate::address_map::build(&sim);
+        let ohms = entries.iter().find(|e| e.module_id == "rtd" && e.channel == 1).unwrap();
+        assert_eq!((ohms.address, ohms.scaling.as_deref()), (3, Some("0x0064..0x2EE0 = 10..1200 Ω")));
+
+        let error = sim.load_from_yaml_string(&with_config("sensor: 'pt500'", "")).unwrap_err();
+        assert!(error.to_string().contains("unknown RTD sensor 'pt500'"));
+        let error = sim.load_from_yaml_string(&with_config("", "wiring: '4'")).unwrap_err();
+        assert!(error.to_string().contains("wiring '4' must be 2 or 3"));
+    }
+
+    #[test]
+    fn test_scenarios_reference_channels_by_rack() {
+        let mut sim = Simulator::new();
+        let yaml = format!(
+            "{}{}",
+            TWO_RACKS,
+            r#"
+reactive_scenarios:
+  - name: 'cross'
+    behaviors:
+      - id: 'b1'
+        source: { modulePosition: 0, channel: 0 }
+        target: { rack: 'rack1', modulePosition: 1, channel: 2 }
+"#
+        );
+        sim.load_from_yaml_string(&yaml).unwrap();
+        let target = &sim.reactive_manager.scenarios["cross"].behaviors[0].target;
+        assert_eq!(sim.module_index(target), Some(2));
+
+        let bad = yaml.replace("rack: 'rack1'", "rack: 'nope'");
+        assert!(sim.load_from_yaml_string(&bad).is_err());
+    }
+
+    #[test]
+    fn test_rack_references_follow_layout_changes() {
+        let mut sim = Simulator::new();
+        let yaml = format!(
+            "{}{}",
+            TWO_RACKS,
+            r#"
+reactive_scenarios:
+  - name: 'follow'
+    default: true
+    behaviors:
+      - id: 'b1'
+        source: { rack: 'rack1', modulePosition: 0, channel: 0 }
+        target: { rack: 'rack1', modulePosition: 1, channel: 2 }
+"#
+        );
+        sim.load_from_yaml_string(&yaml).unwrap();
+
+        // A module added to rack 0 moves rack 1's modules along
+        let mut rack0 = sim.racks[0].config.clone();
+        let mut added = rack0.modules[0].clone();
+        added.id = "r0m1".to_string();
+        added.slot_position += 1;
+        rack0.modules.push(added);
+        sim.update_primary_rack(rack0);
+        assert_eq!(sim.racks[1].module_offset, 2);
+
+        sim.set_channel_value("r1m0", 0, 1.0);
+        sim.tick();
+        assert!(sim.read_coils(1)[2]);
+
+        // Scripted steps resolve their rack when they run
+        let scenario: Scenario = serde_yaml::from_str(
+            "{ name: 'set', version: '1', loop_enabled: false, steps: [\
+             { action: 'set', rack: 'rack1', module_position: 1, channel: 3, value: 1 }] }",
+        )
+        .unwrap();
+        sim.scenario_engine.load_scenario(scenario);
+        sim.scenario_engine.play();
+        sim.tick();
+        assert!(sim.read_coils(1)[3]);
+        assert!(!sim.read_discrete_inputs(0).contains(&true));
+    }
+
+    #[test]
+    fn test_rack_references_stay_inside_their_rack() {
+        // Rack 0 has one module; position 1 would be the first module of rack 1
+        let reactive = format!(
+            "{}{}",
+            TWO_RACKS,
+            r#"
+reactive_scenarios:
+  - name: 'overflow'
+    behaviors:
+      - id: 'b1'
+        source: { rack: 'rack1', modulePosition: 0, channel: 0 }
+        target: { rack: 'rack0', modulePosition: 1, channel: 0 }
+"#
+        );
+        let mut sim = Simulator::new();
+        let err = sim.load_from_yaml_string(&reactive).unwrap_err();
+        assert!(err.to_string().contains("rack 'rack0'"), "{}", err);
+        sim.load_from_yaml_string(&reactive.replace("rack: 'rack0', modulePosition: 1", "rack: 'rack0', modulePosition: 0"))
+            .unwrap();
+
+        let scripted = format!(
+            "{}{}",
+            TWO_RACKS,
+            r#"
+scenarios:
+  - name: 'overflow'
+    version: '1'
+    loop_enabled: false
+    steps:
+      - { action: 'set', rack: 'rack0', module_position: 1, channel: 0, value: 1 }
+"#
+        );
+        assert!(sim.load_from_yaml_string(&scripted).is_err());
+        let trigger = scripted.replace(
+            "rack: 'rack0', module_position: 1",
+            "rack: 'rack1', module_position: 1, trigger_rack: 'rack0', trigger_module: 1, trigger_channel: 0, trigger_value: 1",
+        );
+        assert!(sim.load_from_yaml_string(&trigger).is_err());
+        sim.load_from_yaml_string(&trigger.replace("trigger_module: 1", "trigger_module: 0")).unwrap();
+
+        // Scenarios loaded at runtime are not validated; the reference resolves to nothing
+        let overflow = ChannelRef { rack: Some("rack0".to_string()), ..ChannelRef::new(1, 0) };
+        assert_eq!(sim.module_index(&overflow), None);
+    }
+}
@@ -0,0 +1,258 @@
+// WAGO 750 coupler watchdog (registers 0x1000-0x100A). Writing a timeout
+// starts the watchdog; requests with a function code enabled in the mask
+// retrigger it. When it is not triggered in time it trips: outputs go to their
+// safe values and stay there until the watchdog is restarted or stopped.
+
+use std::collections::HashMap;
+
+pub const FIRST_REGISTER: u16 = 0x1000;
+pub const LAST_REGISTER: u16 = 0x100A;
+
+const STOP_SEQUENCE_FIRST: u16 = 0xAAAA;
+const STOP_SEQUENCE_SECOND: u16 = 0x5555;
+/// Either value written to 0x1008 stops the watchdog at once
+const SIMPLE_STOP_VALUES: [u16; 2] = [0xAA55, 0x55AA];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchdogState {
+    #[default]
+    Stopped,
+    Running,
+    /// Timed out; outputs hold their safe values
+    Tripped,
+}
+
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    /// Timeout in 100 ms units (0x1000); 0 disables the watchdog
+    time: u16,
+    /// Function codes 1-16 and 17-32 that retrigger the watchdog (0x1001/0x1002)
+    function_mask: [u16; 2],
+    /// Last value written to the trigger register (0x1003)
+    trigger_value: u16,
+    /// Shortest time left at a trigger since the watchdog started, in 100 ms units (0x1004)
+    min_trigger_time: u16,
+    /// 0xAAAA seen at 0x1005; 0x5555 next stops the watchdog
+    stop_armed: bool,
+    /// 1 = close the Modbus connections on timeout (0x1009)
+    close_on_timeout: u16,
+    /// 1 = only writes to 0x1003 retrigger the watchdog (0x100A)
+    trigger_register_only: u16,
+    state: WatchdogState,
+    last_trigger: u64,
+    /// When a timeout last closed the Modbus connections (ms), 0 = never
+    pub sockets_closed_at: u64,
+    /// Output values applied on timeout by module ID and channel; unlisted channels go to 0
+    pub safe_values: HashMap<String, HashMap<u16, f64>>,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            time: 0,
+            function_mask: [0xFFFF, 0xFFFF],
+            trigger_value: 0,
+            min_trigger_time: 0,
+            stop_armed: false,
+            close_on_timeout: 0,
+            trigger_register_only: 0,
+            state: WatchdogState::Stopped,
+            last_trigger: 0,
+            sockets_closed_at: 0,
+            safe_values: HashMap::new(),
+        }
+    }
+}
+
+impl Watchdog {
+    /// Stopped watchdog whose timeout register powers up at `time` (100 ms units)
+    pub fn with_time(time: u16) -> Self {
+        Self { time, ..Self::default() }
+    }
+
+    pub fn state(&self) -> WatchdogState {
+        self.state
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.state == WatchdogState::Tripped
+    }
+
+    fn timeout_ms(&self) -> u64 {
+        self.time as u64 * 100
+    }
+
+    pub fn read(&self, addr: u16) -> u16 {
+        match addr {
+            0x1000 => self.time,
+            0x1001 => self.function_mask[0],
+            0x1002 => self.function_mask[1],
+            0x1003 => self.trigger_value,
+            0x1004 => self.min_trigger_time,
+            0x1006 => (self.state == WatchdogState::Running) as u16,
+            0x1009 => self.close_on_timeout,
+            0x100A => self.trigger_register_only,
+            // Stop and restart registers (0x1005, 0x1007, 0x1008) read as 0
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, value: u16, now: u64) {
+        if addr != 0x1005 {
+            self.stop_armed = false;
+        }
+        match addr {
+            0x1000 => {
+                self.time = value;
+                if value == 0 {
+                    self.stop();
+                } else {
+                    self.start(now);
+                }
+            }
+            0x1001 => self.function_mask[0] = value,
+            0x1002 => self.function_mask[1] = value,
+            0x1003 => {
+                self.trigger_value = value;
+                self.retrigger(now);
+            }
+            0x1005 => match value {
+                STOP_SEQUENCE_FIRST => self.stop_armed = true,
+                STOP_SEQUENCE_SECOND if self.stop_armed => self.stop(),
+                _ => self.stop_armed = false,
+            },
+            0x1007 if value == 1 && self.time != 0 => self.start(now),
+            0x1008 if SIMPLE_STOP_VALUES.contains(&value) => self.stop(),
+            0x1009 => self.close_on_timeout = value,
+            0x100A => self.trigger_register_only = value,
+            // 0x1004 and 0x1006 are read-only; other values are ignored
+            _ => {}
+        }
+    }
+
+    /// A request with `function` reached the coupler.
+    pub fn on_request(&mut self, function: u8, now: u64) {
+        if self.trigger_register_only != 0 || !(1..=32).contains(&function) {
+            return;
+        }
+        let bit = function as usize - 1;
+        if self.function_mask[bit / 16] & (1 << (bit % 16)) != 0 {
+            self.retrigger(now);
+        }
+    }
+
+    /// Trip the watchdog if it has not been triggered within its timeout.
+    /// Returns true when it trips.
+    pub fn poll(&mut self, now: u64) -> bool {
+        if self.state != WatchdogState::Running || now.saturating_sub(self.last_trigger) <= self.timeout_ms() {
+            return false;
+        }
+        self.state = WatchdogState::Tripped;
+        if self.close_on_timeout == 1 {
+            self.sockets_closed_at = now;
+        }
+        true
+    }
+
+    fn start(&mut self, now: u64) {
+        self.state = WatchdogState::Running;
+        self.last_trigger = now;
+        self.min_trigger_time = self.time;
+    }
+
+    fn stop(&mut self) {
+        self.state = WatchdogState::Stopped;
+        self.stop_armed = false;
+    }
+
+    fn retrigger(&mut self, now: u64) {
+        if self.state != WatchdogState::Running {
+            return;
+        }
+        let remaining = self.timeout_ms().saturating_sub(now.saturating_sub(self.last_trigger));
+        self.min_trigger_time = self.min_trigger_time.min((remaining / 100) as u16);
+        self.last_trigger = now;
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_in_100ms_units() {
+        let mut wd = Watchdog::default();
+        wd.write(0x1000, 5, 1_000);
+        assert_eq!(wd.read(0x1006), 1);
+        wd.on_request(0x03, 1_400);
+        assert!(!wd.poll(1_900));
+        assert_eq!(wd.read(0x1004), 1); // 100 ms were left at the trigger
+
+        assert!(wd.poll(1_901));
+        assert!(wd.is_tripped());
+        assert_eq!(wd.read(0x1006), 0);
+
+        // Requests do not recover a tripped watchdog; a restart does
+        wd.on_request(0x03, 2_000);
+        assert!(wd.is_tripped());
+        wd.write(0x1007, 1, 2_000);
+        assert_eq!(wd.state(), WatchdogState::Running);
+    }
+
+    #[test]
+    fn test_function_mask_and_trigger_register() {
+        let mut wd = Watchdog::default();
+        wd.write(0x1001, 1 << 2, 0); // Only FC03 retriggers
+        wd.write(0x1000, 10, 0);
+        wd.on_request(0x04, 900);
+        assert!(wd.poll(1_001));
+
+        let mut wd = Watchdog::default();
+        wd.write(0x100A, 1, 0);
+        wd.write(0x1000, 10, 0);
+        wd.on_request(0x03, 900);
+        wd.write(0x1003, 0x1234, 900);
+        assert!(!wd.poll(1_001));
+        assert_eq!(wd.read(0x1003), 0x1234);
+    }
+
+    #[test]
+    fn test_stop_sequences() {
+        let mut wd = Watchdog::default();
+        wd.write(0x1000, 10, 0);
+        wd.write(0x1005, 0x5555, 0);
+        assert_eq!(wd.state(), WatchdogState::Running);
+        wd.write(0x1005, 0xAAAA, 0);
+        wd.write(0x1005, 0x5555, 0);
+        assert_eq!(wd.state(), WatchdogState::Stopped);
+
+        wd.write(0x1007, 1, 0);
+        wd.write(0x1009, 1, 0);
+        assert!(wd.poll(5_000));
+        assert_eq!(wd.sockets_closed_at, 5_000);
+        wd.write(0x1008, 0xAA55, 5_000);
+        assert_eq!(wd.state(), WatchdogState::Stopped);
+        assert!(!wd.poll(10_000));
+    }
+
+    #[test]
+    fn test_simple_stop_values() {
+        for value in [0xAA55, 0x55AA] {
+            let mut wd = Watchdog::default();
+            wd.write(0x1000, 10, 0);
+            wd.write(0x1008, value, 0);
+            assert_eq!(wd.state(), WatchdogState::Stopped);
+        }
+
+        // The 0x1005 sequence values do not stop it at 0x1008
+        let mut wd = Watchdog::default();
+        wd.write(0x1000, 10, 0);
+        wd.write(0x1008, 0xAAAA, 0);
+        wd.write(0x1008, 0x5555, 0);
+        assert_eq!(wd.state(), WatchdogState::Running);
+    }
+}
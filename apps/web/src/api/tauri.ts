@@ -13,6 +13,7 @@ import type {
   ManualInfo,
   ValidationError,
   BehaviorDebug,
+  FaultType,
 } from '@wago/shared';
 import { mockInvoke } from '../mocks/tauriMock';
 
@@ -72,6 +73,10 @@ export const tauriApi = {
     return await invoke('set_channel_value', { moduleId, channel, value: numericValue });
   },
 
+  setChannelFault: async (moduleId: string, channel: number, fault: FaultType): Promise<void> => {
+    return await invoke('set_channel_fault', { moduleId, channel, fault });
+  },
+
   startSimulation: async (): Promise<void> => {
     return await invoke('start_simulation');
   },
@@ -100,6 +105,10 @@ export const tauriApi = {
     return await invoke('get_scenario_status');
   },
 
+  exportAddressMap: async (format: 'csv' | 'json' | 'markdown', path?: string): Promise<string> => {
+    return await invoke('export_address_map', { format, path });
+  },
+
   resetAllIO: async (): Promise<void> => {
     return await invoke('reset_all_io');
   },
@@ -4,8 +4,18 @@ import { useUIStore } from '@/stores/uiStore';
 import { useRackStore } from '@/stores/rackStore';
 import { useForceStore } from '@/stores/forceStore';
 import { MODULE_CATALOG, MODULE_TYPE_LABELS } from '@wago/shared';
+import type { FaultType } from '@wago/shared';
 import { formatAddress } from '@/utils/formatting';
 import { Zap } from 'lucide-react';
+
+const FAULT_OPTIONS: { value: Exclude<FaultType, null>; label: string }[] = [
+  { value: 'open-circuit', label: 'Wire break' },
+  { value: 'short-circuit', label: 'Short circuit' },
+  { value: 'over-range', label: 'Over range' },
+  { value: 'under-range', label: 'Under range' },
+  { value: 'sensor-break', label: 'Sensor break' },
+  { value: 'module-fault', label: 'Module fault' },
+];
 import { ReactiveDebugPanel } from '@/components/debug/ReactiveDebugPanel';
 
 function ModuleProperties() {
@@ -84,7 +94,7 @@ function ModuleProperties() {
 
 function ChannelOverride() {
   const { selectedModuleId, selectedChannel } = useUIStore();
-  const { getModule, getModuleState, setChannelValue, config } = useRackStore();
+  const { getModule, getModuleState, setChannelValue, setChannelFault, config } = useRackStore();
   const { isForced, getForcedValue, setForce, clearForce } = useForceStore();
 
   if (selectedModuleId === null || selectedChannel === null) {
@@ -290,13 +300,41 @@ function ChannelOverride() {
         </div>
       )}
 
+      {/* Fault injection for analog channels */}
+      {!isDigital && (
+        <div className="flex items-center justify-between">
+          <span className="text-sm text-panel-text">Fault</span>
+          <select
+            className="bg-panel-bg-hover text-panel-text text-xs rounded border border-panel-border px-2 py-1"
+            value={channelState.fault ?? ''}
+            onChange={(e) =>
+              setChannelFault(selectedModuleId, selectedChannel, (e.target.value || null) as FaultType)
+            }
+          >
+            <option value="">None</option>
+            {FAULT_OPTIONS.map((option) => (
+              <option key={option.value} value={option.value}>
+                {option.label}
+              </option>
+            ))}
+          </select>
+        </div>
+      )}
+
       {/* Raw value display */}
-      <div className="pt-2 border-t border-panel-border">
+      <div className="pt-2 border-t border-panel-border space-y-1">
         <ValueDisplay
           label="Raw"
           value={formatAddress(channelState.rawValue)}
           variant="muted"
         />
+        {!isDigital && (
+          <ValueDisplay
+            label="Status"
+            value={`0x${channelState.status.toString(16).toUpperCase().padStart(2, '0')}`}
+            variant={channelState.status ? 'highlight' : 'muted'}
+          />
+        )}
       </div>
     </div>
   );
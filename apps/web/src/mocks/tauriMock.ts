@@ -1,5 +1,5 @@
 // Mock Tauri invoke for Playwright/Browser environment
-import type { RackConfig, ModuleInstance, ModuleState } from '@wago/shared';
+import type { RackConfig, ModuleInstance, ModuleState, FaultConfig, FaultState } from '@wago/shared';
 import { MODULE_CATALOG } from '@wago/shared';
 
 // Simulated Backend State
@@ -7,6 +7,16 @@ let mockConfig: RackConfig | null = null;
 let mockModuleStates: ModuleState[] = [];
 let mockSimulationState = 'stopped';
 let moduleIdCounter = 0;
+const NO_FAULTS: FaultConfig = {
+  latencyMs: 0,
+  jitterMs: 0,
+  dropRate: 0,
+  exceptionRate: 0,
+  exceptionCode: 4,
+  closeRate: 0,
+  corruptTransactionIdRate: 0,
+};
+let mockFaults: FaultState = { global: { ...NO_FAULTS }, clients: {} };
 const mockModbusClients: { id: string; address: string; connectedAt: number; lastActivity: number; requestCount: number }[] = [];
 
 const createDefaultModuleState = (module: ModuleInstance): ModuleState => {
@@ -43,7 +53,7 @@ export const mockInvoke = async (cmd: string, args: any = {}): Promise<any> => {
         mockConfig,
         mockModuleStates,
         mockSimulationState,
-        { modbusClients: mockModbusClients, lastActivity: Date.now() },
+        { modbusClients: mockModbusClients, lastActivity: Date.now(), listenAddress: null, listenAddresses: [], serverStatus: { state: 'stopped' }, watchdogTrippedRacks: [] },
       ];
 
     case 'create_rack':
@@ -107,6 +117,15 @@ export const mockInvoke = async (cmd: string, args: any = {}): Promise<any> => {
         return;
     }
 
+    case 'set_channel_fault': {
+        const modState = mockModuleStates.find(m => m.id === args.moduleId);
+        const ch = modState?.channels.find(c => c.channel === args.channel);
+        if (ch) {
+            ch.fault = args.fault;
+        }
+        return;
+    }
+
     case 'start_simulation':
       mockSimulationState = 'running';
       return;
@@ -118,6 +137,19 @@ export const mockInvoke = async (cmd: string, args: any = {}): Promise<any> => {
     case 'save_config':
       return;
 
+    case 'get_faults':
+      return { global: mockFaults.global, clients: { ...mockFaults.clients } };
+
+    case 'set_faults':
+      if (args.client) mockFaults.clients[args.client] = args.config;
+      else mockFaults.global = args.config;
+      return;
+
+    case 'clear_faults':
+      if (args.client) delete mockFaults.clients[args.client];
+      else mockFaults = { global: { ...NO_FAULTS }, clients: {} };
+      return;
+
     case 'list_scenarios':
       return ['Contactor Simulation', 'Pump Failure', 'Network Stress Test'];
 
@@ -5,6 +5,7 @@ import type {
   ModuleState,
   SimulationState,
   ConnectionState,
+  FaultType,
 } from '@wago/shared';
 import { tauriApi } from '../api/tauri';
 import { useConnectionStore } from './connectionStore';
@@ -34,6 +35,7 @@ interface RackStore {
   removeModule: (moduleId: string) => Promise<void>;
   
   setChannelValue: (moduleId: string, channel: number, value: number | boolean) => Promise<void>;
+  setChannelFault: (moduleId: string, channel: number, fault: FaultType) => Promise<void>;
   
   startSimulation: () => Promise<void>;
   stopSimulation: () => Promise<void>;
@@ -53,6 +55,10 @@ export const useRackStore = create<RackStore>((set, get) => ({
   connectionState: {
     modbusClients: [],
     lastActivity: 0,
+    listenAddress: null,
+    listenAddresses: [],
+    watchdogTrippedRacks: [],
+    serverStatus: { state: 'stopped' },
   },
 
   init: () => {
@@ -156,6 +162,10 @@ export const useRackStore = create<RackStore>((set, get) => ({
     await tauriApi.setChannelValue(moduleId, channel, value);
   },
 
+  setChannelFault: async (moduleId, channel, fault) => {
+    await tauriApi.setChannelFault(moduleId, channel, fault);
+  },
+
   startSimulation: async () => {
     await tauriApi.startSimulation();
   },
@@ -162,6 +162,84 @@ export const MODULE_CATALOG: Record<string, ModuleDefinition> = {
     processImageOutputSize: 0,
     hasStatus: false,
   },
+  '750-456': {
+    moduleNumber: '750-456',
+    name: '2-AI ±10V',
+    type: 'analog-input',
+    channels: 2,
+    width: 1,
+    color: '#9333ea',
+    description: '2-Channel Analog Input, ±10V, Differential',
+    bitsPerChannel: 16,
+    processImageInputSize: 4,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-459': {
+    moduleNumber: '750-459',
+    name: '4-AI 0-10V',
+    type: 'analog-input',
+    channels: 4,
+    width: 1,
+    color: '#9333ea',
+    description: '4-Channel Analog Input, 0-10V, Single-ended',
+    bitsPerChannel: 16,
+    processImageInputSize: 8,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-466': {
+    moduleNumber: '750-466',
+    name: '2-AI 4-20mA',
+    type: 'analog-input',
+    channels: 2,
+    width: 1,
+    color: '#9333ea',
+    description: '2-Channel Analog Input, 4-20mA, Single-ended',
+    bitsPerChannel: 16,
+    processImageInputSize: 4,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-467': {
+    moduleNumber: '750-467',
+    name: '4-AI 0-10V',
+    type: 'analog-input',
+    channels: 4,
+    width: 1,
+    color: '#9333ea',
+    description: '4-Channel Analog Input, 0-10V, Single-ended',
+    bitsPerChannel: 16,
+    processImageInputSize: 8,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-468': {
+    moduleNumber: '750-468',
+    name: '4-AI 0-10V',
+    type: 'analog-input',
+    channels: 4,
+    width: 1,
+    color: '#9333ea',
+    description: '4-Channel Analog Input, 0-10V, Single-ended',
+    bitsPerChannel: 16,
+    processImageInputSize: 8,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-476': {
+    moduleNumber: '750-476',
+    name: '2-AI ±10V',
+    type: 'analog-input',
+    channels: 2,
+    width: 1,
+    color: '#9333ea',
+    description: '2-Channel Analog Input, ±10V, Single-ended',
+    bitsPerChannel: 16,
+    processImageInputSize: 4,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
 
   // Analog Output Modules
   '750-563': {
@@ -219,12 +297,40 @@ export const MODULE_CATALOG: Record<string, ModuleDefinition> = {
     hasStatus: false,
   },
 
+  // Thermocouple Input Modules
+  '750-469': {
+    moduleNumber: '750-469',
+    name: '2-TC',
+    type: 'rtd-input',
+    channels: 2,
+    width: 1,
+    color: '#16a34a',
+    description: '2-Channel Thermocouple Input, Cold Junction Compensated',
+    bitsPerChannel: 16,
+    processImageInputSize: 4,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+  '750-458': {
+    moduleNumber: '750-458',
+    name: '8-TC',
+    type: 'rtd-input',
+    channels: 8,
+    width: 1,
+    color: '#16a34a',
+    description: '8-Channel Thermocouple Input, Cold Junction Compensated',
+    bitsPerChannel: 16,
+    processImageInputSize: 16,
+    processImageOutputSize: 0,
+    hasStatus: false,
+  },
+
   // Special/Counter Modules
   '750-404': {
     moduleNumber: '750-404',
     name: 'Up/Down Counter',
     type: 'special',
-    channels: 1, // Logical channels?
+    channels: 1, // Count; CLOCK, U/D, O1 and O2 are status bits
     width: 1,
     color: '#14b8a6',
     description: 'Up/Down Counter, 100kHz, 32-bit',
@@ -246,6 +352,34 @@ export const MODULE_CATALOG: Record<string, ModuleDefinition> = {
     processImageOutputSize: 6,
     hasStatus: true,
   },
+
+  // PWM and Pulse Train Output Modules
+  '750-511': {
+    moduleNumber: '750-511',
+    name: '2-PWM 24VDC',
+    type: 'special',
+    channels: 2, // Duty cycles; the periods follow as channels 2-3
+    width: 1,
+    color: '#14b8a6',
+    description: '2-Channel PWM Output, 24V DC, 250 Hz',
+    bitsPerChannel: 16, // + status/control
+    processImageInputSize: 8,
+    processImageOutputSize: 8,
+    hasStatus: true,
+  },
+  '750-673': {
+    moduleNumber: '750-673',
+    name: 'Pulse Train Output',
+    type: 'special',
+    channels: 1,
+    width: 1,
+    color: '#14b8a6',
+    description: 'Pulse Train Output, 24V DC',
+    bitsPerChannel: 16,
+    processImageInputSize: 4,
+    processImageOutputSize: 4,
+    hasStatus: true,
+  },
 } as const;
 
 /**
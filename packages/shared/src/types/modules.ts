@@ -19,6 +19,7 @@ export type FaultType =
   | 'over-range'
   | 'under-range'
   | 'sensor-break'
+  | 'module-fault'
   | null;
 
 /**
@@ -88,4 +89,24 @@ export interface ModuleInstance {
   moduleNumber: string;
   slotPosition: number;
   label?: string;
+  /** Signals wired to the channels */
+  channels?: ChannelDefinition[];
+  /** Module parameters, e.g. scaling overrides (`scale`, `offset`, `min_value`, `max_value`, `units`) */
+  moduleConfig?: Record<string, string>;
+}
+
+/**
+ * Signal wired to a module channel, with optional scaling overrides for analog channels
+ */
+export interface ChannelDefinition {
+  ch: number;
+  signal: {
+    name: string;
+    type: string;
+    scale?: number;
+    offset?: number;
+    min_value?: number;
+    max_value?: number;
+    units?: string;
+  };
 }
@@ -8,6 +8,19 @@ export interface CouplerConfig {
   ipAddress: string;
   modbusPort: number;
   unitId: number;
+  metadata?: CouplerMetadata;
+}
+
+/**
+ * Coupler information register contents; unset fields use the simulator defaults
+ */
+export interface CouplerMetadata {
+  macAddress?: string;
+  subnetMask?: string;
+  gateway?: string;
+  firmwareVersion?: string;
+  firmwareDate?: string;
+  firmwareTime?: string;
 }
 
 /**
@@ -51,6 +64,15 @@ export interface ModbusClientInfo {
   requestCount: number;
 }
 
+/**
+ * Lifecycle status of the backend Modbus server
+ */
+export type ServerStatus =
+  | { state: 'stopped' }
+  | { state: 'binding'; address: string }
+  | { state: 'listening'; address: string }
+  | { state: 'failed'; reason: string };
+
 /**
  * Connection state from the backend
  * This represents the actual Modbus TCP connection state
@@ -58,6 +80,37 @@ export interface ModbusClientInfo {
 export interface ConnectionState {
   modbusClients: ModbusClientInfo[];
   lastActivity: number;
+  /** Address the Modbus server is bound to (null when not listening) */
+  listenAddress: string | null;
+  /** Every bound listener when racks are served on separate endpoints */
+  listenAddresses: string[];
+  serverStatus: ServerStatus;
+  /** IDs of racks whose watchdog timed out (outputs held at safe values) */
+  watchdogTrippedRacks: string[];
+}
+
+/**
+ * Wire-level faults injected into Modbus responses.
+ * Rates are probabilities from 0 to 1.
+ */
+export interface FaultConfig {
+  latencyMs: number;
+  /** Random extra delay of up to this many milliseconds */
+  jitterMs: number;
+  dropRate: number;
+  exceptionRate: number;
+  /** Exception code sent instead of the response (default 0x04) */
+  exceptionCode: number;
+  closeRate: number;
+  corruptTransactionIdRate: number;
+}
+
+/**
+ * Global faults and per-client overrides keyed by client IP
+ */
+export interface FaultState {
+  global: FaultConfig;
+  clients: Record<string, FaultConfig>;
 }
 
 /**
@@ -5,12 +5,16 @@
  * continuous I/O relationships with ownership tracking.
  */
 
+import type { FaultType } from './modules.js';
+
 /**
  * Reference to a specific channel on a module
  */
 export interface ChannelRef {
   modulePosition: number;
   channel: number;
+  /** Rack the position is relative to (resolved by the backend on load) */
+  rack?: string;
 }
 
 /**
@@ -36,6 +40,8 @@ export interface ReactiveBehavior {
   enabled: boolean;
   /** Constant value (for constant mapping) */
   value?: number;
+  /** Fault injected on the target while the mapped value is on */
+  fault?: FaultType;
 }
 
 /**